@@ -3,8 +3,27 @@ use std::alloc::Layout;
 pub mod host;
 
 pub trait Allocator: Default + Clone {
+    /// Allocates memory for `layout`.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must have a nonzero size.
     unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Frees memory from [`alloc`](Self::alloc).
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `alloc` of this allocator for the
+    /// same `layout`, and must not be used afterwards.
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+
+    /// Copies `size` bytes from `src` to `dst`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid to read and `dst` valid to write for `size` bytes,
+    /// and the two ranges must not overlap.
     unsafe fn copy(&self, src: *const u8, dst: *mut u8, size: usize);
 }
 
@@ -0,0 +1,452 @@
+//! Host implementations of the BLAS kernels.
+//!
+//! The level-1 kernels are plain loops over the increments, written so that
+//! unit increments vectorise.
+//!
+//! `gemm` follows the usual Goto/BLIS scheme: `B` is packed into `KC x NC`
+//! blocks and `A` into `MC x KC` blocks that stay in cache, both split into
+//! panels of `NR` columns and `MR` rows, and an `MR x NR` register tile of
+//! `C` is accumulated per pair of panels. Packing reads the operands through
+//! their strides, so transposed or otherwise strided inputs need no copy.
+
+use num_traits::{Float, Signed};
+
+use crate::{
+    backend::{BlasOps, CpuBackend},
+    elem::Elem,
+};
+
+const MR: usize = 4;
+const NR: usize = 8;
+const MC: usize = 64;
+const KC: usize = 256;
+const NC: usize = 2048;
+
+/// Packs the `mc x kc` block of `A` at `a` into row panels of height `MR`,
+/// each stored column by column and padded with zeros.
+unsafe fn pack_a<T: Elem>(mc: usize, kc: usize, a: *const T, [rs, cs]: [isize; 2], out: &mut [T]) {
+    for (panel, ir) in (0..mc).step_by(MR).enumerate() {
+        let rows = MR.min(mc - ir);
+        let out = &mut out[panel * MR * kc..(panel + 1) * MR * kc];
+        for p in 0..kc {
+            for i in 0..MR {
+                out[p * MR + i] = if i < rows {
+                    *a.wrapping_offset((ir + i) as isize * rs + p as isize * cs)
+                } else {
+                    T::zero()
+                };
+            }
+        }
+    }
+}
+
+/// Packs the `kc x nc` block of `B` at `b` into column panels of width `NR`,
+/// each stored row by row and padded with zeros.
+unsafe fn pack_b<T: Elem>(kc: usize, nc: usize, b: *const T, [rs, cs]: [isize; 2], out: &mut [T]) {
+    for (panel, jr) in (0..nc).step_by(NR).enumerate() {
+        let cols = NR.min(nc - jr);
+        let out = &mut out[panel * NR * kc..(panel + 1) * NR * kc];
+        for p in 0..kc {
+            for j in 0..NR {
+                out[p * NR + j] = if j < cols {
+                    *b.wrapping_offset(p as isize * rs + (jr + j) as isize * cs)
+                } else {
+                    T::zero()
+                };
+            }
+        }
+    }
+}
+
+/// Adds `alpha` times the product of a packed row panel of `A` and a packed
+/// column panel of `B` to the `mr x nr` tile of `C` at `c`.
+#[allow(clippy::too_many_arguments)]
+unsafe fn kernel<T: Elem>(
+    kc: usize,
+    alpha: T,
+    a: &[T],
+    b: &[T],
+    mr: usize,
+    nr: usize,
+    c: *mut T,
+    [rs, cs]: [isize; 2],
+) {
+    let mut acc = [[T::zero(); NR]; MR];
+    for (a, b) in a.chunks_exact(MR).zip(b.chunks_exact(NR)).take(kc) {
+        for i in 0..MR {
+            for j in 0..NR {
+                acc[i][j] = acc[i][j] + a[i] * b[j];
+            }
+        }
+    }
+    for (i, row) in acc.iter().enumerate().take(mr) {
+        for (j, &x) in row.iter().enumerate().take(nr) {
+            let c = c.wrapping_offset(i as isize * rs + j as isize * cs);
+            *c = *c + alpha * x;
+        }
+    }
+}
+
+impl BlasOps for CpuBackend {
+    unsafe fn axpy<T: Elem>(&self, n: usize, alpha: T, x: *const T, incx: isize, y: *mut T, incy: isize) {
+        if incx == 1 && incy == 1 {
+            let x = std::slice::from_raw_parts(x, n);
+            let y = std::slice::from_raw_parts_mut(y, n);
+            for (y, &x) in y.iter_mut().zip(x) {
+                *y = *y + alpha * x;
+            }
+        } else {
+            for i in 0..n as isize {
+                let y = y.wrapping_offset(i * incy);
+                *y = *y + alpha * *x.wrapping_offset(i * incx);
+            }
+        }
+    }
+
+    unsafe fn dot<T: Elem>(&self, n: usize, x: *const T, incx: isize, y: *const T, incy: isize) -> T {
+        if incx == 1 && incy == 1 {
+            let x = std::slice::from_raw_parts(x, n);
+            let y = std::slice::from_raw_parts(y, n);
+            x.iter().zip(y).fold(T::zero(), |acc, (&x, &y)| acc + x * y)
+        } else {
+            (0..n as isize).fold(T::zero(), |acc, i| {
+                acc + *x.wrapping_offset(i * incx) * *y.wrapping_offset(i * incy)
+            })
+        }
+    }
+
+    unsafe fn scal<T: Elem>(&self, n: usize, alpha: T, x: *mut T, incx: isize) {
+        for i in 0..n as isize {
+            let x = x.wrapping_offset(i * incx);
+            *x = alpha * *x;
+        }
+    }
+
+    unsafe fn nrm2<T: Elem + Float>(&self, n: usize, x: *const T, incx: isize) -> T {
+        // Keep the sum of squares relative to the largest magnitude seen so
+        // far, as the reference BLAS does.
+        let mut scale = T::zero();
+        let mut ssq = T::one();
+        for i in 0..n as isize {
+            let x = *x.wrapping_offset(i * incx);
+            if x.is_nan() {
+                return x;
+            }
+            if x.is_zero() {
+                continue;
+            }
+            let abs = x.abs();
+            if scale < abs {
+                ssq = T::one() + ssq * (scale / abs) * (scale / abs);
+                scale = abs;
+            } else {
+                ssq = ssq + (abs / scale) * (abs / scale);
+            }
+        }
+        scale * ssq.sqrt()
+    }
+
+    unsafe fn asum<T: Elem + Signed>(&self, n: usize, x: *const T, incx: isize) -> T {
+        (0..n as isize).fold(T::zero(), |acc, i| acc + (*x.wrapping_offset(i * incx)).abs())
+    }
+
+    unsafe fn gemm<T: Elem>(
+        &self,
+        m: usize,
+        k: usize,
+        n: usize,
+        alpha: T,
+        a: *const T,
+        [rsa, csa]: [isize; 2],
+        b: *const T,
+        [rsb, csb]: [isize; 2],
+        beta: T,
+        c: *mut T,
+        [rsc, csc]: [isize; 2],
+    ) {
+        if m == 0 || n == 0 {
+            return;
+        }
+
+        if beta != T::one() {
+            for i in 0..m {
+                for j in 0..n {
+                    let c = c.wrapping_offset(i as isize * rsc + j as isize * csc);
+                    *c = if beta.is_zero() { T::zero() } else { beta * *c };
+                }
+            }
+        }
+        if k == 0 || alpha.is_zero() {
+            return;
+        }
+
+        let round_up = |x: usize, to: usize| x.div_ceil(to) * to;
+        let mut a_pack = vec![T::zero(); round_up(m.min(MC), MR) * k.min(KC)];
+        let mut b_pack = vec![T::zero(); round_up(n.min(NC), NR) * k.min(KC)];
+        for jc in (0..n).step_by(NC) {
+            let nc = NC.min(n - jc);
+            for pc in (0..k).step_by(KC) {
+                let kc = KC.min(k - pc);
+                pack_b(
+                    kc,
+                    nc,
+                    b.wrapping_offset(pc as isize * rsb + jc as isize * csb),
+                    [rsb, csb],
+                    &mut b_pack,
+                );
+                for ic in (0..m).step_by(MC) {
+                    let mc = MC.min(m - ic);
+                    pack_a(
+                        mc,
+                        kc,
+                        a.wrapping_offset(ic as isize * rsa + pc as isize * csa),
+                        [rsa, csa],
+                        &mut a_pack,
+                    );
+                    for jr in (0..nc).step_by(NR) {
+                        for ir in (0..mc).step_by(MR) {
+                            kernel(
+                                kc,
+                                alpha,
+                                &a_pack[ir * kc..],
+                                &b_pack[jr * kc..],
+                                MR.min(mc - ir),
+                                NR.min(nc - jr),
+                                c.wrapping_offset(
+                                    (ic + ir) as isize * rsc + (jc + jr) as isize * csc,
+                                ),
+                                [rsc, csc],
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_close;
+
+    /// `alpha * A * B + beta * C` with one loop per index, reading each
+    /// matrix through its strides from the given offset within its buffer.
+    #[allow(clippy::too_many_arguments)]
+    fn reference(
+        [m, k, n]: [usize; 3],
+        alpha: f64,
+        (a, a_off, [rsa, csa]): (&[f64], isize, [isize; 2]),
+        (b, b_off, [rsb, csb]): (&[f64], isize, [isize; 2]),
+        beta: f64,
+        (c, c_off, [rsc, csc]): (&mut [f64], isize, [isize; 2]),
+    ) {
+        for i in 0..m as isize {
+            for j in 0..n as isize {
+                let mut acc = 0.0;
+                for p in 0..k as isize {
+                    acc += a[(a_off + i * rsa + p * csa) as usize] * b[(b_off + p * rsb + j * csb) as usize];
+                }
+                let c = &mut c[(c_off + i * rsc + j * csc) as usize];
+                *c = alpha * acc + if beta == 0.0 { 0.0 } else { beta * *c };
+            }
+        }
+    }
+
+    /// Integer values, so that every sum is exact whatever its order.
+    fn values(len: usize, salt: usize) -> Vec<f64> {
+        (0..len).map(|i| ((i * 37 + salt) % 11) as f64 - 5.0).collect()
+    }
+
+    /// Runs `gemm` and the reference on the same inputs for each layout of
+    /// the operands: row-major, column-major, and reversed rows.
+    fn check(m: usize, k: usize, n: usize, alpha: f64, beta: f64) {
+        let a = values(m * k, 1);
+        let b = values(k * n, 2);
+        let layouts = |rows: usize, cols: usize| {
+            let (r, c) = (rows as isize, cols as isize);
+            [(0, [c, 1]), (0, [1, r]), ((r - 1) * c, [-c, 1])]
+        };
+        for (a_off, a_strides) in layouts(m, k) {
+            for (b_off, b_strides) in layouts(k, n) {
+                for (c_off, c_strides) in layouts(m, n) {
+                    let mut expected = values(m * n, 3);
+                    let mut actual = expected.clone();
+                    reference(
+                        [m, k, n],
+                        alpha,
+                        (&a, a_off, a_strides),
+                        (&b, b_off, b_strides),
+                        beta,
+                        (&mut expected, c_off, c_strides),
+                    );
+                    unsafe {
+                        CpuBackend.gemm(
+                            m,
+                            k,
+                            n,
+                            alpha,
+                            a.as_ptr().offset(a_off),
+                            a_strides,
+                            b.as_ptr().offset(b_off),
+                            b_strides,
+                            beta,
+                            actual.as_mut_ptr().offset(c_off),
+                            c_strides,
+                        );
+                    }
+                    assert_eq!(actual, expected, "{m}x{k}x{n} {a_strides:?} {b_strides:?} {c_strides:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gemm_matches_reference() {
+        for (m, k, n) in [(1, 1, 1), (3, 5, 2), (4, 8, 8), (5, 9, 17), (7, 1, 3), (1, 13, 1)] {
+            for (alpha, beta) in [(1.0, 0.0), (2.0, 1.0), (-1.0, 3.0)] {
+                check(m, k, n, alpha, beta);
+            }
+        }
+    }
+
+    #[test]
+    fn gemm_across_cache_blocks() {
+        // More rows than MC, a longer inner dimension than KC and more
+        // columns than NC, each with a partial last block.
+        check(MC + 6, 2, 3, 1.0, 0.0);
+        check(3, KC + 5, 2, 1.0, 1.0);
+        check(2, 3, NC + 9, 2.0, 0.0);
+    }
+
+    #[test]
+    fn gemm_edge_cases() {
+        // With beta zero, C is overwritten without being read.
+        let a = [1.0, 2.0];
+        let b = [3.0, 4.0];
+        let mut c = [f64::NAN];
+        unsafe {
+            CpuBackend.gemm(1, 2, 1, 1.0, a.as_ptr(), [2, 1], b.as_ptr(), [1, 1], 0.0, c.as_mut_ptr(), [1, 1]);
+        }
+        assert_eq!(c, [11.0]);
+
+        // An empty inner dimension scales C by beta.
+        let mut c = [1.0, 2.0];
+        unsafe {
+            CpuBackend.gemm(1, 0, 2, 1.0, a.as_ptr(), [0, 1], b.as_ptr(), [2, 1], 3.0, c.as_mut_ptr(), [2, 1]);
+        }
+        assert_eq!(c, [3.0, 6.0]);
+
+        // Zero strides broadcast a row of A over every row of C.
+        let identity = [1.0, 0.0, 0.0, 1.0];
+        let mut c = [0.0; 4];
+        unsafe {
+            CpuBackend.gemm(2, 2, 2, 1.0, a.as_ptr(), [0, 1], identity.as_ptr(), [2, 1], 0.0, c.as_mut_ptr(), [2, 1]);
+        }
+        assert_eq!(c, [1.0, 2.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn gemm_of_integers() {
+        let a: Vec<i64> = (0..6).collect();
+        let b: Vec<i64> = (0..6).map(|x| x - 2).collect();
+        let mut c = [1; 4];
+        unsafe {
+            CpuBackend.gemm(2, 3, 2, 1, a.as_ptr(), [3, 1], b.as_ptr(), [2, 1], 1, c.as_mut_ptr(), [2, 1]);
+        }
+        assert_eq!(c, [5, 8, 5, 17]);
+    }
+
+    /// The vector of `n` elements at `offset` in `buffer` with increment
+    /// `inc`, and the pointer to its first element.
+    fn strided(buffer: &[f64], offset: isize, n: usize, inc: isize) -> (Vec<f64>, *const f64) {
+        let elems = (0..n as isize).map(|i| buffer[(offset + i * inc) as usize]).collect();
+        (elems, buffer.as_ptr().wrapping_offset(offset))
+    }
+
+    /// Vectors of length 5 within a buffer of 16: contiguous, strided,
+    /// reversed and broadcast.
+    const VECTORS: [(isize, isize); 4] = [(0, 1), (1, 3), (14, -2), (7, 0)];
+
+    #[test]
+    fn level1_matches_reference() {
+        let (x_buf, y_buf) = (values(16, 4), values(16, 5));
+        for (x_off, incx) in VECTORS {
+            let (x, x_ptr) = strided(&x_buf, x_off, 5, incx);
+            let abs_sum: f64 = x.iter().map(|x| x.abs()).sum();
+            let norm = x.iter().map(|x| x * x).sum::<f64>().sqrt();
+            unsafe {
+                assert_eq!(CpuBackend.asum(5, x_ptr, incx), abs_sum);
+                assert_close(&[CpuBackend.nrm2(5, x_ptr, incx)], &[norm]);
+            }
+
+            for (y_off, incy) in VECTORS {
+                let (y, y_ptr) = strided(&y_buf, y_off, 5, incy);
+                let inner: f64 = x.iter().zip(&y).map(|(x, y)| x * y).sum();
+                assert_eq!(unsafe { CpuBackend.dot(5, x_ptr, incx, y_ptr, incy) }, inner);
+                if incy == 0 {
+                    continue;
+                }
+
+                let mut expected = y_buf.clone();
+                for i in 0..5 {
+                    expected[(y_off + i * incy) as usize] += -2.0 * x[i as usize];
+                }
+                let mut actual = y_buf.clone();
+                unsafe {
+                    CpuBackend.axpy(5, -2.0, x_ptr, incx, actual.as_mut_ptr().offset(y_off), incy);
+                }
+                assert_eq!(actual, expected, "{x_off} {incx} {y_off} {incy}");
+            }
+
+            if incx != 0 {
+                let mut expected = x_buf.clone();
+                for i in 0..5 {
+                    expected[(x_off + i * incx) as usize] *= 3.0;
+                }
+                let mut actual = x_buf.clone();
+                unsafe { CpuBackend.scal(5, 3.0, actual.as_mut_ptr().offset(x_off), incx) };
+                assert_eq!(actual, expected, "{x_off} {incx}");
+            }
+        }
+    }
+
+    #[test]
+    fn level1_of_empty_vectors() {
+        let mut y = [1.0; 2];
+        unsafe {
+            assert_eq!(CpuBackend.dot(0, y.as_ptr(), 1, y.as_ptr(), 1), 0.0);
+            assert_eq!(CpuBackend.asum(0, y.as_ptr(), 1), 0.0);
+            assert_eq!(CpuBackend.nrm2(0, y.as_ptr(), 1), 0.0);
+            CpuBackend.axpy(0, 2.0, y.as_ptr(), 1, y.as_mut_ptr(), 1);
+            CpuBackend.scal(0, 2.0, y.as_mut_ptr(), 1);
+        }
+        assert_eq!(y, [1.0; 2]);
+    }
+
+    #[test]
+    fn nrm2_neither_overflows_nor_underflows() {
+        for scale in [1e300, 1e-300] {
+            let x = [3.0 * scale, 0.0, -4.0 * scale];
+            let norm = unsafe { CpuBackend.nrm2(3, x.as_ptr(), 1) };
+            assert_close(&[norm / scale], &[5.0]);
+        }
+        let x = [1.0, f64::NAN, f64::INFINITY];
+        assert!(unsafe { CpuBackend.nrm2(3, x.as_ptr(), 1) }.is_nan());
+        let x = [1.0f32, -1.0, 1.0, -1.0];
+        assert_eq!(unsafe { CpuBackend.nrm2(4, x.as_ptr(), 1) }, 2.0);
+    }
+
+    #[test]
+    fn level1_of_integers() {
+        let x: [i32; 4] = [1, -2, 3, -4];
+        let mut y = [10; 4];
+        unsafe {
+            assert_eq!(CpuBackend.asum(4, x.as_ptr(), 1), 10);
+            assert_eq!(CpuBackend.dot(2, x.as_ptr(), 2, x.as_ptr().add(1), 2), -14);
+            CpuBackend.axpy(4, 3, x.as_ptr().add(3), -1, y.as_mut_ptr(), 1);
+            CpuBackend.scal(2, -1, y.as_mut_ptr().add(1), 2);
+        }
+        assert_eq!(y, [-2, -19, 4, -13]);
+    }
+}
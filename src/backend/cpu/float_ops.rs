@@ -0,0 +1,63 @@
+//! Host implementations of the element-wise float kernels.
+//!
+//! The operation is matched once per call rather than per element, so each
+//! lane runs a loop specialised to a single function, and unit-stride lanes
+//! vectorise where the function allows it.
+
+use num_traits::Float;
+
+use crate::{
+    backend::{CpuBackend, FloatOps, UnaryOp},
+    elem::Elem,
+    index::Ix,
+    strided,
+};
+
+/// Writes `f` applied to each element of `x` into `y`, lane by lane.
+unsafe fn map_lanes<T, F>(dims: &[Ix], x: *const T, x_strides: &[isize], y: *mut T, y_strides: &[isize], f: F)
+where
+    T: Copy,
+    F: Fn(T) -> T,
+{
+    strided::lanes2(dims, x as *mut T, x_strides, y, y_strides, |x, y, n, incx, incy| {
+        if incx == 1 && incy == 1 {
+            for i in 0..n {
+                *y.add(i) = f(*x.add(i));
+            }
+        } else {
+            for i in 0..n as isize {
+                *y.offset(i * incy) = f(*x.offset(i * incx));
+            }
+        }
+    });
+}
+
+impl FloatOps for CpuBackend {
+    unsafe fn unary<T: Elem + Float>(
+        &self,
+        op: UnaryOp<T>,
+        dims: &[Ix],
+        src: *const T,
+        src_strides: &[isize],
+        dst: *mut T,
+        dst_strides: &[isize],
+    ) {
+        macro_rules! dispatch {
+            ($($variant:ident),+; $($param:ident),+) => {
+                match op {
+                    $(UnaryOp::$variant => {
+                        map_lanes(dims, src, src_strides, dst, dst_strides, |x| UnaryOp::$variant.apply(x))
+                    },)+
+                    $(UnaryOp::$param(n) => {
+                        map_lanes(dims, src, src_strides, dst, dst_strides, |x| UnaryOp::$param(n).apply(x))
+                    },)+
+                }
+            };
+        }
+        dispatch!(
+            Exp, Exp2, Ln, Log2, Log10, Sqrt, Rsqrt, Sin, Cos, Tan, Tanh, Sigmoid, Abs, Floor, Ceil, Round,
+            Relu, Gelu, Silu;
+            Powf, Powi
+        );
+    }
+}
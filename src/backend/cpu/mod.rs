@@ -1,2 +1,5 @@
 pub mod allocator;
+pub mod blas_ops;
+pub mod float_ops;
 pub mod mem_ops;
+pub mod nn_ops;
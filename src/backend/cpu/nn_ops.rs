@@ -0,0 +1,120 @@
+//! Host implementations of the fused neural-network kernels.
+//!
+//! `softmax` finds the maximum and the sum of exponentials together in one
+//! pass, rescaling the running sum whenever the maximum grows, and writes
+//! the output in a second pass. The norms likewise gather their statistics
+//! in one pass, the mean and variance with Welford's update, before writing.
+
+use num_traits::Float;
+
+use crate::{
+    backend::{CpuBackend, NnOps},
+    elem::Elem,
+};
+
+/// Scales and shifts `y`, the normalised `i`-th element of a lane.
+#[inline]
+unsafe fn affine<T: Float>(y: T, i: isize, weight: Option<(*const T, isize)>, bias: Option<(*const T, isize)>) -> T {
+    let y = match weight {
+        Some((w, incw)) => y * *w.offset(i * incw),
+        None => y,
+    };
+    match bias {
+        Some((b, incb)) => y + *b.offset(i * incb),
+        None => y,
+    }
+}
+
+/// Writes `(x - shift) * scale`, followed by the affine transform, into `y`.
+#[allow(clippy::too_many_arguments)]
+unsafe fn normalize<T: Float>(
+    n: usize,
+    x: *const T,
+    incx: isize,
+    shift: T,
+    scale: T,
+    weight: Option<(*const T, isize)>,
+    bias: Option<(*const T, isize)>,
+    y: *mut T,
+    incy: isize,
+) {
+    for i in 0..n as isize {
+        *y.offset(i * incy) = affine((*x.offset(i * incx) - shift) * scale, i, weight, bias);
+    }
+}
+
+impl NnOps for CpuBackend {
+    unsafe fn softmax<T: Elem + Float>(&self, n: usize, x: *const T, incx: isize, y: *mut T, incy: isize, log: bool) {
+        let mut max = T::neg_infinity();
+        let mut sum = T::zero();
+        for i in 0..n as isize {
+            let v = *x.offset(i * incx);
+            if v > max {
+                sum = sum * (max - v).exp() + T::one();
+                max = v;
+            } else {
+                sum = sum + (v - max).exp();
+            }
+        }
+        if log {
+            let lse = max + sum.ln();
+            for i in 0..n as isize {
+                *y.offset(i * incy) = *x.offset(i * incx) - lse;
+            }
+        } else {
+            let inv = sum.recip();
+            for i in 0..n as isize {
+                *y.offset(i * incy) = (*x.offset(i * incx) - max).exp() * inv;
+            }
+        }
+    }
+
+    unsafe fn layer_norm<T: Elem + Float>(
+        &self,
+        n: usize,
+        x: *const T,
+        incx: isize,
+        weight: Option<(*const T, isize)>,
+        bias: Option<(*const T, isize)>,
+        eps: T,
+        y: *mut T,
+        incy: isize,
+    ) {
+        if n == 0 {
+            return;
+        }
+        let mut mean = T::zero();
+        let mut m2 = T::zero();
+        for i in 0..n {
+            let v = *x.offset(i as isize * incx);
+            let d = v - mean;
+            mean = mean + d / T::from(i + 1).unwrap();
+            m2 = m2 + d * (v - mean);
+        }
+        let var = m2 / T::from(n).unwrap();
+        normalize(n, x, incx, mean, (var + eps).sqrt().recip(), weight, bias, y, incy);
+    }
+
+    unsafe fn rms_norm<T: Elem + Float>(
+        &self,
+        n: usize,
+        x: *const T,
+        incx: isize,
+        weight: Option<(*const T, isize)>,
+        bias: Option<(*const T, isize)>,
+        eps: T,
+        y: *mut T,
+        incy: isize,
+    ) {
+        if n == 0 {
+            return;
+        }
+        let mut ss = T::zero();
+        for i in 0..n as isize {
+            let v = *x.offset(i * incx);
+            ss = ss + v * v;
+        }
+        let ms = ss / T::from(n).unwrap();
+        normalize(n, x, incx, T::zero(), (ms + eps).sqrt().recip(), weight, bias, y, incy);
+    }
+}
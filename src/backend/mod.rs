@@ -1,5 +1,9 @@
 use std::alloc::Layout;
 
+use num_traits::{Float, Signed};
+
+use crate::{elem::Elem, index::Ix};
+
 pub mod cpu;
 
 pub enum BackendKind {
@@ -7,7 +11,7 @@ pub enum BackendKind {
     Cuda,
 }
 
-pub trait Backend: Allocator + MemOps + Copy + Clone + Default {
+pub trait Backend: Allocator + MemOps + BlasOps + FloatOps + NnOps + Copy + Clone + Default {
     const KIND: BackendKind;
 }
 
@@ -26,12 +30,298 @@ impl Backend for CpuBackend {
 // }
 
 pub trait Allocator {
+    /// Allocates memory for `layout` on the device.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must have a nonzero size.
     unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Frees memory from [`alloc`](Self::alloc).
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `alloc` of this backend for the same
+    /// `layout`, and must not be used afterwards.
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
 }
 
 pub trait MemOps {
+    /// Copies `count` contiguous elements from `src` to `dst`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid to read and `dst` valid to write for `count`
+    /// elements, and the two ranges must not overlap.
     unsafe fn copy<T>(&self, src: *const T, dst: *mut T, count: usize);
 
-    unsafe fn fill<T>(&self, ptr: *mut T, value: T, count: usize);
+    /// Copies the elements of a strided layout into another strided layout
+    /// of the same `dims`. Strides are given in elements and may be negative
+    /// or zero on the source side.
+    ///
+    /// # Safety
+    ///
+    /// `src_strides` and `dst_strides` must have one entry per axis of
+    /// `dims`. `src` must be valid to read and `dst` valid to write at every
+    /// offset reachable through `dims` and their strides. The destination
+    /// must not overlap the source, nor reach an element twice.
+    unsafe fn copy_strided<T>(
+        &self,
+        dims: &[Ix],
+        src: *const T,
+        src_strides: &[isize],
+        dst: *mut T,
+        dst_strides: &[isize],
+    );
+
+    /// Writes clones of `value` into `count` contiguous elements at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid to write for `count` elements. The elements are
+    /// overwritten without being dropped.
+    unsafe fn fill<T: Clone>(&self, ptr: *mut T, value: T, count: usize);
+}
+
+/// Numeric kernels in the style of BLAS.
+///
+/// Vectors are given by a pointer to their first element, a length and an
+/// increment in elements, which may be negative or zero.
+///
+/// Each kernel requires that every vector it reads is valid to read, and
+/// every vector it writes valid to write, at the `n` offsets `i * inc` for
+/// `i` in `0..n`. A written vector must not overlap any other operand, and
+/// its increment must be nonzero unless `n` is at most one.
+pub trait BlasOps {
+    /// Computes `y = alpha * x + y`.
+    ///
+    /// # Safety
+    ///
+    /// `x` and `y` must be valid vectors of length `n`, as described on the
+    /// trait.
+    unsafe fn axpy<T: Elem>(&self, n: usize, alpha: T, x: *const T, incx: isize, y: *mut T, incy: isize);
+
+    /// Returns the inner product of `x` and `y`.
+    ///
+    /// # Safety
+    ///
+    /// `x` and `y` must be valid vectors of length `n`, as described on the
+    /// trait.
+    unsafe fn dot<T: Elem>(&self, n: usize, x: *const T, incx: isize, y: *const T, incy: isize) -> T;
+
+    /// Computes `x = alpha * x`.
+    ///
+    /// # Safety
+    ///
+    /// `x` must be a valid vector of length `n`, as described on the trait.
+    unsafe fn scal<T: Elem>(&self, n: usize, alpha: T, x: *mut T, incx: isize);
+
+    /// Returns the Euclidean norm of `x`, avoiding overflow and underflow in
+    /// the intermediate squares.
+    ///
+    /// # Safety
+    ///
+    /// `x` must be a valid vector of length `n`, as described on the trait.
+    unsafe fn nrm2<T: Elem + Float>(&self, n: usize, x: *const T, incx: isize) -> T;
+
+    /// Returns the sum of the absolute values of `x`.
+    ///
+    /// # Safety
+    ///
+    /// `x` must be a valid vector of length `n`, as described on the trait.
+    unsafe fn asum<T: Elem + Signed>(&self, n: usize, x: *const T, incx: isize) -> T;
+
+    /// Computes `C = alpha * A * B + beta * C` for an `m x k` matrix `A`, a
+    /// `k x n` matrix `B` and an `m x n` matrix `C`.
+    ///
+    /// Each matrix is given by a pointer to its first element and its
+    /// `[row, column]` strides in elements, which may be negative or zero on
+    /// the input side. When `beta` is zero, `C` is overwritten without being
+    /// read.
+    ///
+    /// # Safety
+    ///
+    /// Each matrix must be valid at every offset `i * strides[0] + j *
+    /// strides[1]` within its shape, to read for `A` and `B` and to write for
+    /// `C`. `C` must not overlap `A` or `B`, nor reach an element twice.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn gemm<T: Elem>(
+        &self,
+        m: usize,
+        k: usize,
+        n: usize,
+        alpha: T,
+        a: *const T,
+        a_strides: [isize; 2],
+        b: *const T,
+        b_strides: [isize; 2],
+        beta: T,
+        c: *mut T,
+        c_strides: [isize; 2],
+    );
+}
+
+/// An element-wise function of floats, as applied by [`FloatOps::unary`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp<T> {
+    Exp,
+    Exp2,
+    Ln,
+    Log2,
+    Log10,
+    Sqrt,
+    /// `1 / sqrt(x)`.
+    Rsqrt,
+    Sin,
+    Cos,
+    Tan,
+    Tanh,
+    /// The logistic function `1 / (1 + exp(-x))`.
+    Sigmoid,
+    Abs,
+    Floor,
+    Ceil,
+    /// Rounds half-way cases away from zero.
+    Round,
+    Powf(T),
+    Powi(i32),
+    /// `max(x, 0)`, keeping NaN.
+    Relu,
+    /// GELU in its tanh approximation,
+    /// `x / 2 * (1 + tanh(sqrt(2 / π) * (x + 0.044715 * x^3)))`.
+    Gelu,
+    /// SiLU, or swish, `x * sigmoid(x)`.
+    Silu,
+}
+
+fn sigmoid<T: Float>(x: T) -> T {
+    // Only ever exponentiate a non-positive number, so that large inputs of
+    // either sign cannot overflow.
+    let e = (-x.abs()).exp();
+    if x >= T::zero() {
+        (T::one() + e).recip()
+    } else {
+        e / (T::one() + e)
+    }
+}
+
+impl<T: Float> UnaryOp<T> {
+    /// Applies the function to a single value; this is the reference every
+    /// kernel has to match.
+    #[inline]
+    pub fn apply(self, x: T) -> T {
+        match self {
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Exp2 => x.exp2(),
+            UnaryOp::Ln => x.ln(),
+            UnaryOp::Log2 => x.log2(),
+            UnaryOp::Log10 => x.log10(),
+            UnaryOp::Sqrt => x.sqrt(),
+            UnaryOp::Rsqrt => x.sqrt().recip(),
+            UnaryOp::Sin => x.sin(),
+            UnaryOp::Cos => x.cos(),
+            UnaryOp::Tan => x.tan(),
+            UnaryOp::Tanh => x.tanh(),
+            UnaryOp::Sigmoid => sigmoid(x),
+            UnaryOp::Abs => x.abs(),
+            UnaryOp::Floor => x.floor(),
+            UnaryOp::Ceil => x.ceil(),
+            UnaryOp::Round => x.round(),
+            UnaryOp::Powf(n) => x.powf(n),
+            UnaryOp::Powi(n) => x.powi(n),
+            UnaryOp::Relu => {
+                if x < T::zero() {
+                    T::zero()
+                } else {
+                    x
+                }
+            },
+            UnaryOp::Gelu => {
+                let half = T::from(0.5).unwrap();
+                let c = T::from(0.797_884_560_802_865_4).unwrap();
+                let k = T::from(0.044_715).unwrap();
+                half * x * (T::one() + (c * (x + k * x * x * x)).tanh())
+            },
+            UnaryOp::Silu => x * sigmoid(x),
+        }
+    }
+}
+
+/// Element-wise float kernels.
+pub trait FloatOps {
+    /// Writes `op` applied to each element of a strided layout into another
+    /// strided layout of the same `dims`. Strides are given in elements; the
+    /// source may be the destination itself, with the same strides.
+    ///
+    /// # Safety
+    ///
+    /// `src_strides` and `dst_strides` must have one entry per axis of
+    /// `dims`. `src` must be valid to read and `dst` valid to write at every
+    /// offset reachable through `dims` and their strides. The destination
+    /// must not reach an element twice, and must either be the source with
+    /// the same strides or not overlap it at all.
+    unsafe fn unary<T: Elem + Float>(
+        &self,
+        op: UnaryOp<T>,
+        dims: &[Ix],
+        src: *const T,
+        src_strides: &[isize],
+        dst: *mut T,
+        dst_strides: &[isize],
+    );
+}
+
+/// Fused kernels for neural-network layers.
+///
+/// Each kernel handles one lane, given like the vectors of [`BlasOps`], and
+/// must not allocate.
+pub trait NnOps {
+    /// Writes the softmax of `x` into `y`, or its logarithm if `log` is set,
+    /// subtracting the maximum first for numerical stability.
+    ///
+    /// # Safety
+    ///
+    /// `x` and `y` must be valid vectors of length `n`, as described on
+    /// [`BlasOps`].
+    unsafe fn softmax<T: Elem + Float>(&self, n: usize, x: *const T, incx: isize, y: *mut T, incy: isize, log: bool);
+
+    /// Writes `(x - mean) / sqrt(var + eps) * weight + bias` into `y`, where
+    /// the mean and the biased variance are taken over `x`, and `weight` and
+    /// `bias` are optional vectors of length `n` with their increments.
+    ///
+    /// # Safety
+    ///
+    /// `x`, `y`, and `weight` and `bias` when given, must be valid vectors of
+    /// length `n`, as described on [`BlasOps`].
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn layer_norm<T: Elem + Float>(
+        &self,
+        n: usize,
+        x: *const T,
+        incx: isize,
+        weight: Option<(*const T, isize)>,
+        bias: Option<(*const T, isize)>,
+        eps: T,
+        y: *mut T,
+        incy: isize,
+    );
+
+    /// Like [`layer_norm`](Self::layer_norm), but scales by the root mean
+    /// square `sqrt(mean(x^2) + eps)` without centering.
+    ///
+    /// # Safety
+    ///
+    /// As for [`layer_norm`](Self::layer_norm).
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn rms_norm<T: Elem + Float>(
+        &self,
+        n: usize,
+        x: *const T,
+        incx: isize,
+        weight: Option<(*const T, isize)>,
+        bias: Option<(*const T, isize)>,
+        eps: T,
+        y: *mut T,
+        incy: isize,
+    );
 }
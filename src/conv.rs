@@ -0,0 +1,523 @@
+//! Convolutions.
+//!
+//! [`conv2d`](TensorBase::conv2d) takes an `NCHW` input and an `OIHW`
+//! weight, and [`conv1d`](TensorBase::conv1d) and
+//! [`conv3d`](TensorBase::conv3d) are the same with one and three spatial
+//! axes. The input channels are split into `groups` groups, each convolved
+//! with its own slice of output channels.
+//!
+//! Each convolution lowers every batch item and group to a matrix product:
+//! the input patches are unrolled into columns (im2col), one row per
+//! channel and kernel tap, and multiplied with the weight by the backend's
+//! `gemm`. The weight is used in place when its layout allows it.
+
+use crate::{
+    backend::Backend,
+    dimension::{dims_from_slice, strides_as_isize, Dimensions, Dims1, Dims3, Dims4, Dims5},
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    index::Ix,
+    storage::{traits::Storage, OwnedStorage},
+    tensor::TensorBase,
+    tensor_view::TensorView,
+};
+
+/// Returns the output size of a sliding window along each spatial axis.
+///
+/// With `ceil_mode`, a last window that starts inside the input or its left
+/// padding, but runs past the end, is kept.
+///
+/// **Errors** with `InvalidWindowParams` if a window size, stride or
+/// dilation is zero, and with `InvalidWindow` if a window does not fit the
+/// padded input.
+pub(crate) fn window_output(
+    input: &[Ix],
+    window: &[Ix],
+    stride: &[Ix],
+    padding: &[Ix],
+    dilation: &[Ix],
+    ceil_mode: bool,
+) -> OmniResult<Vec<Ix>> {
+    if [window, stride, dilation].iter().any(|xs| xs.contains(&0)) {
+        return Err(ShapeError::InvalidWindowParams(window.to_vec(), stride.to_vec(), dilation.to_vec()).into());
+    }
+    let padded: Vec<Ix> = input.iter().zip(padding).map(|(&i, &p)| i + 2 * p).collect();
+    let span: Vec<Ix> = window.iter().zip(dilation).map(|(&k, &d)| d * (k - 1) + 1).collect();
+    if padded.iter().zip(&span).any(|(p, s)| p < s) {
+        return Err(ShapeError::InvalidWindow(padded, span).into());
+    }
+    Ok((0..input.len())
+        .map(|d| {
+            let room = padded[d] - span[d];
+            if !ceil_mode {
+                return room / stride[d] + 1;
+            }
+            let out = room.div_ceil(stride[d]) + 1;
+            // The last window must start before the right padding.
+            if (out - 1) * stride[d] >= input[d] + padding[d] {
+                out - 1
+            } else {
+                out
+            }
+        })
+        .collect())
+}
+
+/// The spatial layout of a convolution.
+struct Geometry<'a> {
+    input: &'a [Ix],
+    input_strides: &'a [isize],
+    kernel: &'a [Ix],
+    output: &'a [Ix],
+    stride: &'a [Ix],
+    padding: &'a [Ix],
+    dilation: &'a [Ix],
+}
+
+impl Geometry<'_> {
+    /// Writes the values that kernel tap `tap` sees at every output position
+    /// into `dst`, for the spatial axes from `d` on, reading one input
+    /// channel at `src`. Taps that fall into the padding read zero.
+    unsafe fn unroll_tap<T: Elem>(&self, d: usize, tap: &[Ix], src: *const T, dst: &mut [T]) {
+        let len: usize = self.output[d + 1..].iter().product();
+        for (o, dst) in dst.chunks_exact_mut(len).enumerate() {
+            let pos = (o * self.stride[d] + tap[d] * self.dilation[d]) as isize - self.padding[d] as isize;
+            if pos < 0 || pos >= self.input[d] as isize {
+                dst.fill(T::zero());
+            } else if d + 1 == self.input.len() {
+                dst[0] = *src.offset(pos * self.input_strides[d]);
+            } else {
+                self.unroll_tap(d + 1, tap, src.offset(pos * self.input_strides[d]), dst);
+            }
+        }
+    }
+
+    /// Unrolls consecutive input channels, starting at `src` and
+    /// `channel_stride` apart, into the columns of `cols`: one row per
+    /// channel and kernel tap, one column per output position, for as many
+    /// channels as `cols` has room for.
+    unsafe fn im2col<T: Elem>(&self, src: *const T, channel_stride: isize, cols: &mut [T]) {
+        let taps: usize = self.kernel.iter().product();
+        let len: usize = self.output.iter().product();
+        let mut tap = vec![0; self.kernel.len()];
+        for (row, dst) in cols.chunks_exact_mut(len).enumerate() {
+            let (c, mut t) = (row / taps, row % taps);
+            for (i, &k) in tap.iter_mut().zip(self.kernel).rev() {
+                *i = t % k;
+                t /= k;
+            }
+            self.unroll_tap(0, &tap, src.offset(c as isize * channel_stride), dst);
+        }
+    }
+}
+
+/// Convolves an `N, C, spatial..` input with an `O, C / groups, kernel..`
+/// weight, for any number of spatial axes.
+#[allow(clippy::too_many_arguments)]
+fn conv<T, B, S, S2, D>(
+    input: &TensorBase<S, D>,
+    weight: &TensorBase<S2, D>,
+    bias: Option<TensorView<'_, T, B, Dims1>>,
+    stride: &[Ix],
+    padding: &[Ix],
+    dilation: &[Ix],
+    groups: usize,
+) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    S2: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    let (batch, in_channels) = (input.dims[0], input.dims[1]);
+    let (out_channels, group_channels) = (weight.dims[0], weight.dims[1]);
+    if groups == 0 || in_channels % groups != 0 || out_channels % groups != 0 {
+        return Err(ShapeError::InvalidGroups(groups, in_channels, out_channels).into());
+    }
+    if group_channels * groups != in_channels {
+        return Err(ShapeError::IncompatibleChannels(in_channels, group_channels * groups).into());
+    }
+    if let Some(bias) = &bias {
+        if bias.dims[0] != out_channels {
+            return Err(ShapeError::IncompatibleBias(bias.dims[0], out_channels).into());
+        }
+    }
+    let spatial = &input.dims.as_slice()[2..];
+    let kernel = &weight.dims.as_slice()[2..];
+    let output = window_output(spatial, kernel, stride, padding, dilation, false)?;
+
+    let mut out_dims = vec![batch, out_channels];
+    out_dims.extend_from_slice(&output);
+    let backend = input.storage.backend();
+    let out = unsafe { TensorBase::<OwnedStorage<T, B>, D>::uninit(dims_from_slice(&out_dims), backend) };
+
+    let rows = group_channels * kernel.iter().product::<Ix>();
+    let cols = output.iter().product::<Ix>();
+    let group_out = out_channels / groups;
+    let weight = weight.reshape([out_channels, rows])?;
+    let w_strides = strides_as_isize(weight.strides.as_slice());
+    let in_strides = strides_as_isize(input.strides.as_slice());
+    let out_strides = strides_as_isize(out.strides.as_slice());
+    let geometry = Geometry {
+        input: spatial,
+        input_strides: &in_strides[2..],
+        kernel,
+        output: &output,
+        stride,
+        padding,
+        dilation,
+    };
+    let mut unrolled = vec![T::zero(); rows * cols];
+    unsafe {
+        let beta = match &bias {
+            Some(bias) => {
+                for n in 0..batch {
+                    for (o, &b) in bias.iter().enumerate() {
+                        let dst = out.ptr.as_ptr().offset(n as isize * out_strides[0] + o as isize * out_strides[1]);
+                        backend.fill(dst, b, cols);
+                    }
+                }
+                T::one()
+            },
+            None if rows == 0 => {
+                backend.fill(out.ptr.as_ptr(), T::zero(), out.size());
+                T::one()
+            },
+            None => T::zero(),
+        };
+        for n in 0..batch {
+            for g in 0..groups {
+                let src = input
+                    .ptr
+                    .as_ptr()
+                    .offset(n as isize * in_strides[0] + (g * group_channels) as isize * in_strides[1]);
+                geometry.im2col(src, in_strides[1], &mut unrolled);
+                backend.gemm(
+                    group_out,
+                    rows,
+                    cols,
+                    T::one(),
+                    weight.ptr.as_ptr().offset((g * group_out) as isize * w_strides[0]),
+                    [w_strides[0], w_strides[1]],
+                    unrolled.as_ptr(),
+                    [cols as isize, 1],
+                    beta,
+                    out.ptr
+                        .as_ptr()
+                        .offset(n as isize * out_strides[0] + (g * group_out) as isize * out_strides[1]),
+                    [out_strides[1], 1],
+                );
+            }
+        }
+    }
+    Ok(out)
+}
+
+impl<T, B, S> TensorBase<S, Dims3>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+{
+    /// 1-D convolution of an `NCL` input with an `OIK` weight, where `I` is
+    /// the number of input channels per group; see
+    /// [`conv2d`](Self::conv2d).
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv1d<S2>(
+        &self,
+        weight: &TensorBase<S2, Dims3>,
+        bias: Option<TensorView<'_, T, B, Dims1>>,
+        stride: usize,
+        padding: usize,
+        dilation: usize,
+        groups: usize,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims3>>
+    where
+        S2: Storage<Elem = T, Backend = B>,
+    {
+        conv(self, weight, bias, &[stride], &[padding], &[dilation], groups)
+    }
+}
+
+impl<T, B, S> TensorBase<S, Dims4>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+{
+    /// 2-D convolution of an `NCHW` input with an `OIHW` weight, where `I`
+    /// is the number of input channels per group, adding `bias` to each
+    /// output channel if given. The input is zero-padded by `padding` on
+    /// both sides of each spatial axis, and the kernel taps are `dilation`
+    /// apart.
+    ///
+    /// **Errors** with `InvalidGroups` if the input or output channels do not
+    /// divide into `groups`, with `IncompatibleChannels` if the weight does
+    /// not match the input channels, with `IncompatibleBias` if the bias does
+    /// not match the output channels, with `InvalidWindowParams` if a kernel
+    /// size, stride or dilation is zero, and with `InvalidWindow` if the
+    /// dilated kernel does not fit the padded input.
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv2d<S2>(
+        &self,
+        weight: &TensorBase<S2, Dims4>,
+        bias: Option<TensorView<'_, T, B, Dims1>>,
+        stride: [usize; 2],
+        padding: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims4>>
+    where
+        S2: Storage<Elem = T, Backend = B>,
+    {
+        conv(self, weight, bias, &stride, &padding, &dilation, groups)
+    }
+}
+
+impl<T, B, S> TensorBase<S, Dims5>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+{
+    /// 3-D convolution of an `NCDHW` input with an `OIDHW` weight, where `I`
+    /// is the number of input channels per group; see
+    /// [`conv2d`](Self::conv2d).
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv3d<S2>(
+        &self,
+        weight: &TensorBase<S2, Dims5>,
+        bias: Option<TensorView<'_, T, B, Dims1>>,
+        stride: [usize; 3],
+        padding: [usize; 3],
+        dilation: [usize; 3],
+        groups: usize,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims5>>
+    where
+        S2: Storage<Elem = T, Backend = B>,
+    {
+        conv(self, weight, bias, &stride, &padding, &dilation, groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::CpuBackend,
+        dimension::DynDims,
+        error::OmniError,
+        test_util::*,
+    };
+
+    type View<'a> = TensorView<'a, f64, CpuBackend, DynDims>;
+
+    /// The options of one convolution, one entry per spatial axis.
+    struct Options<'a> {
+        stride: &'a [Ix],
+        padding: &'a [Ix],
+        dilation: &'a [Ix],
+        groups: usize,
+    }
+
+    fn opts<'a>(stride: &'a [Ix], padding: &'a [Ix], dilation: &'a [Ix], groups: usize) -> Options<'a> {
+        Options { stride, padding, dilation, groups }
+    }
+
+    /// The convolution with one loop per output element, input channel and
+    /// kernel tap.
+    fn reference(x: &View<'_>, w: &View<'_>, bias: Option<&[f64]>, opts: &Options<'_>) -> Tensor<f64, DynDims> {
+        let (x_dims, w_dims) = (x.shape().as_slice(), w.shape().as_slice());
+        let spatial = x_dims.len() - 2;
+        let (out_channels, group_channels) = (w_dims[0], w_dims[1]);
+        let group_out = out_channels / opts.groups;
+        let mut out_dims = vec![x_dims[0], out_channels];
+        for d in 0..spatial {
+            let span = opts.dilation[d] * (w_dims[2 + d] - 1) + 1;
+            out_dims.push((x_dims[2 + d] + 2 * opts.padding[d] - span) / opts.stride[d] + 1);
+        }
+        let taps: Ix = w_dims[2..].iter().product();
+        from_fn(out_dims, |i| {
+            let (n, o, pos) = (i[0], i[1], &i[2..]);
+            let g = o / group_out;
+            let mut acc = bias.map_or(0.0, |b| b[o]);
+            for c in 0..group_channels {
+                'taps: for t in 0..taps {
+                    let mut tap = vec![0; spatial];
+                    let mut rest = t;
+                    for d in (0..spatial).rev() {
+                        tap[d] = rest % w_dims[2 + d];
+                        rest /= w_dims[2 + d];
+                    }
+                    let mut x_index = vec![n, g * group_channels + c];
+                    for d in 0..spatial {
+                        let p = (pos[d] * opts.stride[d] + tap[d] * opts.dilation[d]) as isize
+                            - opts.padding[d] as isize;
+                        if p < 0 || p >= x_dims[2 + d] as isize {
+                            continue 'taps;
+                        }
+                        x_index.push(p as Ix);
+                    }
+                    let w_index = [&[o, c][..], &tap].concat();
+                    acc += x[x_index.as_slice()] * w[w_index.as_slice()];
+                }
+            }
+            acc
+        })
+    }
+
+    /// An input of `dims` stored channels-last, and a weight of `dims`
+    /// stored with its two channel axes swapped, so that neither is
+    /// contiguous and the weight cannot be reshaped without a copy.
+    fn operands(x_dims: &[Ix], w_dims: &[Ix]) -> (Tensor<f64, DynDims>, Tensor<f64, DynDims>) {
+        let ndim = x_dims.len();
+        let mut x_order: Vec<Ix> = (0..ndim).filter(|&d| d != 1).collect();
+        x_order.push(1);
+        let x_stored: Vec<Ix> = x_order.iter().map(|&d| x_dims[d]).collect();
+        let mut w_order: Vec<Ix> = (0..ndim).collect();
+        w_order.swap(0, 1);
+        let w_stored: Vec<Ix> = w_order.iter().map(|&d| w_dims[d]).collect();
+        // The inverse permutations bring the stored axes back into order.
+        let inverse = |order: &[Ix]| (0..ndim).map(|d| order.iter().position(|&o| o == d).unwrap()).collect::<Vec<_>>();
+        let x = noise(x_stored).permuted_axes(inverse(&x_order)).unwrap();
+        let w = noise(w_stored).permuted_axes(inverse(&w_order)).unwrap();
+        (x, w)
+    }
+
+    /// Runs `conv` on `x_dims` and `w_dims` for every combination of bias,
+    /// layout and the given options, comparing against the reference.
+    fn check(x_dims: &[Ix], w_dims: &[Ix], opts: &Options<'_>) {
+        let (strided_x, strided_w) = operands(x_dims, w_dims);
+        let bias = noise([w_dims[0]]);
+        let layouts = [(strided_x.to_owned(), strided_w.to_owned()), (strided_x, strided_w)];
+        for (x, w) in &layouts {
+            for bias in [None, Some(bias.view())] {
+                let expected = reference(&x.view(), &w.view(), bias.as_ref().map(to_vec).as_deref(), opts);
+                let out = conv(x, w, bias, opts.stride, opts.padding, opts.dilation, opts.groups).unwrap();
+                assert_eq!(out.shape().as_slice(), expected.shape().as_slice());
+                assert_close(&to_vec(&out), &to_vec(&expected));
+            }
+        }
+    }
+
+    #[test]
+    fn conv_matches_reference() {
+        // Inputs and weights with 1, 2 and 3 spatial axes.
+        let cases: [(&[Ix], &[Ix], Options<'_>); 11] = [
+            (&[2, 3, 9], &[4, 3, 3], opts(&[1], &[0], &[1], 1)),
+            (&[1, 2, 10], &[2, 2, 4], opts(&[3], &[2], &[2], 1)),
+            (&[2, 4, 7], &[6, 2, 2], opts(&[2], &[1], &[1], 2)),
+            (&[1, 3, 5], &[3, 1, 5], opts(&[1], &[2], &[1], 3)),
+            (&[2, 3, 6, 5], &[4, 3, 3, 2], opts(&[1, 1], &[0, 0], &[1, 1], 1)),
+            (&[1, 2, 7, 8], &[3, 2, 3, 3], opts(&[2, 3], &[1, 2], &[1, 2], 1)),
+            (&[2, 4, 5, 6], &[4, 2, 2, 3], opts(&[1, 2], &[1, 0], &[2, 1], 2)),
+            (&[1, 3, 4, 4], &[6, 1, 3, 3], opts(&[1, 1], &[1, 1], &[1, 1], 3)),
+            (&[1, 1, 1, 1], &[2, 1, 1, 1], opts(&[1, 1], &[0, 0], &[1, 1], 1)),
+            (&[2, 2, 4, 5, 3], &[3, 2, 2, 3, 2], opts(&[1, 2, 1], &[1, 0, 1], &[1, 1, 2], 1)),
+            (&[1, 4, 3, 4, 5], &[2, 2, 3, 1, 2], opts(&[2, 1, 3], &[1, 1, 0], &[1, 2, 1], 2)),
+        ];
+        for (x_dims, w_dims, opts) in cases {
+            check(x_dims, w_dims, &opts);
+        }
+    }
+
+    #[test]
+    fn conv1d_2d_3d() {
+        // The public entry points run the same convolution as `conv`.
+        let x = noise([2, 2, 6]);
+        let w = noise([3, 2, 3]);
+        let out = x.conv1d(&w, None, 2, 1, 1, 1).unwrap();
+        let expected = reference(
+            &x.view().into_dyn(),
+            &w.view().into_dyn(),
+            None,
+            &opts(&[2], &[1], &[1], 1),
+        );
+        assert_close(&to_vec(&out), &to_vec(&expected));
+
+        let x = noise([1, 2, 5, 4]);
+        let w = noise([2, 1, 2, 2]);
+        let bias = arange([2]);
+        let out = x.conv2d(&w, Some(bias.view()), [1, 2], [0, 1], [2, 1], 2).unwrap();
+        let expected = reference(
+            &x.view().into_dyn(),
+            &w.view().into_dyn(),
+            Some(&[0.0, 1.0]),
+            &opts(&[1, 2], &[0, 1], &[2, 1], 2),
+        );
+        assert_close(&to_vec(&out), &to_vec(&expected));
+
+        let x = noise([1, 1, 3, 4, 3]);
+        let w = noise([2, 1, 2, 2, 2]);
+        let out = x.conv3d(&w, None, [1, 1, 1], [0, 1, 0], [1, 1, 1], 1).unwrap();
+        let expected = reference(
+            &x.view().into_dyn(),
+            &w.view().into_dyn(),
+            None,
+            &opts(&[1, 1, 1], &[0, 1, 0], &[1, 1, 1], 1),
+        );
+        assert_eq!(out.shape().as_slice(), &[1, 2, 2, 5, 2]);
+        assert_close(&to_vec(&out), &to_vec(&expected));
+    }
+
+    #[test]
+    fn conv_of_integers() {
+        let x = from_fn([1, 1, 5], |i| i[2] as i32);
+        let w = from_fn([1, 1, 2], |i| [1, -1][i[2]]);
+        let bias = from_fn([1], |_| 10);
+        let out = x.conv1d(&w, Some(bias.view()), 1, 1, 1, 1).unwrap();
+        assert_eq!(to_vec(&out), [10, 9, 9, 9, 9, 14]);
+    }
+
+    #[test]
+    fn empty_operands() {
+        let out = noise([0, 2, 5]).conv1d(&noise([3, 2, 2]), None, 1, 0, 1, 1).unwrap();
+        assert_eq!(out.shape().as_slice(), &[0, 3, 4]);
+
+        // Without input channels, the output is the bias, or zero.
+        let x = noise([2, 0, 4]);
+        let w = noise([2, 0, 3]);
+        let out = x.conv1d(&w, None, 1, 0, 1, 1).unwrap();
+        assert_eq!(to_vec(&out), [0.0; 8]);
+        let bias = arange([2]);
+        let out = x.conv1d(&w, Some(bias.view()), 1, 0, 1, 1).unwrap();
+        assert_eq!(to_vec(&out), [0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn conv_errors() {
+        let x = noise([1, 4, 6]);
+        let err = |w: &Tensor<f64, Dims3>, bias: Option<&Tensor<f64, Dims1>>, stride, groups| {
+            match x.conv1d(w, bias.map(|b| b.view()), stride, 0, 1, groups) {
+                Ok(_) => panic!("expected an error"),
+                Err(e) => e,
+            }
+        };
+        let w = noise([2, 4, 3]);
+        assert!(matches!(err(&w, None, 1, 0), OmniError::ShapeError(ShapeError::InvalidGroups(0, 4, 2))));
+        assert!(matches!(err(&w, None, 1, 3), OmniError::ShapeError(ShapeError::InvalidGroups(3, 4, 2))));
+        assert!(matches!(err(&w, None, 1, 2), OmniError::ShapeError(ShapeError::IncompatibleChannels(4, 8))));
+        assert!(matches!(
+            err(&w, Some(&noise([3])), 1, 1),
+            OmniError::ShapeError(ShapeError::IncompatibleBias(3, 2)),
+        ));
+        assert!(matches!(err(&w, None, 0, 1), OmniError::ShapeError(ShapeError::InvalidWindowParams(..))));
+        assert!(matches!(
+            err(&noise([2, 4, 7]), None, 1, 1),
+            OmniError::ShapeError(ShapeError::InvalidWindow(..)),
+        ));
+    }
+
+    #[test]
+    fn window_output_sizes() {
+        assert_eq!(window_output(&[7], &[3], &[2], &[0], &[1], false).unwrap(), [3]);
+        assert_eq!(window_output(&[8], &[3], &[2], &[0], &[1], false).unwrap(), [3]);
+        assert_eq!(window_output(&[8], &[3], &[2], &[0], &[1], true).unwrap(), [4]);
+        assert_eq!(window_output(&[5, 5], &[2, 3], &[1, 2], &[1, 1], &[3, 1], false).unwrap(), [4, 3]);
+        // A last window that would start in the right padding is dropped.
+        assert_eq!(window_output(&[4], &[2], &[3], &[1], &[1], true).unwrap(), [2]);
+        assert!(window_output(&[4], &[3], &[1], &[0], &[2], false).is_err());
+        assert!(window_output(&[4], &[2], &[1], &[0], &[0], false).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    error::{OmniResult, ShapeError},
+    index::Ix,
+};
+use super::{
+    dimensions_trait::Dimensions,
+    dyn_dims::DynDims,
+};
+
+/// Computes the shape that all of `shapes` broadcast to, following the NumPy
+/// rules, e.g. `[8, 1, 6, 1]` and `[7, 1, 5]` broadcast to `[8, 7, 6, 5]`.
+///
+/// **Errors** with `IncompatibleBroadcast` naming the two input shapes that
+/// conflict.
+pub fn broadcast_shapes(shapes: &[&[Ix]]) -> OmniResult<DynDims> {
+    let ndim = shapes.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut out = DynDims::zeros(ndim);
+    out.as_slice_mut().iter_mut().for_each(|x| *x = 1);
+    // For each axis, the input shape that set its length, so that a conflict
+    // names the two input shapes rather than a partly merged one.
+    let mut source: Vec<Option<usize>> = vec![None; ndim];
+    for (i, &shape) in shapes.iter().enumerate() {
+        let axes = out.as_slice_mut().iter_mut().rev().zip(source.iter_mut().rev());
+        for ((o, src), &s) in axes.zip(shape.iter().rev()) {
+            if s == 1 || s == *o {
+                continue;
+            }
+            if let Some(j) = *src {
+                return Err(ShapeError::IncompatibleBroadcast(shapes[j].to_vec(), shape.to_vec()).into());
+            }
+            *o = s;
+            *src = Some(i);
+        }
+    }
+    Ok(out)
+}
+
+/// Broadcasts two shapes against each other following the NumPy rules and
+/// returns the resulting shape as `Output`.
+///
+/// Shapes are aligned on their trailing axes; two axes are compatible when
+/// they are equal or when one of them has length 1.
+pub(crate) fn co_broadcast<D1, D2, Output>(shape1: &D1, shape2: &D2) -> OmniResult<Output>
+where
+    D1: Dimensions,
+    D2: Dimensions,
+    Output: Dimensions,
+{
+    let ndim = shape1.ndim().max(shape2.ndim());
+    let mut out = Output::zeros(ndim);
+    out.as_slice_mut().iter_mut().for_each(|x| *x = 1);
+    for (o, &s) in out.as_slice_mut().iter_mut().rev().zip(shape1.as_slice().iter().rev()) {
+        *o = s;
+    }
+    for (o, &s) in out.as_slice_mut().iter_mut().rev().zip(shape2.as_slice().iter().rev()) {
+        if *o != s {
+            if *o == 1 {
+                *o = s;
+            } else if s != 1 {
+                return Err(ShapeError::IncompatibleBroadcast(
+                    shape1.as_slice().to_vec(),
+                    shape2.as_slice().to_vec(),
+                ).into());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Computes the strides that view a tensor of shape `from` (with `strides`)
+/// as a tensor of shape `to`, using zero strides on the broadcast axes.
+///
+/// Returns `None` if `from` cannot be broadcast to `to`.
+pub(crate) fn upcast<D, E>(to: &D, from: &E, strides: &E) -> Option<D>
+where
+    D: Dimensions,
+    E: Dimensions,
+{
+    if from.ndim() > to.ndim() {
+        return None;
+    }
+    let mut new_strides = D::zeros(to.ndim());
+    let from_strides = from.as_slice().iter().zip(strides.as_slice()).rev();
+    for ((&to_dim, new_stride), (&from_dim, &stride)) in to
+        .as_slice()
+        .iter()
+        .rev()
+        .zip(new_strides.as_slice_mut().iter_mut().rev())
+        .zip(from_strides)
+    {
+        if to_dim == from_dim {
+            *new_stride = stride;
+        } else if from_dim == 1 {
+            *new_stride = 0;
+        } else {
+            return None;
+        }
+    }
+    Some(new_strides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dimension::{Dims, Dims2, Dims3},
+        error::OmniError,
+        tensor::broadcast_tensors,
+        test_util::*,
+    };
+
+    /// The broadcast shape of `shapes`, one axis at a time from the end.
+    fn reference(shapes: &[&[Ix]]) -> Option<Vec<Ix>> {
+        let ndim = shapes.iter().map(|s| s.len()).max().unwrap_or(0);
+        let mut out = vec![1; ndim];
+        for shape in shapes {
+            for (k, &len) in shape.iter().rev().enumerate() {
+                let o = &mut out[ndim - 1 - k];
+                match (*o, len) {
+                    (a, b) if a == b || b == 1 => {},
+                    (1, b) => *o = b,
+                    _ => return None,
+                }
+            }
+        }
+        Some(out)
+    }
+
+    #[test]
+    fn broadcast_shapes_matches_reference() {
+        let cases: [&[&[Ix]]; 9] = [
+            &[],
+            &[&[]],
+            &[&[8, 1, 6, 1], &[7, 1, 5]],
+            &[&[3], &[2, 1], &[1, 1, 1]],
+            &[&[0, 1], &[1, 4]],
+            &[&[0], &[1]],
+            &[&[0], &[3]],
+            &[&[2, 3], &[3, 3]],
+            &[&[5, 1], &[1, 6], &[2, 1, 1]],
+        ];
+        for shapes in cases {
+            let out = broadcast_shapes(shapes).ok().map(|d| d.as_slice().to_vec());
+            assert_eq!(out, reference(shapes), "{shapes:?}");
+        }
+    }
+
+    #[test]
+    fn broadcast_shapes_names_the_conflicting_inputs() {
+        // The last axis is set to 4 by the second shape, merged from the
+        // first into [3, 4], and conflicts with the 5 of the third.
+        let shapes: [&[Ix]; 3] = [&[3, 1], &[1, 4], &[2, 1, 5]];
+        match broadcast_shapes(&shapes) {
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(a, b))) => {
+                assert_eq!((a, b), (vec![1, 4], vec![2, 1, 5]));
+            },
+            other => panic!("unexpected {other:?}"),
+        }
+        let shapes: [&[Ix]; 3] = [&[2, 1], &[1], &[3, 1]];
+        match broadcast_shapes(&shapes) {
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(a, b))) => {
+                assert_eq!((a, b), (vec![2, 1], vec![3, 1]));
+            },
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn co_broadcast_and_upcast() {
+        let dims: Dims3 = co_broadcast(&Dims([4, 1]), &Dims([2, 1, 3])).unwrap();
+        assert_eq!(dims.as_slice(), &[2, 4, 3]);
+        assert!(matches!(
+            co_broadcast::<_, _, Dims2>(&Dims([4, 2]), &Dims([3, 2])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+
+        let strides = upcast(&Dims([2, 4, 3]), &Dims([4, 1]), &Dims([5, 1])).unwrap();
+        assert_eq!(strides.as_slice(), &[0, 5, 0]);
+        assert!(upcast(&Dims([4]), &Dims([2, 4]), &Dims([4, 1])).is_none());
+        assert!(upcast(&Dims([3, 4]), &Dims([2, 4]), &Dims([4, 1])).is_none());
+    }
+
+    #[test]
+    fn broadcast_views() {
+        let col = arange([3, 1]);
+        let v = col.broadcast_to([2, 3, 4]).unwrap();
+        let expected = from_fn([2, 3, 4], |i| i[1] as f64);
+        assert_eq!(to_vec(&v), to_vec(&expected));
+        assert!(matches!(
+            col.broadcast_to([3, 2, 4]),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+
+        let row = arange([4]);
+        let (a, b) = col.broadcast_with(&row).unwrap();
+        assert_eq!(to_vec(&a), to_vec(&from_fn([3, 4], |i| i[0] as f64)));
+        assert_eq!(to_vec(&b), to_vec(&from_fn([3, 4], |i| i[1] as f64)));
+
+        let row = row.into_dyn();
+        let col = col.into_dyn();
+        let scalar = arange(Vec::<Ix>::new());
+        let views = broadcast_tensors(&[row.view(), col.view(), scalar.view()]).unwrap();
+        for view in &views {
+            assert_eq!(view.shape().as_slice(), &[3, 4]);
+        }
+        assert_eq!(to_vec(&views[2]), [0.0; 12]);
+    }
+}
@@ -1,10 +1,10 @@
 use std::ops::{Index, IndexMut};
 
-use crate::index::Ix;
+use crate::{error::ShapeError, index::Ix};
 use super::{
     dims::Dims,
     dimensions_trait::{Dimensions, IntoDimension},
-    dyn_dims::DynDims,
+    dyn_dims::{DynDims, DynDimsImpl},
 };
 
 impl Index<usize> for Dims<[Ix; 0]> {
@@ -167,6 +167,25 @@ impl IndexMut<usize> for DynDims {
     }
 }
 
+impl<const N: usize> From<Dims<[Ix; N]>> for DynDims {
+    fn from(dims: Dims<[Ix; N]>) -> Self {
+        Dims::new(DynDimsImpl::from_slice(&dims.0))
+    }
+}
+
+/// Fails with `IncompatibleShape` unless the dynamic dimensions have exactly
+/// `N` axes.
+impl<const N: usize> TryFrom<DynDims> for Dims<[Ix; N]> {
+    type Error = ShapeError;
+
+    fn try_from(dims: DynDims) -> Result<Self, Self::Error> {
+        dims.as_slice()
+            .try_into()
+            .map(Dims::new)
+            .map_err(|_| ShapeError::IncompatibleShape)
+    }
+}
+
 impl IntoDimension for [Ix; 0] {
     type Dims = Dims<[Ix; 0]>;
 
@@ -239,6 +258,22 @@ impl IntoDimension for [Ix; 8] {
     }
 }
 
+impl IntoDimension for &[Ix] {
+    type Dims = DynDims;
+
+    fn into_dimension(self) -> Self::Dims {
+        Dims::new(DynDimsImpl::from_slice(self))
+    }
+}
+
+impl IntoDimension for Vec<Ix> {
+    type Dims = DynDims;
+
+    fn into_dimension(self) -> Self::Dims {
+        Dims::new(DynDimsImpl::from_slice(&self))
+    }
+}
+
 impl IntoDimension for () {
     type Dims = Dims<[Ix; 0]>;
 
@@ -312,3 +347,70 @@ impl IntoDimension for (Ix, Ix, Ix, Ix, Ix, Ix, Ix, Ix) {
         ])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dimension::{into_dimensionality, Dims2, Dims3, Dims6},
+        error::{OmniError, ShapeError},
+        s,
+        test_util::*,
+    };
+
+    #[test]
+    fn fixed_and_dynamic_round_trip() {
+        // Up to four axes are stored inline, more on the heap.
+        for xs in [&[][..], &[3], &[2, 0, 4, 1], &[1, 2, 3, 4, 5, 6]] {
+            let dynamic = xs.into_dimension();
+            assert_eq!(dynamic.as_slice(), xs);
+            assert_eq!(dynamic, xs.to_vec().into_dimension());
+            assert_eq!(dynamic.ndim(), xs.len());
+        }
+        let dims = Dims([2, 3, 4]);
+        let dynamic = DynDims::from(dims);
+        assert_eq!(dynamic.as_slice(), &[2, 3, 4]);
+        assert_eq!(Dims3::try_from(dynamic.clone()).unwrap(), dims);
+        assert!(matches!(Dims2::try_from(dynamic), Err(ShapeError::IncompatibleShape)));
+        let six = DynDims::from(Dims([1, 2, 3, 4, 5, 6]));
+        assert_eq!(Dims6::try_from(six).unwrap().as_slice(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn patterns_and_indexing() {
+        assert_eq!((2, 3, 4).into_dimension(), Dims([2, 3, 4]));
+        assert_eq!(5.into_dimension().into_pattern(), 5);
+        assert_eq!([4, 5].into_dimension().into_pattern(), (4, 5));
+        assert_eq!(().into_dimension().ndim(), 0);
+        let mut dims = vec![1, 2, 3, 4, 5].into_dimension();
+        dims[4] = 9;
+        assert_eq!(dims[4], 9);
+        assert_eq!(dims.as_slice(), &[1, 2, 3, 4, 9]);
+    }
+
+    #[test]
+    fn into_dimensionality_checks_the_axes() {
+        let dims = Dims([2, 3]);
+        assert_eq!(into_dimensionality::<_, DynDims>(&dims).unwrap().as_slice(), &[2, 3]);
+        assert_eq!(into_dimensionality::<_, Dims2>(&DynDims::from(dims)), Some(dims));
+        assert_eq!(into_dimensionality::<_, Dims3>(&DynDims::from(dims)), None);
+    }
+
+    #[test]
+    fn tensor_conversions_keep_the_layout() {
+        let t = arange([3, 4]);
+        let v = t.slice(s![..;-1, 1..;2]);
+        let d = v.clone().into_dyn();
+        assert_eq!(d.shape().as_slice(), v.shape().as_slice());
+        assert_eq!(d.strides(), v.strides());
+        assert_eq!(to_vec(&d), to_vec(&v));
+
+        let back = d.clone().into_dimensionality::<Dims2>().unwrap();
+        assert_eq!(back.strides(), &[-4, 2]);
+        assert_eq!(to_vec(&back), to_vec(&v));
+        assert!(matches!(
+            d.into_dimensionality::<Dims3>(),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+    }
+}
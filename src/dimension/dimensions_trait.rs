@@ -37,7 +37,7 @@ pub trait Dimensions:
     }
 
     fn default_strides(&self) -> Self {
-        let mut strides = Self::default();
+        let mut strides = Self::zeros(self.ndim());
 
         if self.as_slice().iter().all(|&i| i != 0) {
             let mut it = strides.as_slice_mut().iter_mut().rev();
@@ -100,6 +100,19 @@ pub trait Dimensions:
         true
     }
 
+    /// Whether `strides` lay out `dims` in row-major order without gaps, so
+    /// that memory order and logical order coincide. Axes of length 1 may
+    /// have any stride.
+    fn is_standard_layout(dims: &Self, strides: &Self) -> bool {
+        is_packed_in_axis_order(dims, strides, |prev, axis| axis < prev)
+    }
+
+    /// Whether `strides` lay out `dims` in column-major order without gaps.
+    /// Axes of length 1 may have any stride.
+    fn is_fortran_layout(dims: &Self, strides: &Self) -> bool {
+        is_packed_in_axis_order(dims, strides, |prev, axis| axis > prev)
+    }
+
     fn _fastest_varying_stride_order(&self) -> Self {
         let mut indices = self.clone();
         for (i, elem) in indices.as_slice_mut().iter_mut().enumerate() {
@@ -113,6 +126,34 @@ pub trait Dimensions:
     }
 }
 
+/// Whether `strides` pack `dims` without gaps, with positive strides growing
+/// from one axis to the next in the order accepted by `follows`, which is
+/// called with the previous and the next axis from fastest to slowest
+/// varying. Axes of length 1 are skipped, and empty tensors always qualify.
+fn is_packed_in_axis_order<D, F>(dims: &D, strides: &D, follows: F) -> bool
+where
+    D: Dimensions,
+    F: Fn(usize, usize) -> bool,
+{
+    if dims.as_slice().contains(&0) {
+        return true;
+    }
+    let order = strides._fastest_varying_stride_order();
+    let mut prev = None;
+    let mut packed = 1;
+    for &axis in order.as_slice() {
+        if dims[axis] == 1 {
+            continue;
+        }
+        if strides[axis] != packed || prev.is_some_and(|prev| !follows(prev, axis)) {
+            return false;
+        }
+        prev = Some(axis);
+        packed *= dims[axis];
+    }
+    true
+}
+
 pub trait IntoDimension {
     type Dims: Dimensions;
 
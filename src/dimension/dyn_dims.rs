@@ -44,7 +44,7 @@ impl<T: PartialEq> PartialEq for DynDimsRepr<T> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (&Self::Inline(len1, ref x1), &Self::Inline(len2, ref x2)) => {
-                len1 == len2 && (0..CAP as usize)
+                len1 == len2 && (0..CAP)
                     .filter(|&i| i < len1 as usize)
                     .all(|i| x1[i] == x2[i])
             },
@@ -83,10 +83,32 @@ impl DerefMut for DynDimsImpl {
 }
 
 impl DynDimsImpl {
+    pub(crate) fn from_slice(xs: &[Ix]) -> Self {
+        let repr = if xs.len() <= CAP {
+            let mut arr = [0; CAP];
+            arr[..xs.len()].copy_from_slice(xs);
+            DynDimsRepr::Inline(xs.len() as u32, arr)
+        } else {
+            DynDimsRepr::from_vec(xs.to_vec())
+        };
+        DynDimsImpl(repr)
+    }
+
+    pub(crate) fn zeros(ndim: usize) -> Self {
+        let repr = if ndim <= CAP {
+            DynDimsRepr::Inline(ndim as u32, [0; CAP])
+        } else {
+            DynDimsRepr::from_vec(vec![0; ndim])
+        };
+        DynDimsImpl(repr)
+    }
+
+    /// Inserts a length-1 axis before position `axis`, which may be equal to
+    /// the current number of axes to append one.
     pub(crate) fn unsqueeze(&self, axis: usize) -> Self {
         let len = self.len();
-        debug_assert!(axis < len);
-        let repr = if axis < CAP {
+        debug_assert!(axis <= len);
+        let repr = if len < CAP {
             let mut out = [1; CAP];
             out[0..axis].copy_from_slice(&self[0..axis]);
             out[axis + 1..=len].copy_from_slice(&self[axis..len]);
@@ -101,35 +123,20 @@ impl DynDimsImpl {
         DynDimsImpl(repr)
     }
 
+    /// Drops the axis at position `axis`.
     pub(crate) fn squeeze(&self, axis: usize) -> Self {
         debug_assert!(axis < self.len());
-        let repr = match self.0 {
-            DynDimsRepr::Inline(0, _) => DynDimsRepr::Inline(0, [0; CAP]),
-            DynDimsRepr::Inline(1, _) => DynDimsRepr::Inline(0, [0; CAP]),
-            DynDimsRepr::Inline(2, ref x) => {
-                let mut out = [0; CAP];
-                out[0] = x[1 - axis];
-                DynDimsRepr::Inline(1, out)
-            },
-            DynDimsRepr::Inline(3, ref x) => {
-                let mut out = [0; CAP];
-                out[0] = x[1 - axis];
-                out[1] = x[2 - axis];
-                DynDimsRepr::Inline(2, out)
-            },
-            DynDimsRepr::Inline(4, ref x) => {
-                let mut out = [0; CAP];
-                out[0] = x[1 - axis];
-                out[1] = x[2 - axis];
-                out[2] = x[3 - axis];
-                DynDimsRepr::Inline(3, out)
-            },
-            ref repr => {
-                let mut out = Vec::with_capacity(repr.len() - 1);
-                out.extend_from_slice(&repr[0..axis]);
-                out.extend_from_slice(&repr[axis + 1..repr.len()]);
-                DynDimsRepr::from_vec(out)
-            },
+        let len = self.len();
+        let repr = if len <= CAP {
+            let mut out = [0; CAP];
+            out[0..axis].copy_from_slice(&self[0..axis]);
+            out[axis..len - 1].copy_from_slice(&self[axis + 1..len]);
+            DynDimsRepr::Inline((len - 1) as u32, out)
+        } else {
+            let mut out = Vec::with_capacity(len - 1);
+            out.extend_from_slice(&self[0..axis]);
+            out.extend_from_slice(&self[axis + 1..len]);
+            DynDimsRepr::from_vec(out)
         };
         DynDimsImpl(repr)
     }
@@ -142,7 +149,7 @@ impl Dimensions for DynDims {
 
     type Pattern = Self;
 
-    type Smaller = Dims<[Ix; 8]>;
+    type Smaller = Self;
 
     type Larger = Self;
 
@@ -163,6 +170,6 @@ impl Dimensions for DynDims {
     }
 
     fn zeros(ndim: usize) -> Self {
-        DynDims::zeros(ndim)
+        Dims(DynDimsImpl::zeros(ndim))
     }
 }
@@ -0,0 +1,107 @@
+use super::{
+    dimensions_trait::Dimensions,
+    dims::{
+        Dims0,
+        Dims1,
+        Dims2,
+        Dims3,
+        Dims4,
+        Dims5,
+        Dims6,
+        Dims7,
+        Dims8,
+    },
+    dims_max::DimsMaxOf,
+    dyn_dims::DynDims,
+};
+
+/// Dimension type of the result of a matrix product.
+///
+/// Follows NumPy's `matmul`: the last two axes are the matrix axes and all
+/// leading axes are broadcast batch axes. A 1-D operand is treated as a row
+/// (left) or column (right) vector, whose axis is dropped from the result.
+pub trait MatMulDims<Rhs: Dimensions> {
+    /// The resulting dimension type of the product.
+    type Output: Dimensions;
+}
+
+impl MatMulDims<Dims1> for Dims1 {
+    type Output = Dims0;
+}
+
+macro_rules! impl_matmul_vector {
+    ($matrix:ty) => {
+        impl MatMulDims<Dims1> for $matrix {
+            type Output = <$matrix as Dimensions>::Smaller;
+        }
+
+        impl MatMulDims<$matrix> for Dims1 {
+            type Output = <$matrix as Dimensions>::Smaller;
+        }
+    };
+}
+
+impl_matmul_vector!(Dims2);
+impl_matmul_vector!(Dims3);
+impl_matmul_vector!(Dims4);
+impl_matmul_vector!(Dims5);
+impl_matmul_vector!(Dims6);
+impl_matmul_vector!(Dims7);
+impl_matmul_vector!(Dims8);
+impl_matmul_vector!(DynDims);
+
+macro_rules! impl_matmul_batched {
+    ($dims:ty) => {
+        impl MatMulDims<$dims> for $dims {
+            type Output = $dims;
+        }
+    };
+    ($smaller:ty, $larger:ty) => {
+        impl MatMulDims<$larger> for $smaller {
+            type Output = DimsMaxOf<$smaller, $larger>;
+        }
+
+        impl MatMulDims<$smaller> for $larger {
+            type Output = DimsMaxOf<$larger, $smaller>;
+        }
+    };
+}
+
+impl_matmul_batched!(Dims2);
+impl_matmul_batched!(Dims3);
+impl_matmul_batched!(Dims4);
+impl_matmul_batched!(Dims5);
+impl_matmul_batched!(Dims6);
+impl_matmul_batched!(Dims7);
+impl_matmul_batched!(Dims8);
+impl_matmul_batched!(DynDims);
+impl_matmul_batched!(Dims2, Dims3);
+impl_matmul_batched!(Dims2, Dims4);
+impl_matmul_batched!(Dims2, Dims5);
+impl_matmul_batched!(Dims2, Dims6);
+impl_matmul_batched!(Dims2, Dims7);
+impl_matmul_batched!(Dims2, Dims8);
+impl_matmul_batched!(Dims2, DynDims);
+impl_matmul_batched!(Dims3, Dims4);
+impl_matmul_batched!(Dims3, Dims5);
+impl_matmul_batched!(Dims3, Dims6);
+impl_matmul_batched!(Dims3, Dims7);
+impl_matmul_batched!(Dims3, Dims8);
+impl_matmul_batched!(Dims3, DynDims);
+impl_matmul_batched!(Dims4, Dims5);
+impl_matmul_batched!(Dims4, Dims6);
+impl_matmul_batched!(Dims4, Dims7);
+impl_matmul_batched!(Dims4, Dims8);
+impl_matmul_batched!(Dims4, DynDims);
+impl_matmul_batched!(Dims5, Dims6);
+impl_matmul_batched!(Dims5, Dims7);
+impl_matmul_batched!(Dims5, Dims8);
+impl_matmul_batched!(Dims5, DynDims);
+impl_matmul_batched!(Dims6, Dims7);
+impl_matmul_batched!(Dims6, Dims8);
+impl_matmul_batched!(Dims6, DynDims);
+impl_matmul_batched!(Dims7, Dims8);
+impl_matmul_batched!(Dims7, DynDims);
+impl_matmul_batched!(Dims8, DynDims);
+
+pub type MatMulOf<D1, D2> = <D1 as MatMulDims<D2>>::Output;
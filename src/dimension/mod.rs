@@ -1,9 +1,18 @@
+use crate::{
+    error::{OmniResult, ShapeError},
+    index::{Axis, Ix},
+};
+
+pub mod broadcast;
 pub mod conversion;
 pub mod dims_max;
 pub mod dimensions_trait;
 pub mod dims;
 pub mod dyn_dims;
+pub mod matmul_dims;
+pub(crate) mod reshape;
 
+pub use broadcast::broadcast_shapes;
 pub use dims_max::{
     DimsMax,
     DimsMaxOf,
@@ -21,6 +30,68 @@ pub use dims::{
     Dims7,
     Dims8,
 };
+pub use dyn_dims::DynDims;
+pub use matmul_dims::{
+    MatMulDims,
+    MatMulOf,
+};
+
+/// Builds a `D` holding the axis lengths in `xs`.
+///
+/// The caller must make sure that `xs.len()` is a valid rank for `D`.
+pub(crate) fn dims_from_slice<D: Dimensions>(xs: &[Ix]) -> D {
+    let mut dims = D::zeros(xs.len());
+    dims.as_slice_mut().copy_from_slice(xs);
+    dims
+}
+
+/// Converts `dims` to another dimension type with the same number of axes,
+/// or returns `None` if `D2` has a fixed, different number of axes.
+pub(crate) fn into_dimensionality<D, D2>(dims: &D) -> Option<D2>
+where
+    D: Dimensions,
+    D2: Dimensions,
+{
+    match D2::NDIM {
+        Some(ndim) if ndim != dims.ndim() => None,
+        _ => Some(dims_from_slice(dims.as_slice())),
+    }
+}
+
+/// Returns the index of `axis`, or `AxisOutOfBounds` if a tensor with `ndim`
+/// dimensions has no such axis.
+pub(crate) fn check_axis(axis: Axis, ndim: usize) -> OmniResult<usize> {
+    if axis.index() < ndim {
+        Ok(axis.index())
+    } else {
+        Err(ShapeError::AxisOutOfBounds(axis.index(), ndim).into())
+    }
+}
+
+/// Inserts `value` into `dims` before position `axis`.
+pub(crate) fn insert_axis<D: Dimensions>(dims: &D, axis: usize, value: Ix) -> D::Larger {
+    let mut out = D::Larger::zeros(dims.ndim() + 1);
+    let (head, tail) = dims.as_slice().split_at(axis);
+    out.as_slice_mut()[..axis].copy_from_slice(head);
+    out.as_slice_mut()[axis] = value;
+    out.as_slice_mut()[axis + 1..].copy_from_slice(tail);
+    out
+}
+
+/// Drops `axis` from `dims`.
+pub(crate) fn remove_axis<D: Dimensions>(dims: &D, axis: usize) -> D::Smaller {
+    let mut out = D::Smaller::zeros(dims.ndim() - 1);
+    let (head, tail) = dims.as_slice().split_at(axis);
+    out.as_slice_mut()[..axis].copy_from_slice(head);
+    out.as_slice_mut()[axis..].copy_from_slice(&tail[1..]);
+    out
+}
+
+/// Reinterprets stored strides as the signed element offsets they encode.
+pub(crate) fn strides_as_isize(strides: &[Ix]) -> &[isize] {
+    // Safe because `Ix` and `isize` have the same size and alignment.
+    unsafe { std::slice::from_raw_parts(strides.as_ptr() as *const isize, strides.len()) }
+}
 
 pub fn offset_from_low_addr_ptr_to_logical_ptr<D: Dimensions>(dims: &D, strides: &D) -> usize {
     let zip_iter = dims.as_slice().iter().zip(strides.as_slice().iter());
@@ -0,0 +1,91 @@
+//! Stride computation for reshaping without copying.
+
+use crate::index::Ix;
+
+/// Computes strides that let a tensor with `dims`/`strides` be viewed with
+/// the axis lengths `new_dims`, reading elements in row-major order, or in
+/// column-major order when `is_f` is set.
+///
+/// Returns `None` if the current memory layout cannot be expressed with the
+/// new shape, in which case the elements have to be copied. The element
+/// counts of `dims` and `new_dims` must match.
+pub(crate) fn reshape_strides(
+    dims: &[Ix],
+    strides: &[isize],
+    new_dims: &[Ix],
+    is_f: bool,
+) -> Option<Vec<isize>> {
+    debug_assert_eq!(dims.iter().product::<Ix>(), new_dims.iter().product::<Ix>());
+
+    if is_f {
+        // Column-major order is row-major order over the reversed axes.
+        let rev_dims: Vec<Ix> = dims.iter().rev().copied().collect();
+        let rev_strides: Vec<isize> = strides.iter().rev().copied().collect();
+        let rev_new_dims: Vec<Ix> = new_dims.iter().rev().copied().collect();
+        let mut new_strides = reshape_strides(&rev_dims, &rev_strides, &rev_new_dims, false)?;
+        new_strides.reverse();
+        return Some(new_strides);
+    }
+
+    let mut new_strides = vec![0; new_dims.len()];
+    if new_dims.contains(&0) {
+        // Nothing is ever read, any strides will do.
+        let mut stride = 1;
+        for (s, &d) in new_strides.iter_mut().zip(new_dims).rev() {
+            *s = stride;
+            stride *= d.max(1) as isize;
+        }
+        return Some(new_strides);
+    }
+
+    // Length-1 axes never contribute to addressing.
+    let (old_dims, old_strides): (Vec<Ix>, Vec<isize>) = dims
+        .iter()
+        .zip(strides)
+        .filter(|(&d, _)| d != 1)
+        .map(|(&d, &s)| (d, s))
+        .unzip();
+
+    // Walk both shapes, pairing up the smallest runs of axes whose lengths
+    // have the same product.
+    let (mut oi, mut oj) = (0, 1);
+    let (mut ni, mut nj) = (0, 1);
+    while ni < new_dims.len() && oi < old_dims.len() {
+        let mut np = new_dims[ni];
+        let mut op = old_dims[oi];
+        while np != op {
+            if np < op {
+                np *= new_dims[nj];
+                nj += 1;
+            } else {
+                op *= old_dims[oj];
+                oj += 1;
+            }
+        }
+
+        // The old axes of the run must be laid out contiguously relative to
+        // each other.
+        for ok in oi..oj - 1 {
+            if old_strides[ok] != old_dims[ok + 1] as isize * old_strides[ok + 1] {
+                return None;
+            }
+        }
+
+        new_strides[nj - 1] = old_strides[oj - 1];
+        for nk in (ni + 1..nj).rev() {
+            new_strides[nk - 1] = new_strides[nk] * new_dims[nk] as isize;
+        }
+
+        ni = nj;
+        nj += 1;
+        oi = oj;
+        oj += 1;
+    }
+
+    // Trailing length-1 axes.
+    let last_stride = if ni >= 1 { new_strides[ni - 1] } else { 1 };
+    for s in &mut new_strides[ni..] {
+        *s = last_stride;
+    }
+    Some(new_strides)
+}
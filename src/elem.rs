@@ -1,3 +1,137 @@
 use num_traits::Num;
 
+use crate::{
+    backend::{Backend, UnaryOp},
+    index::Ix,
+    strided,
+};
+
+/// IEEE 754 half precision and bfloat16 floats, re-exported from the `half`
+/// crate rather than defined here, so they are the same types other crates
+/// use and `half` is part of the public API. Its `num-traits` feature
+/// provides `Num`, `Float` and the casting traits, and the types implement
+/// `Display`. `from_f32` rounds to nearest, ties to even, and
+/// [`HalfFloatSliceExt`] converts whole slices to and from `f32` and `f64`.
+pub use half::{bf16, f16, slice::HalfFloatSliceExt};
+/// Complex numbers, re-exported from the `num-complex` crate.
+pub use num_complex::{Complex, Complex32, Complex64, ComplexFloat};
+
 pub trait Elem: Num + Copy + Default {}
+
+macro_rules! impl_elem {
+    ($($t:ty),*) => {
+        $(impl Elem for $t {})*
+    };
+}
+
+impl_elem!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, f16, bf16);
+impl_elem!(Complex32, Complex64);
+
+/// Element types with an absolute value, as taken by
+/// [`TensorBase::abs`](crate::tensor::TensorBase::abs): the float types,
+/// through the backend's `Abs` kernel, and the complex types, whose absolute
+/// value is their real modulus.
+pub trait Abs: Elem {
+    type Real: Elem;
+
+    /// Writes the absolute value of each element of a strided layout into
+    /// another strided layout of the same `dims`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid to read and `dst` valid to write at every offset
+    /// reachable through `dims` and their strides, and the two layouts must
+    /// not overlap.
+    unsafe fn abs_strided<B: Backend>(
+        backend: B,
+        dims: &[Ix],
+        src: *const Self,
+        src_strides: &[isize],
+        dst: *mut Self::Real,
+        dst_strides: &[isize],
+    );
+}
+
+macro_rules! impl_abs_float {
+    ($($t:ty),*) => {
+        $(
+            impl Abs for $t {
+                type Real = $t;
+
+                unsafe fn abs_strided<B: Backend>(
+                    backend: B,
+                    dims: &[Ix],
+                    src: *const $t,
+                    src_strides: &[isize],
+                    dst: *mut $t,
+                    dst_strides: &[isize],
+                ) {
+                    backend.unary(UnaryOp::Abs, dims, src, src_strides, dst, dst_strides);
+                }
+            }
+        )*
+    };
+}
+
+impl_abs_float!(f16, bf16, f32, f64);
+
+macro_rules! impl_abs_complex {
+    ($($t:ty => $real:ty),*) => {
+        $(
+            impl Abs for $t {
+                type Real = $real;
+
+                unsafe fn abs_strided<B: Backend>(
+                    _: B,
+                    dims: &[Ix],
+                    src: *const $t,
+                    src_strides: &[isize],
+                    dst: *mut $real,
+                    dst_strides: &[isize],
+                ) {
+                    strided::zip2(dims, src as *mut $t, src_strides, dst, dst_strides, |x, y| {
+                        y.write((*x).norm())
+                    });
+                }
+            }
+        )*
+    };
+}
+
+impl_abs_complex!(Complex32 => f32, Complex64 => f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dimension::Dims2, s, test_util::*};
+
+    /// Values that half precision holds exactly, so that sums and products
+    /// of a few of them are exact too.
+    fn halves<T: Copy>(shape: [Ix; 2], from_f32: fn(f32) -> T) -> Tensor<T, Dims2> {
+        from_fn(shape, |i| from_f32(((i[0] * 5 + i[1] * 3) % 7) as f32 * 0.5 - 1.5))
+    }
+
+    #[test]
+    fn half_tensors_match_f32() {
+        let h = halves([3, 4], f16::from_f32);
+        let b = halves([3, 4], bf16::from_f32);
+        let reference = halves([3, 4], |x| x);
+
+        let v = h.slice(s![..;-1, 1..]);
+        let expected: Vec<f32> = to_vec(&reference.slice(s![..;-1, 1..]));
+        assert_eq!(to_vec(&v.to_owned()), expected.iter().map(|&x| f16::from_f32(x)).collect::<Vec<_>>());
+        assert_eq!(v.sum().to_f32(), expected.iter().sum::<f32>());
+        assert_eq!(b.slice(s![..;-1, 1..]).max().map(bf16::to_f32), Some(1.5));
+
+        let product = &h * &h.slice(s![..1, ..]);
+        let elems = to_vec(&reference);
+        let expected: Vec<f32> = (0..12).map(|k| elems[k] * elems[k % 4]).collect();
+        assert_eq!(to_vec(&product), expected.iter().map(|&x| f16::from_f32(x)).collect::<Vec<_>>());
+
+        let matmul = b.matmul(&b.t()).unwrap();
+        let expected = from_fn([3, 3], |i| {
+            (0..4).map(|k| reference[[i[0], k]] * reference[[i[1], k]]).sum::<f32>()
+        });
+        assert_eq!(to_vec(&matmul), to_vec(&expected).iter().map(|&x| bf16::from_f32(x)).collect::<Vec<_>>());
+    }
+}
@@ -1,11 +1,41 @@
 use thiserror::Error;
 
+use crate::index::Ix;
+
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
 pub enum ShapeError {
     #[error("Incompatible shape")]
     IncompatibleShape,
     #[error("Incompatible layout")]
     IncompatibleLayout,
+    #[error("Cannot broadcast shapes {0:?} and {1:?}")]
+    IncompatibleBroadcast(Vec<Ix>, Vec<Ix>),
+    #[error("Axis {0} is out of bounds for a tensor with {1} dimensions")]
+    AxisOutOfBounds(usize, usize),
+    #[error("Invalid axis permutation {0:?}")]
+    InvalidPermutation(Vec<usize>),
+    #[error("Expected at least one tensor")]
+    NoTensors,
+    #[error("Cannot concatenate shapes {0:?} and {1:?} along axis {2}")]
+    IncompatibleConcatenate(Vec<Ix>, Vec<Ix>, usize),
+    #[error("Cannot stack shapes {0:?} and {1:?}")]
+    IncompatibleStack(Vec<Ix>, Vec<Ix>),
+    #[error("Invalid split indices {0:?} for an axis of length {1}")]
+    InvalidSplit(Vec<usize>, usize),
+    #[error("Cannot split into {0} chunks")]
+    InvalidChunks(usize),
+    #[error("Input has {0} channels but the weight expects {1}")]
+    IncompatibleChannels(Ix, Ix),
+    #[error("Cannot split {1} input and {2} output channels into {0} groups")]
+    InvalidGroups(usize, Ix, Ix),
+    #[error("Bias has length {0} but there are {1} output channels")]
+    IncompatibleBias(Ix, Ix),
+    #[error("Window size, stride and dilation must be positive, got {0:?}, {1:?} and {2:?}")]
+    InvalidWindowParams(Vec<Ix>, Vec<Ix>, Vec<Ix>),
+    #[error("Window of size {1:?} does not fit the padded input of size {0:?}")]
+    InvalidWindow(Vec<Ix>, Vec<Ix>),
+    #[error("Padding {0:?} exceeds half of the window size {1:?}")]
+    InvalidPadding(Vec<Ix>, Vec<Ix>),
 }
 
 
@@ -13,6 +43,10 @@ pub enum ShapeError {
 pub enum OmniError {
     #[error("Shape error: {0}")]
     ShapeError(#[from] ShapeError),
+    #[error("Cannot cast a {from} value to {to}: it is NaN or out of range")]
+    InvalidCast { from: &'static str, to: &'static str },
+    #[error("Index {0} is out of bounds for an axis of length {1}")]
+    IndexOutOfBounds(Ix, Ix),
 }
 
 pub type OmniResult<T> = Result<T, OmniError>;
@@ -0,0 +1,222 @@
+//! Conversion between element types.
+//!
+//! The three casts differ only for values an integer target cannot represent:
+//!
+//! - [`cast`](TensorBase::cast) follows Rust's `as`: integers keep their low
+//!   bits, floats are truncated toward zero and saturate at the bounds of an
+//!   integer target, and NaN becomes 0.
+//! - [`cast_saturating`](TensorBase::cast_saturating) clamps every value to
+//!   the range of the target type, truncating floats toward zero and mapping
+//!   NaN to 0.
+//! - [`try_cast`](TensorBase::try_cast) truncates floats toward zero like
+//!   the others, but errors on NaN and out-of-range values.
+//!
+//! Float targets, including `f16` and `bf16`, behave the same in all three:
+//! values are rounded to nearest and overflow to infinity.
+//!
+//! Contiguous tensors are converted straight through their memory, keeping
+//! their strides; all others are collected in standard layout.
+
+use num_traits::{AsPrimitive, Bounded, NumCast, ToPrimitive};
+
+use crate::{
+    backend::Backend,
+    dimension::{offset_from_low_addr_ptr_to_logical_ptr, Dimensions},
+    elem::Elem,
+    error::{OmniError, OmniResult},
+    storage::{
+        traits::{Storage, StorageOwned},
+        OwnedStorage,
+    },
+    tensor::TensorBase,
+};
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Applies `f` to every element, converting contiguous tensors in memory
+    /// order and keeping their layout.
+    fn convert<U, F>(&self, mut f: F) -> TensorBase<OwnedStorage<U, B>, D>
+    where
+        U: Elem,
+        F: FnMut(T) -> U,
+    {
+        let Some(src) = self.as_slice_memory_order() else {
+            return self.mapv(f);
+        };
+        let (storage, base) = OwnedStorage::<U, B>::empty(src.len(), self.storage.backend());
+        unsafe {
+            for (i, &x) in src.iter().enumerate() {
+                base.as_ptr().add(i).write(f(x));
+            }
+            let offset = offset_from_low_addr_ptr_to_logical_ptr(&self.dims, &self.strides);
+            TensorBase {
+                storage,
+                ptr: base.add(offset),
+                dims: self.dims.clone(),
+                strides: self.strides.clone(),
+            }
+        }
+    }
+
+    /// Converts every element to `U` with `as` semantics.
+    pub fn cast<U>(&self) -> TensorBase<OwnedStorage<U, B>, D>
+    where
+        T: AsPrimitive<U>,
+        U: Elem + 'static,
+    {
+        self.convert(|x| x.as_())
+    }
+
+    /// Converts every element to `U`, clamping values outside the range of
+    /// `U` to its bounds and NaN to 0.
+    pub fn cast_saturating<U>(&self) -> TensorBase<OwnedStorage<U, B>, D>
+    where
+        T: ToPrimitive + PartialOrd,
+        U: Elem + NumCast + Bounded,
+    {
+        self.convert(|x| match U::from(x) {
+            Some(y) => y,
+            None if x > T::zero() => U::max_value(),
+            None if x < T::zero() => U::min_value(),
+            None => U::zero(),
+        })
+    }
+
+    /// Converts every element to `U`, truncating floats toward zero.
+    ///
+    /// **Errors** with `InvalidCast` if an element is NaN or outside the
+    /// range of `U`.
+    pub fn try_cast<U>(&self) -> OmniResult<TensorBase<OwnedStorage<U, B>, D>>
+    where
+        T: ToPrimitive,
+        U: Elem + NumCast,
+    {
+        let mut ok = true;
+        let out = self.convert(|x| {
+            U::from(x).unwrap_or_else(|| {
+                ok = false;
+                U::zero()
+            })
+        });
+        if ok {
+            Ok(out)
+        } else {
+            Err(OmniError::InvalidCast {
+                from: std::any::type_name::<T>(),
+                to: std::any::type_name::<U>(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        elem::{bf16, f16},
+        error::OmniError,
+        s,
+        test_util::*,
+    };
+
+    /// Values at and past the bounds of the narrow integer types, fractions
+    /// of both signs, and the special floats.
+    const FLOATS: [f64; 12] = [
+        0.0, -0.0, 2.9, -2.9, 127.5, -128.9, 255.9, 300.0, -1e10, f64::NAN, f64::INFINITY, f64::NEG_INFINITY,
+    ];
+
+    #[test]
+    fn cast_follows_as() {
+        let t = from_fn([3, 4], |i| FLOATS[i[0] * 4 + i[1]]);
+        let v = t.slice(s![..;-1, ..]);
+        let expected = to_vec(&v);
+        assert_eq!(to_vec(&v.cast::<i8>()), expected.iter().map(|&x| x as i8).collect::<Vec<_>>());
+        assert_eq!(to_vec(&v.cast::<u8>()), expected.iter().map(|&x| x as u8).collect::<Vec<_>>());
+        let wide = v.cast::<f32>();
+        for (&y, &x) in to_vec(&wide).iter().zip(&expected) {
+            assert!(y == x as f32 || (y.is_nan() && x.is_nan()), "{x} -> {y}");
+        }
+
+        let ints = from_fn([2, 5], |i| (i[0] * 5 + i[1]) as i64 * 100 - 500);
+        let expected: Vec<i64> = to_vec(&ints.t());
+        assert_eq!(to_vec(&ints.t().cast::<i8>()), expected.iter().map(|&x| x as i8).collect::<Vec<_>>());
+        assert_eq!(to_vec(&ints.t().cast::<u16>()), expected.iter().map(|&x| x as u16).collect::<Vec<_>>());
+        assert_eq!(to_vec(&ints.t().cast::<f64>()), expected.iter().map(|&x| x as f64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn contiguous_layouts_are_kept() {
+        let t = arange([3, 4]);
+        for v in [t.t(), t.slice(s![..;-1, ..;-1]), t.view()] {
+            let cast = v.cast::<i32>();
+            assert_eq!(cast.strides(), v.strides());
+            assert_eq!(to_vec(&cast), to_vec(&v).iter().map(|&x| x as i32).collect::<Vec<_>>());
+        }
+        let gappy = t.slice(s![.., ..;2]);
+        let cast = gappy.cast_saturating::<u8>();
+        assert!(cast.is_standard_layout());
+        assert_eq!(to_vec(&cast), [0, 2, 4, 6, 8, 10]);
+    }
+
+    #[test]
+    fn cast_saturating_clamps() {
+        let t = from_fn([12], |i| FLOATS[i[0]]);
+        // Rust's float-to-integer `as` already saturates.
+        assert_eq!(to_vec(&t.cast_saturating::<i8>()), FLOATS.map(|x| x as i8));
+        assert_eq!(to_vec(&t.cast_saturating::<u8>()), FLOATS.map(|x| x as u8));
+        assert_eq!(to_vec(&t.cast_saturating::<i64>()), FLOATS.map(|x| x as i64));
+
+        let ints = from_fn([2, 5], |i| (i[0] * 5 + i[1]) as i64 * 100 - 500);
+        let expected: Vec<i64> = to_vec(&ints);
+        let clamped = |lo: i64, hi: i64| expected.iter().map(|&x| x.clamp(lo, hi)).collect::<Vec<_>>();
+        let narrow: Vec<i64> = to_vec(&ints.cast_saturating::<i8>()).iter().map(|&x| x as i64).collect();
+        assert_eq!(narrow, clamped(-128, 127));
+        let unsigned: Vec<i64> = to_vec(&ints.cast_saturating::<u8>()).iter().map(|&x| x as i64).collect();
+        assert_eq!(unsigned, clamped(0, 255));
+        let big = from_fn([2], |i| if i[0] == 0 { u64::MAX } else { 7 });
+        assert_eq!(to_vec(&big.cast_saturating::<i32>()), [i32::MAX, 7]);
+    }
+
+    #[test]
+    fn try_cast_rejects_lossy_values() {
+        let t = from_fn([2, 3], |i| FLOATS[i[0] * 3 + i[1]] * 10.0);
+        assert_eq!(to_vec(&t.try_cast::<i16>().unwrap()), [0, 0, 29, -29, 1275, -1289]);
+        assert_eq!(to_vec(&t.slice(s![..1, ..]).try_cast::<u8>().unwrap()), [0, 0, 29]);
+        for (k, &x) in FLOATS.iter().enumerate() {
+            let single = from_fn([1], |_| x);
+            let fits = x.is_finite() && x > -129.0 && x < 128.0;
+            match single.try_cast::<i8>() {
+                Ok(cast) => assert!(fits && to_vec(&cast) == [x as i8], "element {k}: {x}"),
+                Err(e) => assert!(
+                    !fits && matches!(e, OmniError::InvalidCast { from: "f64", to: "i8" }),
+                    "element {k}: {x}"
+                ),
+            }
+        }
+        assert!(from_fn([1], |_| -1i32).try_cast::<u32>().is_err());
+        assert!(from_fn([1], |_| u8::MAX).try_cast::<i8>().is_err());
+    }
+
+    #[test]
+    fn float_targets_round_and_overflow() {
+        let t = from_fn([4], |i| [1e300, -1e300, 1.0 + 1e-10, f64::NAN][i[0]]);
+        for narrow in [t.cast::<f32>(), t.cast_saturating::<f32>(), t.try_cast::<f32>().unwrap()] {
+            let narrow = to_vec(&narrow);
+            assert_eq!(narrow[..3], [f32::INFINITY, f32::NEG_INFINITY, 1.0]);
+            assert!(narrow[3].is_nan());
+        }
+
+        let t = from_fn([3], |i| [70000.0f32, 0.1, -2.5][i[0]]);
+        assert_eq!(to_vec(&t.cast::<f16>()), [70000.0f32, 0.1, -2.5].map(f16::from_f32));
+        assert_eq!(to_vec(&t.cast_saturating::<f16>())[0], f16::INFINITY);
+        assert_eq!(to_vec(&t.cast::<bf16>()), [70000.0f32, 0.1, -2.5].map(bf16::from_f32));
+        let h = t.cast::<f16>();
+        assert_eq!(to_vec(&h.cast::<f32>()), [f32::INFINITY, f16::from_f32(0.1).to_f32(), -2.5]);
+        assert!(h.try_cast::<i32>().is_err());
+        assert_eq!(to_vec(&h.slice(s![1..]).try_cast::<i32>().unwrap()), [0, -2]);
+    }
+}
@@ -0,0 +1,223 @@
+//! Complex tensors.
+//!
+//! A `Complex<F>` is laid out as its real part followed by its imaginary
+//! part, so the parts of a complex tensor are themselves strided tensors of
+//! `F`: [`real`](TensorBase::real) and [`imag`](TensorBase::imag) view them
+//! with doubled strides, and [`view_as_complex`](TensorBase::view_as_complex)
+//! goes the other way, pairing up a trailing axis of length 2.
+//!
+//! `conj` and `arg` are defined for every `ComplexFloat`, so they also apply
+//! to real `f32` and `f64` tensors. The modulus is
+//! [`abs`](TensorBase::abs), shared with the float tensors.
+
+use std::ptr::NonNull;
+
+use num_complex::{Complex, ComplexFloat};
+
+use crate::{
+    backend::Backend,
+    dimension::{remove_axis, Dimensions},
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    storage::{
+        traits::{RawStorage, RawStorageMut, Storage},
+        OwnedStorage,
+    },
+    tensor::TensorBase,
+    tensor_view::{TensorView, TensorViewMut},
+};
+
+/// Strides in units of `F` for a tensor of `Complex<F>`.
+fn doubled_strides<D: Dimensions>(strides: &D) -> D {
+    let mut doubled = strides.clone();
+    for s in doubled.as_slice_mut() {
+        *s = s.wrapping_mul(2);
+    }
+    doubled
+}
+
+/// Pointer to the imaginary part of the complex number at `ptr`.
+///
+/// The pointer of an empty tensor dangles, so this must not use `add`.
+fn imag_ptr<F>(ptr: NonNull<Complex<F>>) -> NonNull<F> {
+    // The imaginary part directly follows the real part, and an address one
+    // `F` past a non-null, aligned pointer is never null.
+    unsafe { NonNull::new_unchecked(ptr.cast::<F>().as_ptr().wrapping_add(1)) }
+}
+
+impl<F, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = Complex<F>, Backend = B>,
+    D: Dimensions,
+{
+    /// Returns a view of the real parts.
+    pub fn real(&self) -> TensorView<'_, F, B, D> {
+        TensorView::new(
+            self.ptr.cast(),
+            self.dims.clone(),
+            doubled_strides(&self.strides),
+            self.storage.backend(),
+        )
+    }
+
+    /// Returns a view of the imaginary parts.
+    pub fn imag(&self) -> TensorView<'_, F, B, D> {
+        TensorView::new(
+            imag_ptr(self.ptr),
+            self.dims.clone(),
+            doubled_strides(&self.strides),
+            self.storage.backend(),
+        )
+    }
+
+    /// Returns a mutable view of the real parts.
+    pub fn real_mut(&mut self) -> TensorViewMut<'_, F, B, D>
+    where
+        S: RawStorageMut,
+    {
+        S::try_ensure_unique(self);
+        TensorViewMut::new(
+            self.ptr.cast(),
+            self.dims.clone(),
+            doubled_strides(&self.strides),
+            self.storage.backend(),
+        )
+    }
+
+    /// Returns a mutable view of the imaginary parts.
+    pub fn imag_mut(&mut self) -> TensorViewMut<'_, F, B, D>
+    where
+        S: RawStorageMut,
+    {
+        S::try_ensure_unique(self);
+        TensorViewMut::new(
+            imag_ptr(self.ptr),
+            self.dims.clone(),
+            doubled_strides(&self.strides),
+            self.storage.backend(),
+        )
+    }
+}
+
+impl<F, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = F, Backend = B>,
+    D: Dimensions,
+{
+    /// Views a trailing axis of length 2 as the real and imaginary parts of
+    /// complex numbers, removing that axis.
+    ///
+    /// **Errors** with `IncompatibleShape` if the last axis does not have
+    /// length 2, and with `IncompatibleLayout` if it is not contiguous or
+    /// another stride is odd, so that the pairs are not whole elements.
+    pub fn view_as_complex(&self) -> OmniResult<TensorView<'_, Complex<F>, B, D::Smaller>> {
+        let ndim = self.ndim();
+        if ndim == 0 || self.dims[ndim - 1] != 2 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let strides = self.strides.as_slice();
+        if strides[ndim - 1] != 1 || strides[..ndim - 1].iter().any(|&s| s % 2 != 0) {
+            return Err(ShapeError::IncompatibleLayout.into());
+        }
+        let mut halved = remove_axis(&self.strides, ndim - 1);
+        for s in halved.as_slice_mut() {
+            *s = (*s as isize / 2) as usize;
+        }
+        Ok(TensorView::new(
+            NonNull::cast(self.ptr),
+            remove_axis(&self.dims, ndim - 1),
+            halved,
+            self.storage.backend(),
+        ))
+    }
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem + ComplexFloat,
+    T::Real: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Complex conjugate of every element.
+    pub fn conj(&self) -> TensorBase<OwnedStorage<T, B>, D> {
+        self.mapv(T::conj)
+    }
+
+    /// Argument, or phase angle, of every element, in `(-π, π]`.
+    pub fn arg(&self) -> TensorBase<OwnedStorage<T::Real, B>, D> {
+        self.mapv(T::arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+
+    use crate::{
+        dimension::{Dimensions, Dims2},
+        error::OmniError,
+        s,
+        test_util::*,
+    };
+
+    fn complex(shape: [usize; 2]) -> Tensor<Complex64, Dims2> {
+        from_fn(shape, |i| Complex64::new(i[0] as f64, i[1] as f64 - 1.0))
+    }
+
+    #[test]
+    fn real_and_imag_views() {
+        let z = complex([2, 3]);
+        assert_eq!(to_vec(&z.real()), [0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(to_vec(&z.imag()), [-1.0, 0.0, 1.0, -1.0, 0.0, 1.0]);
+
+        let v = z.slice(s![..;-1, 1..]);
+        assert_eq!(to_vec(&v.real()), [1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(to_vec(&v.imag()), [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn write_through_part_views() {
+        let mut z = complex([2, 2]);
+        z.real_mut().iter_mut().for_each(|x| *x += 10.0);
+        z.imag_mut().iter_mut().for_each(|x| *x *= 2.0);
+        let expected: Vec<_> = to_vec(&complex([2, 2]))
+            .iter()
+            .map(|c| Complex64::new(c.re + 10.0, c.im * 2.0))
+            .collect();
+        assert_eq!(to_vec(&z), expected);
+    }
+
+    #[test]
+    fn empty_parts() {
+        let z = complex([0, 3]);
+        assert_eq!(z.imag().shape().as_slice(), &[0, 3]);
+        assert!(to_vec(&z.imag()).is_empty());
+        let mut z = complex([2, 0]);
+        assert!(z.imag_mut().iter().next().is_none());
+    }
+
+    #[test]
+    fn view_as_complex() {
+        let t = arange([3, 2]);
+        let z = t.view_as_complex().unwrap();
+        assert_eq!(to_vec(&z), [Complex64::new(0.0, 1.0), Complex64::new(2.0, 3.0), Complex64::new(4.0, 5.0)]);
+        assert_eq!(to_vec(&z.real()), [0.0, 2.0, 4.0]);
+
+        assert!(matches!(arange([2, 3]).view_as_complex(), Err(OmniError::ShapeError(_))));
+        assert!(matches!(t.t().view_as_complex(), Err(OmniError::ShapeError(_))));
+    }
+
+    #[test]
+    fn conj_abs_arg() {
+        let values = [Complex64::new(3.0, 4.0), Complex64::new(0.0, -2.0), Complex64::new(-1.0, 0.0)];
+        let z = from_fn([3], |i| values[i[0]]);
+        assert_eq!(to_vec(&z.conj()), [Complex64::new(3.0, -4.0), Complex64::new(0.0, 2.0), Complex64::new(-1.0, -0.0)]);
+        assert_eq!(to_vec(&z.abs()), [5.0, 2.0, 1.0]);
+        let arg = to_vec(&z.arg());
+        assert_eq!(arg, [4f64.atan2(3.0), -std::f64::consts::FRAC_PI_2, std::f64::consts::PI]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Element-wise float functions.
+//!
+//! Each function comes as a method returning a new standard-layout tensor,
+//! such as `exp`, and as an `_inplace` variant that overwrites the elements,
+//! unsharing shared storage first. Both read and write through strides and
+//! run the backend's [`unary`](crate::backend::FloatOps::unary) kernel, so a
+//! backend can provide its own implementation of every function.
+//!
+//! [`abs`](TensorBase::abs) is defined for every [`Abs`] element type, so
+//! it also takes the modulus of complex tensors; `abs_inplace` is for float
+//! tensors only.
+
+use num_traits::Float;
+
+use crate::{
+    backend::{Backend, UnaryOp},
+    dimension::{strides_as_isize, Dimensions},
+    elem::{Abs, Elem},
+    storage::{
+        traits::{Storage, StorageMut},
+        OwnedStorage,
+    },
+    tensor::TensorBase,
+};
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem + Float,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Applies `op` to every element, collecting the results into a new
+    /// tensor.
+    fn unary(&self, op: UnaryOp<T>) -> TensorBase<OwnedStorage<T, B>, D> {
+        let backend = self.storage.backend();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, D>::uninit(self.dims.clone(), backend);
+            backend.unary(
+                op,
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+            );
+            out
+        }
+    }
+
+    /// Replaces every element with `op` applied to it.
+    fn unary_inplace(&mut self, op: UnaryOp<T>)
+    where
+        S: StorageMut,
+    {
+        S::ensure_unique(self);
+        let strides = strides_as_isize(self.strides.as_slice());
+        unsafe {
+            self.storage.backend().unary(
+                op,
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides,
+                self.ptr.as_ptr(),
+                strides,
+            );
+        }
+    }
+
+    /// Raises every element to the float power `n`.
+    pub fn powf(&self, n: T) -> TensorBase<OwnedStorage<T, B>, D> {
+        self.unary(UnaryOp::Powf(n))
+    }
+
+    /// Raises every element to the float power `n` in place.
+    pub fn powf_inplace(&mut self, n: T)
+    where
+        S: StorageMut,
+    {
+        self.unary_inplace(UnaryOp::Powf(n))
+    }
+
+    /// Raises every element to the integer power `n`.
+    pub fn powi(&self, n: i32) -> TensorBase<OwnedStorage<T, B>, D> {
+        self.unary(UnaryOp::Powi(n))
+    }
+
+    /// Raises every element to the integer power `n` in place.
+    pub fn powi_inplace(&mut self, n: i32)
+    where
+        S: StorageMut,
+    {
+        self.unary_inplace(UnaryOp::Powi(n))
+    }
+
+    /// Replaces every element with its absolute value.
+    pub fn abs_inplace(&mut self)
+    where
+        S: StorageMut,
+    {
+        self.unary_inplace(UnaryOp::Abs)
+    }
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Abs,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Absolute value of every element, which for complex elements is their
+    /// modulus.
+    pub fn abs(&self) -> TensorBase<OwnedStorage<T::Real, B>, D> {
+        let backend = self.storage.backend();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T::Real, B>, D>::uninit(self.dims.clone(), backend);
+            T::abs_strided(
+                backend,
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+            );
+            out
+        }
+    }
+}
+
+macro_rules! impl_unary {
+    ($($mth:ident, $mth_inplace:ident, $variant:ident, $doc:expr;)+) => {
+        impl<T, B, S, D> TensorBase<S, D>
+        where
+            T: Elem + Float,
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            D: Dimensions,
+        {
+            $(
+                #[doc = $doc]
+                pub fn $mth(&self) -> TensorBase<OwnedStorage<T, B>, D> {
+                    self.unary(UnaryOp::$variant)
+                }
+
+                #[doc = concat!("In-place form of [`", stringify!($mth), "`](Self::", stringify!($mth), ").")]
+                pub fn $mth_inplace(&mut self)
+                where
+                    S: StorageMut,
+                {
+                    self.unary_inplace(UnaryOp::$variant)
+                }
+            )+
+        }
+    };
+}
+
+impl_unary! {
+    exp, exp_inplace, Exp, "Exponential of every element.";
+    exp2, exp2_inplace, Exp2, "Base-2 exponential of every element.";
+    ln, ln_inplace, Ln, "Natural logarithm of every element.";
+    log2, log2_inplace, Log2, "Base-2 logarithm of every element.";
+    log10, log10_inplace, Log10, "Base-10 logarithm of every element.";
+    sqrt, sqrt_inplace, Sqrt, "Square root of every element.";
+    rsqrt, rsqrt_inplace, Rsqrt, "Reciprocal square root of every element.";
+    sin, sin_inplace, Sin, "Sine of every element.";
+    cos, cos_inplace, Cos, "Cosine of every element.";
+    tan, tan_inplace, Tan, "Tangent of every element.";
+    tanh, tanh_inplace, Tanh, "Hyperbolic tangent of every element.";
+    sigmoid, sigmoid_inplace, Sigmoid, "Logistic sigmoid of every element.";
+    floor, floor_inplace, Floor, "Rounds every element down.";
+    ceil, ceil_inplace, Ceil, "Rounds every element up.";
+    round, round_inplace, Round, "Rounds every element to the nearest integer, half-way cases away from zero.";
+    relu, relu_inplace, Relu, "Rectified linear unit of every element, `max(x, 0)`, keeping NaN.";
+    gelu, gelu_inplace, Gelu, "GELU of every element, in its tanh approximation.";
+    silu, silu_inplace, Silu, "SiLU of every element, `x * sigmoid(x)`.";
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex32;
+
+    use crate::{
+        dimension::Dims2,
+        elem::{bf16, f16},
+        s,
+        test_util::*,
+    };
+
+    fn signed(shape: [usize; 2]) -> Tensor<f64, Dims2> {
+        from_fn(shape, |i| (i[0] * shape[1] + i[1]) as f64 * 0.75 - 3.0)
+    }
+
+    #[test]
+    fn unary_matches_std_on_strided_input() {
+        let t = signed([4, 5]);
+        let v = t.slice(s![..;-1, 1..;2]);
+        let x = to_vec(&v);
+        let check = |actual: Vec<f64>, f: fn(f64) -> f64| {
+            let expected: Vec<f64> = x.iter().map(|&x| f(x)).collect();
+            for (a, e) in actual.iter().zip(&expected) {
+                let close = (a - e).abs() <= 1e-12 * e.abs().max(1.0);
+                assert!(close || (a.is_nan() && e.is_nan()), "{a} != {e}");
+            }
+        };
+        check(to_vec(&v.exp()), f64::exp);
+        check(to_vec(&v.ln()), f64::ln);
+        check(to_vec(&v.sqrt()), f64::sqrt);
+        check(to_vec(&v.rsqrt()), |x| 1.0 / x.sqrt());
+        check(to_vec(&v.sin()), f64::sin);
+        check(to_vec(&v.tanh()), f64::tanh);
+        check(to_vec(&v.floor()), f64::floor);
+        check(to_vec(&v.round()), f64::round);
+        check(to_vec(&v.abs()), f64::abs);
+        check(to_vec(&v.sigmoid()), |x| 1.0 / (1.0 + (-x).exp()));
+        check(to_vec(&v.relu()), |x| x.max(0.0));
+        check(to_vec(&v.silu()), |x| x / (1.0 + (-x).exp()));
+        check(to_vec(&v.powf(1.5)), |x| x.powf(1.5));
+        check(to_vec(&v.powi(3)), |x| x.powi(3));
+    }
+
+    #[test]
+    fn sigmoid_does_not_overflow() {
+        let t = from_fn([3], |i| [-1000.0, 0.0, 1000.0][i[0]]);
+        assert_eq!(to_vec(&t.sigmoid()), [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn inplace_unshares_and_follows_strides() {
+        let shared = signed([3, 4]).into_shared();
+        let mut v = shared.clone().slice_move(s![.., ..;-2]);
+        let expected: Vec<f64> = to_vec(&v).iter().map(|x| x.abs()).collect();
+        v.abs_inplace();
+        assert_eq!(to_vec(&v), expected);
+        assert_eq!(to_vec(&shared), to_vec(&signed([3, 4])));
+
+        let mut t = signed([2, 2]);
+        t.powi_inplace(2);
+        assert_eq!(to_vec(&t), [9.0, 5.0625, 2.25, 0.5625]);
+    }
+
+    #[test]
+    fn abs_of_half_floats() {
+        let h = from_fn([4], |i| f16::from_f32(i[0] as f32 - 2.5));
+        assert_eq!(to_vec(&h.abs()), [2.5, 1.5, 0.5, 0.5].map(f16::from_f32));
+        let b = from_fn([2, 2], |i| bf16::from_f32(-(i[0] as f32) - i[1] as f32 * 0.5));
+        assert_eq!(to_vec(&b.t().abs()), [0.0, 1.0, 0.5, 1.5].map(bf16::from_f32));
+        assert_eq!(to_vec(&h.exp()), [-2.5f32, -1.5, -0.5, 0.5].map(|x| f16::from_f32(x.exp())));
+    }
+
+    #[test]
+    fn abs_of_complex_is_the_modulus() {
+        let z = from_fn([2], |i| Complex32::new(3.0 * i[0] as f32, -4.0));
+        assert_eq!(to_vec(&z.abs()), [4.0f32, 5.0]);
+    }
+}
@@ -0,0 +1,505 @@
+//! Selection and updates driven by index tensors and boolean masks.
+//!
+//! Indices are `Ix` values. `index_select` and `take` pick whole slices
+//! along an axis, `take` from an index tensor of any rank, whose axes
+//! replace that axis; given no axis, `take` picks elements by their
+//! row-major position instead. `gather`, `scatter` and `scatter_add`
+//! follow PyTorch: the index tensor has the rank of the data, and replaces
+//! the coordinate along one axis. Every index is checked before anything
+//! is read or written, so an out-of-range index leaves the tensors
+//! untouched.
+
+use crate::{
+    backend::Backend,
+    dimension::{check_axis, dims_from_slice, strides_as_isize, Dimensions, Dims, Dims1, DynDims},
+    elem::Elem,
+    error::{OmniError, OmniResult, ShapeError},
+    index::{Axis, Ix},
+    storage::{
+        traits::{Storage, StorageMut},
+        OwnedStorage,
+    },
+    strided,
+    tensor::TensorBase,
+    zip::Zip,
+};
+
+/// Checks that every element of `indices` is less than `len`.
+fn check_indices<S, D>(indices: &TensorBase<S, D>, len: Ix) -> OmniResult<()>
+where
+    S: Storage<Elem = Ix>,
+    D: Dimensions,
+{
+    match indices.iter().find(|&&i| i >= len) {
+        Some(&i) => Err(OmniError::IndexOutOfBounds(i, len)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that an index tensor of shape `index` fits into a tensor of shape
+/// `dims` on every axis but `axis`.
+fn check_index_shape(index: &[Ix], dims: &[Ix], axis: usize) -> OmniResult<()> {
+    let fits = index.len() == dims.len()
+        && (0..dims.len()).all(|d| d == axis || index[d] <= dims[d]);
+    if fits {
+        Ok(())
+    } else {
+        Err(ShapeError::IncompatibleShape.into())
+    }
+}
+
+/// `strides` with the stride along `axis` set to zero, so that walking them
+/// stays at the start of that axis.
+fn pinned_strides<D: Dimensions>(strides: &D, axis: usize) -> D {
+    let mut strides = strides.clone();
+    strides[axis] = 0;
+    strides
+}
+
+/// Offset of the element at row-major position `index`.
+fn flat_offset(dims: &[Ix], strides: &[isize], mut index: Ix) -> isize {
+    let mut offset = 0;
+    for (&dim, &stride) in dims.iter().zip(strides).rev() {
+        offset += (index % dim) as isize * stride;
+        index /= dim;
+    }
+    offset
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Copy,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Picks the slices at `indices` along `axis`, in order and possibly
+    /// repeated.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IndexOutOfBounds` if an index is past the end of the axis.
+    pub fn index_select<S2>(
+        &self,
+        axis: Axis,
+        indices: &TensorBase<S2, Dims1>,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        S2: Storage<Elem = Ix>,
+    {
+        let axis = check_axis(axis, self.ndim())?;
+        check_indices(indices, self.dims[axis])?;
+        let backend = self.storage.backend();
+        let mut dims = self.dims.clone();
+        dims[axis] = indices.dims[0];
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, D>::uninit(dims, backend);
+            let mut slice_dims = self.dims.clone();
+            slice_dims[axis] = 1;
+            let strides = strides_as_isize(self.strides.as_slice());
+            let out_strides = strides_as_isize(out.strides.as_slice());
+            for (k, &i) in indices.iter().enumerate() {
+                backend.copy_strided(
+                    slice_dims.as_slice(),
+                    self.ptr.as_ptr().offset(i as isize * strides[axis]),
+                    strides,
+                    out.ptr.as_ptr().offset(k as isize * out_strides[axis]),
+                    out_strides,
+                );
+            }
+            Ok(out)
+        }
+    }
+
+    /// Picks the elements at `indices` along `axis`, like NumPy's `take`:
+    /// the output replaces that axis with the axes of `indices`, so that it
+    /// holds `self[.., indices[j], ..]` at `[.., j, ..]`. With no axis, the
+    /// elements are picked by their row-major position in `self`, and the
+    /// output has the shape of `indices`.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IndexOutOfBounds` if an index is past the end of the axis, or not
+    /// less than the number of elements with no axis.
+    pub fn take<S2, E>(
+        &self,
+        axis: Option<Axis>,
+        indices: &TensorBase<S2, E>,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, DynDims>>
+    where
+        S2: Storage<Elem = Ix, Backend = B>,
+        E: Dimensions,
+    {
+        let dims = self.dims.as_slice();
+        let strides = strides_as_isize(self.strides.as_slice());
+        let Some(axis) = axis else {
+            check_indices(indices, self.size())?;
+            let ptr = self.ptr.as_ptr();
+            let taken = Zip::from(indices).map_collect(|&i| unsafe { *ptr.offset(flat_offset(dims, strides, i)) });
+            return Ok(taken.into_dyn());
+        };
+        let axis = check_axis(axis, self.ndim())?;
+        check_indices(indices, dims[axis])?;
+        let index_dims = indices.dims.as_slice();
+        let out_dims = [&dims[..axis], index_dims, &dims[axis + 1..]].concat();
+        let backend = self.storage.backend();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, DynDims>::uninit(dims_from_slice(&out_dims), backend);
+            let out_strides = strides_as_isize(out.strides.as_slice());
+            let index_strides = &out_strides[axis..axis + index_dims.len()];
+            // The strides of the output for a slice of `self` along `axis`.
+            let mut slice_dims = self.dims.clone();
+            slice_dims[axis] = 1;
+            let mut slice_strides = vec![0; dims.len()];
+            slice_strides[..axis].copy_from_slice(&out_strides[..axis]);
+            slice_strides[axis + 1..].copy_from_slice(&out_strides[axis + index_dims.len()..]);
+            for (k, &i) in indices.iter().enumerate() {
+                backend.copy_strided(
+                    slice_dims.as_slice(),
+                    self.ptr.as_ptr().offset(i as isize * strides[axis]),
+                    strides,
+                    out.ptr.as_ptr().offset(flat_offset(index_dims, index_strides, k)),
+                    &slice_strides,
+                );
+            }
+            Ok(out)
+        }
+    }
+
+    /// Gathers values along `axis`: the output has the shape of `index`, and
+    /// holds `self[.., index[i], ..]` at each position `i`, where the index
+    /// replaces the coordinate along `axis`.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, with
+    /// `IncompatibleShape` if `index` has another rank or is longer than
+    /// `self` on any other axis, and with `IndexOutOfBounds` if an index is
+    /// past the end of `axis`.
+    pub fn gather<S2>(&self, axis: Axis, index: &TensorBase<S2, D>) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        S2: Storage<Elem = Ix, Backend = B>,
+    {
+        let axis = check_axis(axis, self.ndim())?;
+        check_index_shape(index.dims.as_slice(), self.dims.as_slice(), axis)?;
+        check_indices(index, self.dims[axis])?;
+        let stride = self.strides[axis] as isize;
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, D>::uninit(index.dims.clone(), self.storage.backend());
+            strided::zip3(
+                out.dims.as_slice(),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+                index.ptr.as_ptr(),
+                strides_as_isize(index.strides.as_slice()),
+                self.ptr.as_ptr(),
+                strides_as_isize(pinned_strides(&self.strides, axis).as_slice()),
+                |out, i, src| out.write(*src.offset(*i as isize * stride)),
+            );
+            Ok(out)
+        }
+    }
+
+    /// Returns the elements where `mask` is true, in row-major order, as a
+    /// 1-D tensor. The mask is broadcast to the shape of `self`.
+    ///
+    /// **Errors** with `IncompatibleBroadcast` if the mask cannot be
+    /// broadcast to the shape of `self`.
+    pub fn masked_select<S2, E>(&self, mask: &TensorBase<S2, E>) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims1>>
+    where
+        S2: Storage<Elem = bool, Backend = B>,
+        E: Dimensions,
+    {
+        let mask = mask.broadcast_view(&self.dims)?;
+        let len = mask.iter().filter(|&&m| m).count();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, Dims1>::uninit(Dims([len]), self.storage.backend());
+            let mut dst = out.ptr.as_ptr();
+            Zip::from(self).and(mask).for_each(|&x, &m| {
+                if m {
+                    dst.write(x);
+                    dst = dst.add(1);
+                }
+            });
+            Ok(out)
+        }
+    }
+
+    /// Writes the elements of `src` into `self` along `axis`: for each
+    /// position `i` of `index`, `self[.., index[i], ..]` is set to `src[i]`,
+    /// where the index replaces the coordinate along `axis`. When several
+    /// positions target the same element, the last one in row-major order
+    /// wins.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, with
+    /// `IncompatibleShape` if `index` has another rank, is longer than `src`
+    /// on any axis or longer than `self` on any axis but `axis`, and with
+    /// `IndexOutOfBounds` if an index is past the end of `axis`.
+    pub fn scatter<S2, S3>(&mut self, axis: Axis, index: &TensorBase<S2, D>, src: &TensorBase<S3, D>) -> OmniResult<()>
+    where
+        S: StorageMut,
+        S2: Storage<Elem = Ix, Backend = B>,
+        S3: Storage<Elem = T, Backend = B>,
+    {
+        self.scatter_with(axis, index, src, |_, x| x)
+    }
+
+    /// Like [`scatter`](Self::scatter), but adds the elements of `src`
+    /// instead of overwriting, so that repeated indices accumulate.
+    pub fn scatter_add<S2, S3>(&mut self, axis: Axis, index: &TensorBase<S2, D>, src: &TensorBase<S3, D>) -> OmniResult<()>
+    where
+        T: Elem,
+        S: StorageMut,
+        S2: Storage<Elem = Ix, Backend = B>,
+        S3: Storage<Elem = T, Backend = B>,
+    {
+        self.scatter_with(axis, index, src, |acc, x| acc + x)
+    }
+
+    /// Combines each element of `src` into the element of `self` it is
+    /// scattered to with `f`.
+    fn scatter_with<S2, S3, F>(
+        &mut self,
+        axis: Axis,
+        index: &TensorBase<S2, D>,
+        src: &TensorBase<S3, D>,
+        f: F,
+    ) -> OmniResult<()>
+    where
+        S: StorageMut,
+        S2: Storage<Elem = Ix, Backend = B>,
+        S3: Storage<Elem = T, Backend = B>,
+        F: Fn(T, T) -> T,
+    {
+        let axis = check_axis(axis, self.ndim())?;
+        check_index_shape(index.dims.as_slice(), self.dims.as_slice(), axis)?;
+        if index.ndim() != src.ndim() || index.dims.as_slice().iter().zip(src.dims.as_slice()).any(|(i, s)| i > s) {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        check_indices(index, self.dims[axis])?;
+        S::ensure_unique(self);
+        let stride = self.strides[axis] as isize;
+        unsafe {
+            strided::zip3(
+                index.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(pinned_strides(&self.strides, axis).as_slice()),
+                index.ptr.as_ptr(),
+                strides_as_isize(index.strides.as_slice()),
+                src.ptr.as_ptr(),
+                strides_as_isize(src.strides.as_slice()),
+                |dst, i, x| {
+                    let dst = dst.offset(*i as isize * stride);
+                    *dst = f(*dst, *x);
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the elements where `mask` is true to `value`. The mask is
+    /// broadcast to the shape of `self`.
+    ///
+    /// **Errors** with `IncompatibleBroadcast` if the mask cannot be
+    /// broadcast to the shape of `self`.
+    pub fn masked_fill<S2, E>(&mut self, mask: &TensorBase<S2, E>, value: T) -> OmniResult<()>
+    where
+        S: StorageMut,
+        S2: Storage<Elem = bool, Backend = B>,
+        E: Dimensions,
+    {
+        let mask = mask.broadcast_view(&self.dims)?;
+        Zip::from(self).and(mask).for_each(|x, &m| {
+            if m {
+                *x = value;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dimension::{Dims3, IntoDimension},
+        s,
+        tensor_view::TensorView,
+        test_util::*,
+    };
+
+    /// Irregular indices below `len`.
+    fn indices<Sh: IntoDimension>(shape: Sh, len: Ix) -> Tensor<Ix, Sh::Dims> {
+        let mut k = 0;
+        from_fn(shape, |_| {
+            k += 1;
+            (k * 5 + k / 3) % len
+        })
+    }
+
+    #[test]
+    fn index_select_matches_reference() {
+        let t = noise([3, 4, 5]);
+        let v = t.slice(s![..;-1, .., 1..;2]);
+        let picks = from_fn([4], |i| [1, 0, 1, 1][i[0]]);
+        for axis in 0..3 {
+            let selected = v.index_select(Axis(axis), &picks).unwrap();
+            let mut shape = [3, 4, 2];
+            shape[axis] = 4;
+            let expected = from_fn(shape, |i| {
+                let mut index = [i[0], i[1], i[2]];
+                index[axis] = picks[[i[axis]]];
+                v[index]
+            });
+            assert_eq!(to_vec(&selected), to_vec(&expected), "axis {axis}");
+        }
+        let none = from_fn([0], |_| 0);
+        assert_eq!(v.index_select(Axis(1), &none).unwrap().shape().as_slice(), &[3, 0, 2]);
+        assert!(matches!(
+            v.index_select(Axis(2), &picks.mapv(|i| i + 1)),
+            Err(OmniError::IndexOutOfBounds(2, 2)),
+        ));
+        assert!(matches!(
+            v.index_select(Axis(3), &picks),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 3))),
+        ));
+    }
+
+    #[test]
+    fn take_matches_reference() {
+        let t = noise([3, 4]);
+        let v = t.t();
+        let elems = to_vec(&v);
+        let positions = indices([2, 5], 12);
+        let taken = v.take(None, &positions.t()).unwrap();
+        assert_eq!(taken.shape().as_slice(), &[5, 2]);
+        let expected: Vec<f64> = to_vec(&positions.t()).iter().map(|&k| elems[k]).collect();
+        assert_eq!(to_vec(&taken), expected);
+        assert!(matches!(v.take(None, &from_fn([1], |_| 12)), Err(OmniError::IndexOutOfBounds(12, 12))));
+    }
+
+    #[test]
+    fn take_along_an_axis() {
+        let t = noise([3, 4, 5]);
+        let v: TensorView<'_, f64, _, Dims3> = t.slice(s![..;-1, .., 1..;2]);
+        for axis in 0..3 {
+            let len = v.shape().as_slice()[axis];
+            let picks = indices([3, 2], len);
+            let picks = picks.t();
+            let taken = v.take(Some(Axis(axis)), &picks).unwrap();
+            let mut shape = vec![3, 4, 2];
+            shape.splice(axis..axis + 1, [2, 3]);
+            assert_eq!(taken.shape().as_slice(), &shape[..], "axis {axis}");
+            let expected = from_fn([shape[0], shape[1], shape[2], shape[3]], |i| {
+                let mut at = vec![i[0], i[1], i[2], i[3]];
+                let j = at.splice(axis..axis + 2, []).collect::<Vec<_>>();
+                at.insert(axis, picks[[j[0], j[1]]]);
+                v[[at[0], at[1], at[2]]]
+            });
+            assert_eq!(to_vec(&taken), to_vec(&expected), "axis {axis}");
+        }
+        let none = from_fn([0], |_| 0);
+        assert_eq!(v.take(Some(Axis(1)), &none).unwrap().shape().as_slice(), &[3, 0, 2]);
+        assert!(matches!(
+            v.take(Some(Axis(2)), &from_fn([1], |_| 2)),
+            Err(OmniError::IndexOutOfBounds(2, 2)),
+        ));
+        assert!(matches!(
+            v.take(Some(Axis(3)), &none),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 3))),
+        ));
+    }
+
+    #[test]
+    fn gather_matches_reference() {
+        let t = noise([4, 5, 6]);
+        let v: TensorView<'_, f64, _, Dims3> = t.slice(s![.., ..;-1, 1..]);
+        for axis in 0..3 {
+            // Shorter than the data on every axis, and longer along `axis`.
+            let mut shape = [3, 4, 4];
+            shape[axis] = 7;
+            let index = indices(shape, v.shape().as_slice()[axis]);
+            let gathered = v.gather(Axis(axis), &index).unwrap();
+            let expected = from_fn(shape, |i| {
+                let mut at = [i[0], i[1], i[2]];
+                at[axis] = index[at];
+                v[at]
+            });
+            assert_eq!(to_vec(&gathered), to_vec(&expected), "axis {axis}");
+        }
+        let index = indices([4, 6, 5], 5);
+        assert!(matches!(
+            v.gather(Axis(0), &index),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(matches!(
+            v.gather(Axis(1), &from_fn([2, 2, 2], |i| if i == [1, 1, 1] { 5 } else { 0 })),
+            Err(OmniError::IndexOutOfBounds(5, 5)),
+        ));
+    }
+
+    #[test]
+    fn scatter_matches_reference() {
+        for add in [false, true] {
+            for axis in 0..2 {
+                let mut shape = [3, 4];
+                shape[axis] = 6;
+                // Repeated targets, in a strided index and source.
+                let index = indices([shape[1], shape[0]], [5, 4][axis]);
+                let index = index.t();
+                let src = noise([shape[0], shape[1] + 1]);
+                let src = src.slice(s![.., ..;-1]);
+
+                let mut t = arange([5, 4]);
+                let mut expected = to_vec(&t);
+                for r in 0..shape[0] {
+                    for c in 0..shape[1] {
+                        let (x, i) = (src[[r, c]], index[[r, c]]);
+                        let k = if axis == 0 { i * 4 + c } else { r * 4 + i };
+                        expected[k] = if add { expected[k] + x } else { x };
+                    }
+                }
+                if add {
+                    t.scatter_add(Axis(axis), &index, &src.slice(s![.., ..shape[1]])).unwrap();
+                } else {
+                    t.scatter(Axis(axis), &index, &src.slice(s![.., ..shape[1]])).unwrap();
+                }
+                assert_close(&to_vec(&t), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn failed_scatters_write_nothing() {
+        let shared = arange([2, 3]).into_shared();
+        let mut t = shared.clone();
+        let src = from_fn([2, 3], |_| -1.0);
+        let bad = from_fn([2, 3], |i| if i == [1, 2] { 2 } else { 0 });
+        assert!(matches!(t.scatter(Axis(0), &bad, &src), Err(OmniError::IndexOutOfBounds(2, 2))));
+        assert!(t.scatter_add(Axis(1), &from_fn([2, 4], |_| 0), &src).is_err());
+        assert!(t.scatter(Axis(2), &bad, &src).is_err());
+        assert_eq!(to_vec(&t), to_vec(&shared));
+
+        t.scatter_add(Axis(0), &from_fn([1, 3], |_| 1), &src).unwrap();
+        assert_eq!(to_vec(&t), [0.0, 1.0, 2.0, 2.0, 3.0, 4.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn masked_ops_broadcast_the_mask() {
+        let t = noise([3, 4]);
+        let v = t.slice(s![..;-1, ..]);
+        let mask = from_fn([4], |i| i[0] % 3 == 0);
+        let expected: Vec<f64> = (0..3).flat_map(|r| [v[[r, 0]], v[[r, 3]]]).collect();
+        assert_eq!(to_vec(&v.masked_select(&mask).unwrap()), expected);
+        let positive = v.gt(&from_fn([1], |_| 0.0)).unwrap();
+        let expected: Vec<f64> = to_vec(&v).into_iter().filter(|&x| x > 0.0).collect();
+        assert_eq!(to_vec(&v.masked_select(&positive).unwrap()), expected);
+
+        let mut u = t.clone();
+        u.slice_mut(s![..;-1, ..]).masked_fill(&mask, 9.0).unwrap();
+        let expected = from_fn([3, 4], |i| if i[1] % 3 == 0 { 9.0 } else { t[[i[0], i[1]]] });
+        assert_eq!(to_vec(&u), to_vec(&expected));
+        assert!(matches!(
+            u.masked_fill(&from_fn([3], |_| true), 0.0),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+        assert!(v.masked_select(&from_fn([2, 4], |_| true)).is_err());
+    }
+}
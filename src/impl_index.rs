@@ -0,0 +1,240 @@
+//! Element access by multi-dimensional index.
+//!
+//! Any value that converts into the tensor's dimension type can be used as an
+//! index: the `D::Pattern` tuples (`(i, j)` for `Dims2`), fixed-size arrays,
+//! and `&[Ix]` or `Vec<Ix>` for `DynDims` tensors.
+
+use std::ops::{Index, IndexMut};
+
+use crate::{
+    backend::Backend,
+    dimension::{Dimensions, IntoDimension},
+    storage::traits::{RawStorage, RawStorageMut, Storage, StorageMut},
+    tensor::TensorBase,
+};
+
+/// Returns the offset of `index` from the logical pointer, or `None` if it is
+/// out of bounds or does not have one position per axis.
+fn offset_checked<D: Dimensions>(dims: &D, strides: &D, index: &D) -> Option<isize> {
+    if index.ndim() != dims.ndim() {
+        return None;
+    }
+    let mut offset = 0;
+    for ((&i, &len), &stride) in index
+        .as_slice()
+        .iter()
+        .zip(dims.as_slice())
+        .zip(strides.as_slice())
+    {
+        if i >= len {
+            return None;
+        }
+        offset += i as isize * stride as isize;
+    }
+    Some(offset)
+}
+
+fn offset_unchecked<D: Dimensions>(strides: &D, index: &D) -> isize {
+    index
+        .as_slice()
+        .iter()
+        .zip(strides.as_slice())
+        .map(|(&i, &stride)| i as isize * stride as isize)
+        .sum()
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Returns a reference to the element at `index`, or `None` if the index
+    /// is out of bounds.
+    pub fn get<I>(&self, index: I) -> Option<&T>
+    where
+        S: Storage,
+        I: IntoDimension<Dims = D>,
+    {
+        let index = index.into_dimension();
+        offset_checked(&self.dims, &self.strides, &index)
+            .map(|offset| unsafe { &*self.ptr.as_ptr().offset(offset) })
+    }
+
+    /// Returns a mutable reference to the element at `index`, or `None` if
+    /// the index is out of bounds.
+    ///
+    /// Shared storage is unshared first, so writes never leak into other
+    /// tensors.
+    pub fn get_mut<I>(&mut self, index: I) -> Option<&mut T>
+    where
+        S: StorageMut,
+        I: IntoDimension<Dims = D>,
+    {
+        let index = index.into_dimension();
+        offset_checked(&self.dims, &self.strides, &index)?;
+        // Unsharing may move the elements into a new layout, so the offset is
+        // only computed afterwards.
+        S::try_ensure_unique(self);
+        unsafe { Some(&mut *self.ptr.as_ptr().offset(offset_unchecked(&self.strides, &index))) }
+    }
+
+    /// Returns a reference to the element at `index` without bounds checks.
+    ///
+    /// # Safety
+    ///
+    /// `index` must have the tensor's number of axes and each of its entries
+    /// must be less than the length of its axis.
+    pub unsafe fn uget<I>(&self, index: I) -> &T
+    where
+        S: Storage,
+        I: IntoDimension<Dims = D>,
+    {
+        let index = index.into_dimension();
+        debug_assert!(offset_checked(&self.dims, &self.strides, &index).is_some());
+        &*self.ptr.as_ptr().offset(offset_unchecked(&self.strides, &index))
+    }
+
+    /// Returns a mutable reference to the element at `index` without bounds
+    /// checks. Shared storage is still unshared first.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be in bounds, as for [`uget`](Self::uget), and for a raw
+    /// view no other reference to the element may be alive while the
+    /// returned one is.
+    pub unsafe fn uget_mut<I>(&mut self, index: I) -> &mut T
+    where
+        S: RawStorageMut,
+        I: IntoDimension<Dims = D>,
+    {
+        let index = index.into_dimension();
+        debug_assert!(offset_checked(&self.dims, &self.strides, &index).is_some());
+        S::try_ensure_unique(self);
+        &mut *self.ptr.as_ptr().offset(offset_unchecked(&self.strides, &index))
+    }
+}
+
+/// Access the element at `index`.
+///
+/// **Panics** if the index is out of bounds.
+impl<T, B, S, D, I> Index<I> for TensorBase<S, D>
+where
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+    I: IntoDimension<Dims = D>,
+{
+    type Output = T;
+
+    fn index(&self, index: I) -> &T {
+        let index = index.into_dimension();
+        match offset_checked(&self.dims, &self.strides, &index) {
+            Some(offset) => unsafe { &*self.ptr.as_ptr().offset(offset) },
+            None => panic!(
+                "index {:?} is out of bounds for shape {:?}",
+                index.as_slice(),
+                self.dims.as_slice(),
+            ),
+        }
+    }
+}
+
+/// Mutably access the element at `index`, unsharing shared storage first.
+///
+/// **Panics** if the index is out of bounds.
+impl<T, B, S, D, I> IndexMut<I> for TensorBase<S, D>
+where
+    B: Backend,
+    S: StorageMut<Elem = T, Backend = B>,
+    D: Dimensions,
+    I: IntoDimension<Dims = D>,
+{
+    fn index_mut(&mut self, index: I) -> &mut T {
+        let index = index.into_dimension();
+        if offset_checked(&self.dims, &self.strides, &index).is_none() {
+            panic!(
+                "index {:?} is out of bounds for shape {:?}",
+                index.as_slice(),
+                self.dims.as_slice(),
+            );
+        }
+        S::try_ensure_unique(self);
+        unsafe { &mut *self.ptr.as_ptr().offset(offset_unchecked(&self.strides, &index)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{s, test_util::*};
+
+    #[test]
+    fn get_and_index() {
+        let t = arange([3, 4]);
+        assert_eq!(t[(1, 2)], 6.0);
+        assert_eq!(t[[2, 3]], 11.0);
+        assert_eq!(t.get((2, 0)), Some(&8.0));
+        assert_eq!(t.get((3, 0)), None);
+        assert_eq!(t.get((0, 4)), None);
+        assert_eq!(unsafe { *t.uget((1, 1)) }, 5.0);
+
+        let d = t.into_dyn();
+        assert_eq!(d[&[1, 3][..]], 7.0);
+        assert_eq!(d.get(&[1, 2, 0][..]), None);
+    }
+
+    #[test]
+    fn index_strided_view() {
+        let t = arange([3, 4]);
+        let v = t.slice(s![..;-1, 1..;2]);
+        let expected = from_fn([3, 2], |i| ((2 - i[0]) * 4 + 1 + 2 * i[1]) as f64);
+        for i in 0..3 {
+            for j in 0..2 {
+                assert_eq!(v[(i, j)], expected[(i, j)]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let t = arange([2, 2]);
+        let _ = t[(0, 2)];
+    }
+
+    #[test]
+    fn get_mut_writes_through() {
+        let mut t = arange([2, 3]);
+        *t.get_mut((1, 1)).unwrap() = -1.0;
+        t[(0, 2)] = -2.0;
+        assert!(t.get_mut((2, 0)).is_none());
+        assert_eq!(to_vec(&t), [0.0, 1.0, -2.0, 3.0, -1.0, 5.0]);
+    }
+
+    /// A shared strided view is unshared into a copy with standard strides,
+    /// so the element offset must be taken from the copy.
+    #[test]
+    fn get_mut_unshares_strided_view() {
+        let shared = arange([4, 4]).into_shared();
+        let mut v = shared.clone().reversed_axes().slice_move(s![0..2, 0..2]);
+        *v.get_mut((1, 0)).unwrap() = 999.0;
+        assert_eq!(to_vec(&v), [0.0, 4.0, 999.0, 5.0]);
+        assert_eq!(to_vec(&shared), to_vec(&arange([4, 4])));
+
+        let mut v = shared.clone().reversed_axes().slice_move(s![1..3, 2..4]);
+        v[(1, 1)] = -1.0;
+        assert_eq!(to_vec(&v), [9.0, 13.0, 10.0, -1.0]);
+        assert_eq!(to_vec(&shared), to_vec(&arange([4, 4])));
+    }
+
+    #[test]
+    fn uget_mut_unshares_strided_view() {
+        let shared = arange([4, 4]).into_shared();
+        let mut v = shared.clone().reversed_axes().slice_move(s![0..2, 0..2]);
+        unsafe {
+            *v.uget_mut((0, 1)) = 7.5;
+        }
+        assert_eq!(to_vec(&v), [0.0, 7.5, 1.0, 5.0]);
+        assert_eq!(shared[(1, 0)], 4.0);
+    }
+}
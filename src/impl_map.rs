@@ -0,0 +1,136 @@
+//! Elementwise closures.
+//!
+//! `map` and `mapv` collect the results into a new standard-layout tensor of
+//! any `Copy` element type, while `map_inplace` and `mapv_inplace` overwrite
+//! the elements, unsharing shared storage first. All of them visit the
+//! elements in row-major order; see [`Zip`](crate::zip::Zip) for closures
+//! over several tensors at once.
+
+use crate::{
+    backend::Backend,
+    dimension::{strides_as_isize, Dimensions},
+    storage::{
+        traits::{Storage, StorageMut},
+        OwnedStorage,
+    },
+    strided,
+    tensor::TensorBase,
+};
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Applies `f` to a reference to each element, collecting the results
+    /// into a new tensor.
+    pub fn map<U, F>(&self, mut f: F) -> TensorBase<OwnedStorage<U, B>, D>
+    where
+        U: Copy,
+        F: FnMut(&T) -> U,
+    {
+        unsafe {
+            let out = TensorBase::<OwnedStorage<U, B>, D>::uninit(self.dims.clone(), self.storage.backend());
+            strided::zip2(
+                self.dims.as_slice(),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |y, x| y.write(f(&*x)),
+            );
+            out
+        }
+    }
+
+    /// Applies `f` to each element by value, collecting the results into a
+    /// new tensor.
+    pub fn mapv<U, F>(&self, mut f: F) -> TensorBase<OwnedStorage<U, B>, D>
+    where
+        T: Copy,
+        U: Copy,
+        F: FnMut(T) -> U,
+    {
+        self.map(|&x| f(x))
+    }
+
+    /// Applies `f` to a mutable reference to each element.
+    pub fn map_inplace<F>(&mut self, mut f: F)
+    where
+        S: StorageMut,
+        F: FnMut(&mut T),
+    {
+        S::ensure_unique(self);
+        unsafe {
+            strided::zip1(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |x| f(&mut *x),
+            );
+        }
+    }
+
+    /// Replaces each element with `f` applied to its value.
+    pub fn mapv_inplace<F>(&mut self, mut f: F)
+    where
+        S: StorageMut,
+        T: Copy,
+        F: FnMut(T) -> T,
+    {
+        self.map_inplace(|x| *x = f(*x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{dimension::Dimensions, s, test_util::*};
+
+    #[test]
+    fn map_of_strided_views() {
+        let t = noise([3, 4, 5]);
+        let v = t.slice(s![..;-1, 1..;2, ..]).permuted_axes([2, 0, 1]).unwrap();
+        let expected = from_fn([5, 3, 2], |i| t[[2 - i[1], 1 + 2 * i[2], i[0]]]);
+        let squared = v.map(|x| x * x);
+        assert!(squared.is_standard_layout());
+        assert_eq!(to_vec(&squared), to_vec(&expected).iter().map(|x| x * x).collect::<Vec<_>>());
+        let signs = v.mapv(|x| x >= 0.0);
+        assert_eq!(to_vec(&signs), to_vec(&expected).iter().map(|&x| x >= 0.0).collect::<Vec<_>>());
+
+        // Closures see the elements in row-major order.
+        let mut seen = Vec::new();
+        v.map(|&x| seen.push(x));
+        assert_eq!(seen, to_vec(&expected));
+        assert_eq!(arange([0, 3]).mapv(|x| x as i32).shape().as_slice(), &[0, 3]);
+    }
+
+    #[test]
+    fn map_inplace_only_touches_the_view() {
+        let mut t = arange([4, 5]);
+        let mut seen = Vec::new();
+        t.slice_mut(s![1..;2, ..;-2]).map_inplace(|x| {
+            seen.push(*x);
+            *x = -*x;
+        });
+        assert_eq!(seen, [9.0, 7.0, 5.0, 19.0, 17.0, 15.0]);
+        let expected = from_fn([4, 5], |i| {
+            let x = (i[0] * 5 + i[1]) as f64;
+            if i[0] % 2 == 1 && i[1] % 2 == 0 {
+                -x
+            } else {
+                x
+            }
+        });
+        assert_eq!(to_vec(&t), to_vec(&expected));
+    }
+
+    #[test]
+    fn mapv_inplace_unshares_storage() {
+        let shared = arange([2, 3]).into_shared();
+        let mut t = shared.clone();
+        t.mapv_inplace(|x| 2.0 * x + 1.0);
+        assert_eq!(to_vec(&t), [1.0, 3.0, 5.0, 7.0, 9.0, 11.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+}
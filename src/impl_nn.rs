@@ -0,0 +1,263 @@
+//! Neural-network layers over an axis.
+//!
+//! `softmax` and `log_softmax` work along any axis, while `layer_norm` and
+//! `rms_norm` normalise along the last one. Each lane is handed to a fused
+//! backend kernel that reads the input through its strides and writes the
+//! output directly, without temporaries. The activations `relu`, `gelu` and
+//! `silu` are element-wise and live with the other float functions.
+
+use num_traits::Float;
+
+use crate::{
+    backend::Backend,
+    dimension::{check_axis, strides_as_isize, Dimensions, Dims1},
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    index::Axis,
+    storage::{traits::Storage, OwnedStorage},
+    strided,
+    tensor::TensorBase,
+    tensor_view::TensorView,
+};
+
+/// The pointer and increment of an optional affine parameter.
+fn affine_param<T, B>(param: &Option<TensorView<'_, T, B, Dims1>>, len: usize) -> OmniResult<Option<(*const T, isize)>>
+where
+    B: Backend,
+{
+    match param {
+        Some(p) if p.dims[0] != len => Err(ShapeError::IncompatibleShape.into()),
+        Some(p) => Ok(Some((p.ptr.as_ptr() as *const T, p.strides[0] as isize))),
+        None => Ok(None),
+    }
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem + Float,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Allocates the output and calls `f` with the input and output of each
+    /// lane along `axis`, as pointers to their first elements and increments.
+    fn map_lanes<F>(&self, axis: usize, mut f: F) -> TensorBase<OwnedStorage<T, B>, D>
+    where
+        F: FnMut(*const T, isize, *mut T, isize),
+    {
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, D>::uninit(self.dims.clone(), self.storage.backend());
+            if self.dims[axis] != 0 {
+                let mut dims = self.dims.clone();
+                dims[axis] = 1;
+                let strides = strides_as_isize(self.strides.as_slice());
+                let out_strides = strides_as_isize(out.strides.as_slice());
+                strided::zip2(
+                    dims.as_slice(),
+                    self.ptr.as_ptr(),
+                    strides,
+                    out.ptr.as_ptr(),
+                    out_strides,
+                    |x, y| f(x, strides[axis], y, out_strides[axis]),
+                );
+            }
+            out
+        }
+    }
+
+    fn softmax_impl(&self, axis: Axis, log: bool) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        let axis = check_axis(axis, self.ndim())?;
+        let backend = self.storage.backend();
+        let n = self.dims[axis];
+        Ok(self.map_lanes(axis, |x, incx, y, incy| unsafe { backend.softmax(n, x, incx, y, incy, log) }))
+    }
+
+    /// Softmax along `axis`, computed stably by subtracting the maximum of
+    /// each lane first.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn softmax(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        self.softmax_impl(axis, false)
+    }
+
+    /// Logarithm of the softmax along `axis`, computed directly as
+    /// `x - max - ln(sum(exp(x - max)))` rather than as the log of a softmax.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn log_softmax(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        self.softmax_impl(axis, true)
+    }
+
+    /// Layer normalisation over the last axis: each lane is shifted to zero
+    /// mean and scaled to unit variance, with `eps` added to the variance,
+    /// then multiplied by `weight` and offset by `bias` if given.
+    ///
+    /// **Errors** with `IncompatibleShape` if `self` is 0-D, or if `weight`
+    /// or `bias` does not have the length of the last axis.
+    pub fn layer_norm(
+        &self,
+        weight: Option<TensorView<'_, T, B, Dims1>>,
+        bias: Option<TensorView<'_, T, B, Dims1>>,
+        eps: T,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        let ndim = self.ndim();
+        if ndim == 0 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let n = self.dims[ndim - 1];
+        let (weight, bias) = (affine_param(&weight, n)?, affine_param(&bias, n)?);
+        let backend = self.storage.backend();
+        Ok(self.map_lanes(ndim - 1, |x, incx, y, incy| unsafe {
+            backend.layer_norm(n, x, incx, weight, bias, eps, y, incy)
+        }))
+    }
+
+    /// RMS normalisation over the last axis: each lane is divided by its
+    /// root mean square, with `eps` added to the mean square, then
+    /// multiplied by `weight` and offset by `bias` if given.
+    ///
+    /// **Errors** like [`layer_norm`](Self::layer_norm).
+    pub fn rms_norm(
+        &self,
+        weight: Option<TensorView<'_, T, B, Dims1>>,
+        bias: Option<TensorView<'_, T, B, Dims1>>,
+        eps: T,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        let ndim = self.ndim();
+        if ndim == 0 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let n = self.dims[ndim - 1];
+        let (weight, bias) = (affine_param(&weight, n)?, affine_param(&bias, n)?);
+        let backend = self.storage.backend();
+        Ok(self.map_lanes(ndim - 1, |x, incx, y, incy| unsafe {
+            backend.rms_norm(n, x, incx, weight, bias, eps, y, incy)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::CpuBackend,
+        dimension::{Dims2, Dims3},
+        error::OmniError,
+        index::Ix,
+        s,
+        test_util::*,
+    };
+
+    type View<'a, D> = TensorView<'a, f64, CpuBackend, D>;
+
+    /// Applies `f` to every lane along `axis` of `v`, writing the results in
+    /// row-major order of `v`.
+    fn map_lanes_reference(v: &View<'_, Dims3>, axis: usize, f: impl Fn(&[f64]) -> Vec<f64>) -> Vec<f64> {
+        let shape = v.shape().as_slice().to_vec();
+        let elems = to_vec(v);
+        let inner: Ix = shape[axis + 1..].iter().product();
+        let len = shape[axis];
+        let mut out = vec![0.0; elems.len()];
+        for start in (0..elems.len()).filter(|k| (k / inner).is_multiple_of(len)) {
+            let lane: Vec<f64> = (0..len).map(|a| elems[start + a * inner]).collect();
+            for (a, y) in f(&lane).into_iter().enumerate() {
+                out[start + a * inner] = y;
+            }
+        }
+        out
+    }
+
+    fn softmax(lane: &[f64]) -> Vec<f64> {
+        let sum: f64 = lane.iter().map(|x| x.exp()).sum();
+        lane.iter().map(|x| x.exp() / sum).collect()
+    }
+
+    fn normalize(lane: &[f64], center: bool, eps: f64) -> Vec<f64> {
+        let n = lane.len() as f64;
+        let mean = if center { lane.iter().sum::<f64>() / n } else { 0.0 };
+        let var = lane.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n;
+        lane.iter().map(|x| (x - mean) / (var + eps).sqrt()).collect()
+    }
+
+    fn views(t: &Tensor<f64, Dims3>) -> [View<'_, Dims3>; 3] {
+        [
+            t.view(),
+            t.slice(s![..;-1, .., 1..;2]),
+            t.view().permuted_axes([1, 2, 0]).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn softmax_matches_reference() {
+        let t = noise([3, 4, 5]);
+        for v in &views(&t) {
+            for axis in 0..3 {
+                let expected = map_lanes_reference(v, axis, softmax);
+                assert_close(&to_vec(&v.softmax(Axis(axis)).unwrap()), &expected);
+                let logs: Vec<f64> = expected.iter().map(|x| x.ln()).collect();
+                assert_close(&to_vec(&v.log_softmax(Axis(axis)).unwrap()), &logs);
+            }
+        }
+        assert!(matches!(
+            t.softmax(Axis(3)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 3))),
+        ));
+        assert_eq!(arange([2, 0]).log_softmax(Axis(1)).unwrap().shape().as_slice(), &[2, 0]);
+    }
+
+    #[test]
+    fn softmax_is_stable() {
+        let t = from_fn([2, 3], |i| [1000.0, -1000.0][i[0]] + i[1] as f64);
+        let expected = softmax(&[0.0, 1.0, 2.0]);
+        assert_close(&to_vec(&t.softmax(Axis(1)).unwrap()), &[expected.clone(), expected].concat());
+        let columns = to_vec(&t.softmax(Axis(0)).unwrap());
+        assert_eq!(columns, [1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        let logs = to_vec(&t.log_softmax(Axis(0)).unwrap());
+        assert_close(&logs, &[0.0, 0.0, 0.0, -2000.0, -2000.0, -2000.0]);
+    }
+
+    #[test]
+    fn norms_match_reference() {
+        let t = noise([3, 4, 5]);
+        let params = noise([2, 6]);
+        for v in &views(&t) {
+            let n = v.shape().as_slice()[2];
+            // Strided affine parameters.
+            let weight = params.slice(s![0, ..n]);
+            let bias = params.slice(s![1, ..;-1]);
+            let bias = bias.slice(s![..n]);
+            for center in [false, true] {
+                let norm = |v: &View<'_, Dims3>, w, b| {
+                    if center {
+                        v.layer_norm(w, b, 1e-3).unwrap()
+                    } else {
+                        v.rms_norm(w, b, 1e-3).unwrap()
+                    }
+                };
+                let expected = map_lanes_reference(v, 2, |lane| normalize(lane, center, 1e-3));
+                assert_close(&to_vec(&norm(v, None, None)), &expected);
+                let affine: Vec<f64> = expected
+                    .iter()
+                    .enumerate()
+                    .map(|(k, y)| y * weight[[k % n]] + bias[[k % n]])
+                    .collect();
+                assert_close(&to_vec(&norm(v, Some(weight.clone()), Some(bias.clone()))), &affine);
+            }
+        }
+    }
+
+    #[test]
+    fn norm_errors() {
+        let t: Tensor<f64, Dims2> = noise([2, 3]);
+        let short = arange([2]);
+        assert!(matches!(
+            t.layer_norm(Some(short.view()), None, 1e-5),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(t.rms_norm(None, Some(short.view()), 1e-5).is_err());
+        let lanes = to_vec(&t.layer_norm(None, None, 0.0).unwrap());
+        for lane in lanes.chunks(3) {
+            assert_close(&[lane.iter().sum::<f64>()], &[0.0]);
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Element-wise arithmetic operators with NumPy-style broadcasting.
+//!
+//! Every operator is implemented for owned, shared and view tensors, by value
+//! and by reference. The result is always an owned tensor whose dimensionality
+//! is `DimsMaxOf<D, E>`. When the left operand is passed by value, owns its
+//! buffer and already has the broadcast shape, that buffer is reused for the
+//! result instead of allocating a new one.
+
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use crate::{
+    backend::Backend,
+    dimension::{
+        broadcast::co_broadcast,
+        dims_from_slice,
+        Dimensions,
+        DimsMax,
+        DimsMaxOf,
+        strides_as_isize,
+    },
+    elem::Elem,
+    storage::{traits::{RawStorage, Storage}, OwnedStorage},
+    strided,
+    tensor::TensorBase,
+};
+
+fn broadcast_dims<D, E>(lhs: &D, rhs: &E) -> DimsMaxOf<D, E>
+where
+    D: Dimensions + DimsMax<E>,
+    E: Dimensions,
+{
+    co_broadcast(lhs, rhs).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Applies `f` to each pair of broadcast elements of `lhs` and `rhs`, storing
+/// the results in a new standard-layout tensor.
+fn zip_map<T, U, V, B, S, S2, D, E, F>(
+    lhs: &TensorBase<S, D>,
+    rhs: &TensorBase<S2, E>,
+    f: F,
+) -> TensorBase<OwnedStorage<V, B>, DimsMaxOf<D, E>>
+where
+    T: Copy,
+    U: Copy,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    S2: Storage<Elem = U, Backend = B>,
+    D: Dimensions + DimsMax<E>,
+    E: Dimensions,
+    F: Fn(T, U) -> V,
+{
+    let dims = broadcast_dims(&lhs.dims, &rhs.dims);
+    let lhs = lhs.broadcast_view(&dims).unwrap();
+    let rhs = rhs.broadcast_view(&dims).unwrap();
+    unsafe {
+        let out = TensorBase::<OwnedStorage<V, B>, _>::uninit(dims, lhs.storage.backend());
+        strided::zip3(
+            out.dims.as_slice(),
+            lhs.ptr.as_ptr(),
+            strides_as_isize(lhs.strides.as_slice()),
+            rhs.ptr.as_ptr(),
+            strides_as_isize(rhs.strides.as_slice()),
+            out.ptr.as_ptr(),
+            strides_as_isize(out.strides.as_slice()),
+            |a, b, out| out.write(f(*a, *b)),
+        );
+        out
+    }
+}
+
+/// Like [`zip_map`], but writes the results into the buffer of `lhs` when it
+/// can be taken over without copying and does not need to grow.
+fn zip_map_owned<T, U, B, S, S2, D, E, F>(
+    lhs: TensorBase<S, D>,
+    rhs: &TensorBase<S2, E>,
+    f: F,
+) -> TensorBase<OwnedStorage<T, B>, DimsMaxOf<D, E>>
+where
+    T: Copy,
+    U: Copy,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    S2: Storage<Elem = U, Backend = B>,
+    D: Dimensions + DimsMax<E>,
+    E: Dimensions,
+    F: Fn(T, U) -> T,
+{
+    let dims = broadcast_dims(&lhs.dims, &rhs.dims);
+    if dims.as_slice() != lhs.dims.as_slice() {
+        return zip_map(&lhs, rhs, f);
+    }
+    let out = match S::try_into_owned_nocopy(lhs) {
+        Ok(out) => out,
+        Err(lhs) => return zip_map(&lhs, rhs, f),
+    };
+    let rhs = rhs.broadcast_view(&dims).unwrap();
+    let strides: DimsMaxOf<D, E> = dims_from_slice(out.strides.as_slice());
+    unsafe {
+        strided::zip2(
+            dims.as_slice(),
+            out.ptr.as_ptr(),
+            strides_as_isize(strides.as_slice()),
+            rhs.ptr.as_ptr(),
+            strides_as_isize(rhs.strides.as_slice()),
+            |a, b| *a = f(*a, *b),
+        );
+    }
+    TensorBase {
+        storage: out.storage,
+        ptr: out.ptr,
+        dims,
+        strides,
+    }
+}
+
+macro_rules! impl_binary_op {
+    ($trt:ident, $mth:ident, $op:tt, $doc:expr) => {
+        #[doc = concat!("Element-wise ", $doc, " with broadcasting, reusing the buffer of `self` when possible.")]
+        ///
+        /// **Panics** if the shapes cannot be broadcast together.
+        impl<T, U, B, S, S2, D, E> $trt<TensorBase<S2, E>> for TensorBase<S, D>
+        where
+            T: Elem + $trt<U, Output = T>,
+            U: Elem,
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            S2: Storage<Elem = U, Backend = B>,
+            D: Dimensions + DimsMax<E>,
+            E: Dimensions,
+        {
+            type Output = TensorBase<OwnedStorage<T, B>, DimsMaxOf<D, E>>;
+
+            fn $mth(self, rhs: TensorBase<S2, E>) -> Self::Output {
+                zip_map_owned(self, &rhs, |a, b| a $op b)
+            }
+        }
+
+        #[doc = concat!("Element-wise ", $doc, " with broadcasting, reusing the buffer of `self` when possible.")]
+        ///
+        /// **Panics** if the shapes cannot be broadcast together.
+        impl<T, U, B, S, S2, D, E> $trt<&TensorBase<S2, E>> for TensorBase<S, D>
+        where
+            T: Elem + $trt<U, Output = T>,
+            U: Elem,
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            S2: Storage<Elem = U, Backend = B>,
+            D: Dimensions + DimsMax<E>,
+            E: Dimensions,
+        {
+            type Output = TensorBase<OwnedStorage<T, B>, DimsMaxOf<D, E>>;
+
+            fn $mth(self, rhs: &TensorBase<S2, E>) -> Self::Output {
+                zip_map_owned(self, rhs, |a, b| a $op b)
+            }
+        }
+
+        #[doc = concat!("Element-wise ", $doc, " with broadcasting into a new tensor.")]
+        ///
+        /// **Panics** if the shapes cannot be broadcast together.
+        impl<T, U, B, S, S2, D, E> $trt<TensorBase<S2, E>> for &TensorBase<S, D>
+        where
+            T: Elem + $trt<U, Output = T>,
+            U: Elem,
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            S2: Storage<Elem = U, Backend = B>,
+            D: Dimensions + DimsMax<E>,
+            E: Dimensions,
+        {
+            type Output = TensorBase<OwnedStorage<T, B>, DimsMaxOf<D, E>>;
+
+            fn $mth(self, rhs: TensorBase<S2, E>) -> Self::Output {
+                zip_map(self, &rhs, |a, b| a $op b)
+            }
+        }
+
+        #[doc = concat!("Element-wise ", $doc, " with broadcasting into a new tensor.")]
+        ///
+        /// **Panics** if the shapes cannot be broadcast together.
+        impl<T, U, B, S, S2, D, E> $trt<&TensorBase<S2, E>> for &TensorBase<S, D>
+        where
+            T: Elem + $trt<U, Output = T>,
+            U: Elem,
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            S2: Storage<Elem = U, Backend = B>,
+            D: Dimensions + DimsMax<E>,
+            E: Dimensions,
+        {
+            type Output = TensorBase<OwnedStorage<T, B>, DimsMaxOf<D, E>>;
+
+            fn $mth(self, rhs: &TensorBase<S2, E>) -> Self::Output {
+                zip_map(self, rhs, |a, b| a $op b)
+            }
+        }
+    };
+}
+
+impl_binary_op!(Add, add, +, "addition");
+impl_binary_op!(Sub, sub, -, "subtraction");
+impl_binary_op!(Mul, mul, *, "multiplication");
+impl_binary_op!(Div, div, /, "division");
+impl_binary_op!(Rem, rem, %, "remainder");
+
+/// Element-wise negation, reusing the buffer of `self` when possible.
+impl<T, B, S, D> Neg for TensorBase<S, D>
+where
+    T: Elem + Neg<Output = T>,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Output = TensorBase<OwnedStorage<T, B>, D>;
+
+    fn neg(self) -> Self::Output {
+        match S::try_into_owned_nocopy(self) {
+            Ok(out) => {
+                unsafe {
+                    strided::zip1(
+                        out.dims.as_slice(),
+                        out.ptr.as_ptr(),
+                        strides_as_isize(out.strides.as_slice()),
+                        |a| *a = -*a,
+                    );
+                }
+                out
+            },
+            Err(this) => -&this,
+        }
+    }
+}
+
+/// Element-wise negation into a new tensor.
+impl<T, B, S, D> Neg for &TensorBase<S, D>
+where
+    T: Elem + Neg<Output = T>,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Output = TensorBase<OwnedStorage<T, B>, D>;
+
+    fn neg(self) -> Self::Output {
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, _>::uninit(
+                self.dims.clone(),
+                self.storage.backend(),
+            );
+            strided::zip2(
+                out.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+                |a, out| out.write(-*a),
+            );
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dimension::{Dimensions, DynDims},
+        index::Ix,
+        s,
+        test_util::*,
+    };
+
+    /// The value the operand tests put at `index`, distinct for every
+    /// position and never zero.
+    fn value(index: &[Ix], salt: f64) -> f64 {
+        index.iter().fold(salt, |acc, &i| acc * 5.0 + i as f64) + 0.25
+    }
+
+    /// The index into an operand of shape `shape` that broadcasts to `index`.
+    fn broadcast_index(index: &[Ix], shape: &[Ix]) -> Vec<Ix> {
+        let skip = index.len() - shape.len();
+        shape.iter().zip(&index[skip..]).map(|(&len, &i)| if len == 1 { 0 } else { i }).collect()
+    }
+
+    /// The broadcast of `op` over operands of shapes `a` and `b`, computed
+    /// element by element.
+    fn reference(a: &[Ix], b: &[Ix], out: &[Ix], op: fn(f64, f64) -> f64) -> Vec<f64> {
+        to_vec(&from_fn(out.to_vec(), |i| {
+            op(value(&broadcast_index(i, a), 1.0), value(&broadcast_index(i, b), 2.0))
+        }))
+    }
+
+    fn operand(shape: &[Ix], salt: f64) -> Tensor<f64, DynDims> {
+        from_fn(shape.to_vec(), |i| value(i, salt))
+    }
+
+    #[test]
+    fn broadcasting_matches_reference() {
+        let cases: [(&[Ix], &[Ix], &[Ix]); 7] = [
+            (&[2, 3], &[2, 3], &[2, 3]),
+            (&[2, 3, 4], &[3, 1], &[2, 3, 4]),
+            (&[4, 1], &[1, 3], &[4, 3]),
+            (&[3], &[2, 1, 3], &[2, 1, 3]),
+            (&[1], &[2, 2], &[2, 2]),
+            (&[], &[3, 2], &[3, 2]),
+            (&[0, 3], &[1, 3], &[0, 3]),
+        ];
+        for (a_shape, b_shape, out_shape) in cases {
+            let (a, b) = (operand(a_shape, 1.0), operand(b_shape, 2.0));
+            for name in ["+", "-", "*", "/"] {
+                let (op, results): (fn(f64, f64) -> f64, _) = match name {
+                    "+" => (|x, y| x + y, [&a + &b, a.clone() + &b, &a + b.clone(), a.clone() + b.clone()]),
+                    "-" => (|x, y| x - y, [&a - &b, a.clone() - &b, &a - b.clone(), a.clone() - b.clone()]),
+                    "*" => (|x, y| x * y, [&a * &b, a.clone() * &b, &a * b.clone(), a.clone() * b.clone()]),
+                    _ => (|x, y| x / y, [&a / &b, a.clone() / &b, &a / b.clone(), a.clone() / b.clone()]),
+                };
+                let expected = reference(a_shape, b_shape, out_shape, op);
+                for result in results {
+                    assert_eq!(result.shape().as_slice(), out_shape, "{a_shape:?} {name} {b_shape:?}");
+                    assert_eq!(to_vec(&result), expected, "{a_shape:?} {name} {b_shape:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_dimensionality_and_strided_operands() {
+        let a = arange([3, 4]);
+        let col = arange([3, 1]);
+        let row = arange([4]);
+        let sum = &a.view().reversed_axes() + &col.view().reversed_axes();
+        let expected = from_fn([4, 3], |i| (i[1] * 4 + i[0]) as f64 + i[1] as f64);
+        assert_eq!(to_vec(&sum), to_vec(&expected));
+
+        let diff = a.slice(s![..;-1, 1..;2]) - &row.slice(s![..2]);
+        let expected = from_fn([3, 2], |i| ((2 - i[0]) * 4 + 1 + 2 * i[1]) as f64 - i[1] as f64);
+        assert_eq!(to_vec(&diff), to_vec(&expected));
+
+        let product = &row * &a.into_dyn();
+        let expected = from_fn([3, 4], |i| (i[1] * (i[0] * 4 + i[1])) as f64);
+        assert_eq!(product.shape().as_slice(), &[3, 4]);
+        assert_eq!(to_vec(&product), to_vec(&expected));
+    }
+
+    #[test]
+    fn integer_remainder() {
+        let a = from_fn([2, 3], |i| (i[0] * 3 + i[1]) as i32 - 3);
+        let b = from_fn([3], |i| i[0] as i32 + 2);
+        assert_eq!(to_vec(&(&a % &b)), [-1, -2, -1, 0, 1, 2]);
+        assert_eq!(to_vec(&(a % b)), [-1, -2, -1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn owned_lhs_buffer_is_reused() {
+        let a = arange([2, 3]);
+        let ptr = a.as_ptr();
+        let sum = a + &arange([3]);
+        assert_eq!(sum.as_ptr(), ptr);
+        assert_eq!(to_vec(&sum), [0.0, 2.0, 4.0, 3.0, 5.0, 7.0]);
+
+        // The buffer keeps its transposed layout.
+        let t = arange([3, 2]).reversed_axes();
+        let ptr = t.as_ptr();
+        let sum = t + &arange([3]);
+        assert_eq!(sum.as_ptr(), ptr);
+        assert_eq!(to_vec(&sum), [0.0, 3.0, 6.0, 1.0, 4.0, 7.0]);
+
+        // A left operand that has to grow gets a new buffer.
+        let row = arange([3]);
+        let ptr = row.as_ptr();
+        let sum = row + arange([2, 3]);
+        assert_ne!(sum.as_ptr(), ptr);
+        assert_eq!(to_vec(&sum), [0.0, 2.0, 4.0, 3.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn shared_lhs_is_not_written_while_aliased() {
+        let shared = arange([2, 2]).into_shared();
+        let alias = shared.clone();
+        let sum = shared + &arange([2]);
+        assert_eq!(to_vec(&sum), [0.0, 2.0, 2.0, 4.0]);
+        assert_eq!(to_vec(&alias), [0.0, 1.0, 2.0, 3.0]);
+
+        let neg = -alias.clone();
+        assert_eq!(to_vec(&neg), [-0.0, -1.0, -2.0, -3.0]);
+        assert_eq!(to_vec(&alias), [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn negation() {
+        let a = arange([2, 3]);
+        let expected = [-0.0, -3.0, -1.0, -4.0, -2.0, -5.0];
+        assert_eq!(to_vec(&-&a.view().reversed_axes()), expected);
+        assert_eq!(to_vec(&-a.view().reversed_axes()), expected);
+        let ptr = a.as_ptr();
+        let neg = -a;
+        assert_eq!(neg.as_ptr(), ptr);
+        assert_eq!(to_vec(&neg), [-0.0, -1.0, -2.0, -3.0, -4.0, -5.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn incompatible_shapes_panic() {
+        let _ = &arange([2, 3]) + &arange([2]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Reordering the axes of a tensor.
+//!
+//! All of these only rearrange `dims` and `strides`; the elements are never
+//! moved.
+
+use crate::{
+    backend::Backend,
+    dimension::{check_axis, Dimensions, IntoDimension},
+    error::{OmniResult, ShapeError},
+    index::Axis,
+    storage::traits::RawStorage,
+    tensor::TensorBase,
+    tensor_view::TensorView,
+};
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Returns a transposed view, with the order of the axes reversed.
+    ///
+    /// For 2-D tensors this is the matrix transpose.
+    pub fn t(&self) -> TensorView<'_, T, B, D> {
+        self.view().reversed_axes()
+    }
+
+    /// Reverses the order of the axes.
+    pub fn reversed_axes(mut self) -> Self {
+        self.dims.as_slice_mut().reverse();
+        self.strides.as_slice_mut().reverse();
+        self
+    }
+
+    /// Reorders the axes so that axis `i` of the result is axis `axes[i]` of
+    /// `self`.
+    ///
+    /// **Errors** with `InvalidPermutation` unless `axes` holds every axis of
+    /// the tensor exactly once.
+    pub fn permuted_axes<A>(self, axes: A) -> OmniResult<Self>
+    where
+        A: IntoDimension<Dims = D>,
+    {
+        let axes = axes.into_dimension();
+        let ndim = self.ndim();
+        let mut seen = vec![false; ndim];
+        let is_permutation = axes.ndim() == ndim
+            && axes.as_slice().iter().all(|&axis| {
+                axis < ndim && !std::mem::replace(&mut seen[axis], true)
+            });
+        if !is_permutation {
+            return Err(ShapeError::InvalidPermutation(axes.as_slice().to_vec()).into());
+        }
+
+        let mut dims = self.dims.clone();
+        let mut strides = self.strides.clone();
+        for (i, &axis) in axes.as_slice().iter().enumerate() {
+            dims[i] = self.dims[axis];
+            strides[i] = self.strides[axis];
+        }
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims,
+            strides,
+        })
+    }
+
+    /// Swaps axes `a` and `b` in place.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if either axis does not exist.
+    pub fn swap_axes(&mut self, a: Axis, b: Axis) -> OmniResult<()> {
+        let a = check_axis(a, self.ndim())?;
+        let b = check_axis(b, self.ndim())?;
+        self.dims.as_slice_mut().swap(a, b);
+        self.strides.as_slice_mut().swap(a, b);
+        Ok(())
+    }
+
+    /// Moves axis `source` to position `destination`, keeping the relative
+    /// order of the other axes. For example, moving axis 1 to position 3
+    /// turns an NCHW tensor into NHWC.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if either axis does not exist.
+    pub fn moveaxis(mut self, source: Axis, destination: Axis) -> OmniResult<Self> {
+        let source = check_axis(source, self.ndim())?;
+        let destination = check_axis(destination, self.ndim())?;
+        if source < destination {
+            self.dims.as_slice_mut()[source..=destination].rotate_left(1);
+            self.strides.as_slice_mut()[source..=destination].rotate_left(1);
+        } else {
+            self.dims.as_slice_mut()[destination..=source].rotate_right(1);
+            self.strides.as_slice_mut()[destination..=source].rotate_right(1);
+        }
+        Ok(self)
+    }
+
+    /// Whether the elements are laid out in row-major ("C") order without
+    /// gaps.
+    pub fn is_standard_layout(&self) -> bool {
+        D::is_standard_layout(&self.dims, &self.strides)
+    }
+
+    /// Whether the elements are laid out in column-major ("F") order without
+    /// gaps.
+    pub fn is_f_contiguous(&self) -> bool {
+        D::is_fortran_layout(&self.dims, &self.strides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dimension::Dimensions,
+        error::{OmniError, ShapeError},
+        index::Axis,
+        s,
+        test_util::*,
+    };
+
+    #[test]
+    fn transpose() {
+        let t = arange([2, 3]);
+        let v = t.t();
+        assert_eq!(v.shape().as_slice(), &[3, 2]);
+        assert_eq!(to_vec(&v), [0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+        assert!(v.is_f_contiguous());
+        assert!(!v.is_standard_layout());
+    }
+
+    #[test]
+    fn permute_swap_and_move() {
+        let t = arange([2, 3, 4]);
+        let p = t.view().permuted_axes([2, 0, 1]).unwrap();
+        assert_eq!(p.shape().as_slice(), &[4, 2, 3]);
+        assert_eq!(to_vec(&p), to_vec(&from_fn([4, 2, 3], |i| (i[1] * 12 + i[2] * 4 + i[0]) as f64)));
+
+        let mut w = t.view();
+        w.swap_axes(Axis(0), Axis(2)).unwrap();
+        assert_eq!(to_vec(&w), to_vec(&from_fn([4, 3, 2], |i| (i[2] * 12 + i[1] * 4 + i[0]) as f64)));
+
+        // NCHW -> NHWC and back.
+        let nchw = arange([2, 3, 4, 5]);
+        let nhwc = nchw.view().moveaxis(Axis(1), Axis(3)).unwrap();
+        assert_eq!(nhwc.shape().as_slice(), &[2, 4, 5, 3]);
+        assert_eq!(nhwc[(1, 2, 3, 0)], nchw[(1, 0, 2, 3)]);
+        let back = nhwc.moveaxis(Axis(3), Axis(1)).unwrap();
+        assert!(back.is_standard_layout());
+        assert_eq!(to_vec(&back), to_vec(&nchw));
+    }
+
+    #[test]
+    fn invalid_axes() {
+        let t = arange([2, 3, 4]);
+        assert!(matches!(
+            t.view().permuted_axes([0, 0, 1]),
+            Err(OmniError::ShapeError(ShapeError::InvalidPermutation(_))),
+        ));
+        assert!(matches!(
+            t.view().permuted_axes([0, 1, 3]),
+            Err(OmniError::ShapeError(ShapeError::InvalidPermutation(_))),
+        ));
+        assert!(matches!(
+            t.view().moveaxis(Axis(3), Axis(0)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 3))),
+        ));
+    }
+
+    #[test]
+    fn layout_queries() {
+        let t = arange([2, 3, 4]);
+        assert!(t.is_standard_layout());
+        assert!(!t.is_f_contiguous());
+        assert!(t.view().reversed_axes().is_f_contiguous());
+
+        // Axes of length 1 may have any stride.
+        let column = arange([3, 1]);
+        assert!(column.is_standard_layout() && column.is_f_contiguous());
+        let v = t.slice(s![.., 1..2, ..]);
+        assert!(!v.is_standard_layout());
+        let v = t.slice(s![1..2, .., ..]);
+        assert!(v.is_standard_layout());
+
+        // Gaps and reversed axes are neither.
+        let v = t.slice(s![.., .., ..;2]);
+        assert!(!v.is_standard_layout() && !v.is_f_contiguous());
+        let v = t.slice(s![.., ..;-1, ..]);
+        assert!(!v.is_standard_layout() && !v.is_f_contiguous());
+        let v = t.view().permuted_axes([1, 0, 2]).unwrap();
+        assert!(!v.is_standard_layout() && !v.is_f_contiguous());
+    }
+}
@@ -0,0 +1,762 @@
+//! Reductions over all elements or along one axis.
+//!
+//! Every reduction reads the tensor through its strides, so views never have
+//! to be copied first. The per-axis forms return tensors typed by
+//! `D::Smaller`, and their `_keepdims` variants keep the reduced axis with
+//! length 1.
+//!
+//! `min` and `max` propagate NaN: if any of the reduced elements is NaN, so
+//! is the result. Likewise `argmin` and `argmax` pick the first NaN, and
+//! otherwise the first occurrence of the extreme value, matching NumPy.
+
+use std::cmp::Ordering;
+
+use num_traits::FromPrimitive;
+
+use crate::{
+    backend::Backend,
+    dimension::{check_axis, strides_as_isize, Dimensions},
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    index::{Axis, Ix},
+    storage::{traits::Storage, OwnedStorage},
+    strided,
+    tensor::TensorBase,
+};
+
+/// Whether `x` is unordered with respect to itself, i.e. NaN.
+fn is_nan<T: PartialOrd>(x: &T) -> bool {
+    x.partial_cmp(x).is_none()
+}
+
+fn min_nan<T: PartialOrd>(a: T, b: T) -> T {
+    match a.partial_cmp(&b) {
+        Some(Ordering::Greater) => b,
+        Some(_) => a,
+        None if is_nan(&a) => a,
+        None => b,
+    }
+}
+
+fn max_nan<T: PartialOrd>(a: T, b: T) -> T {
+    match a.partial_cmp(&b) {
+        Some(Ordering::Less) => b,
+        Some(_) => a,
+        None if is_nan(&a) => a,
+        None => b,
+    }
+}
+
+/// Whether `x` should replace `acc` as the minimum, preferring the earlier
+/// element on ties and NaN over any number.
+fn is_less_nan<T: PartialOrd>(x: &T, acc: &T) -> bool {
+    x < acc || (is_nan(x) && !is_nan(acc))
+}
+
+/// Whether `x` should replace `acc` as the maximum, preferring the earlier
+/// element on ties and NaN over any number.
+pub(crate) fn is_greater_nan<T: PartialOrd>(x: &T, acc: &T) -> bool {
+    x > acc || (is_nan(x) && !is_nan(acc))
+}
+
+/// Whether the lanes along `axis` are best walked in an inner loop, which is
+/// the case when `axis` varies fastest in memory.
+fn lane_is_fastest(dims: &[Ix], strides: &[isize], axis: usize) -> bool {
+    (0..dims.len())
+        .filter(|&i| i != axis && dims[i] > 1)
+        .all(|i| strides[i].unsigned_abs() >= strides[axis].unsigned_abs())
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Folds every element into `init` with `f`.
+    fn fold<F>(&self, init: T, f: F) -> T
+    where
+        F: Fn(T, T) -> T,
+    {
+        let mut acc = init;
+        unsafe {
+            strided::zip1(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |x| acc = f(acc, *x),
+            );
+        }
+        acc
+    }
+
+    /// Folds the lanes along `axis` into a new tensor in which `axis` has
+    /// length 1. Each lane starts from `init`, or from its first element if
+    /// `init` is `None`.
+    fn fold_axis_keepdims<F>(
+        &self,
+        axis: Axis,
+        init: Option<T>,
+        f: F,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        F: Fn(T, T) -> T,
+    {
+        let axis = check_axis(axis, self.ndim())?;
+        let len = self.dims[axis];
+        if init.is_none() && len == 0 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+
+        let mut dims = self.dims.clone();
+        dims[axis] = 1;
+        let src_strides = strides_as_isize(self.strides.as_slice());
+        let stride = src_strides[axis];
+        let src = self.ptr.as_ptr();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, _>::uninit(dims, self.storage.backend());
+            let out_strides = strides_as_isize(out.strides.as_slice());
+            let start = match init {
+                Some(init) => {
+                    strided::zip1(out.dims.as_slice(), out.ptr.as_ptr(), out_strides, |o| {
+                        o.write(init)
+                    });
+                    0
+                },
+                None => {
+                    strided::zip2(
+                        out.dims.as_slice(),
+                        out.ptr.as_ptr(),
+                        out_strides,
+                        src,
+                        src_strides,
+                        |o, x| o.write(*x),
+                    );
+                    1
+                },
+            };
+
+            // Walk each lane in an inner loop when `axis` varies fastest in
+            // memory, and sweep over whole slices otherwise.
+            if lane_is_fastest(self.dims.as_slice(), src_strides, axis) {
+                strided::zip2(
+                    out.dims.as_slice(),
+                    out.ptr.as_ptr(),
+                    out_strides,
+                    src,
+                    src_strides,
+                    |o, x| {
+                        let mut acc = *o;
+                        for k in start..len {
+                            acc = f(acc, *x.wrapping_offset(k as isize * stride));
+                        }
+                        *o = acc;
+                    },
+                );
+            } else {
+                for k in start..len {
+                    strided::zip2(
+                        out.dims.as_slice(),
+                        out.ptr.as_ptr(),
+                        out_strides,
+                        src.wrapping_offset(k as isize * stride),
+                        src_strides,
+                        |o, x| *o = f(*o, *x),
+                    );
+                }
+            }
+            Ok(out)
+        }
+    }
+
+    /// Finds the element that wins against all others under `better`, and
+    /// its index in row-major order.
+    fn fold_with_index<F>(&self, better: F) -> Option<(T, Ix)>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        if self.size() == 0 {
+            return None;
+        }
+        let mut best = unsafe { (*self.ptr.as_ptr(), 0) };
+        let mut index = 0;
+        unsafe {
+            strided::zip1(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |x| {
+                    if better(&*x, &best.0) {
+                        best = (*x, index);
+                    }
+                    index += 1;
+                },
+            );
+        }
+        Some(best)
+    }
+
+    /// Like `fold_with_index`, but for each lane along `axis`, returning the
+    /// winning values and their positions in the lane with `axis` kept at
+    /// length 1.
+    #[allow(clippy::type_complexity)]
+    fn fold_axis_with_index_keepdims<F>(
+        &self,
+        axis: Axis,
+        better: F,
+    ) -> OmniResult<(TensorBase<OwnedStorage<T, B>, D>, TensorBase<OwnedStorage<Ix, B>, D>)>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        let axis = check_axis(axis, self.ndim())?;
+        let len = self.dims[axis];
+        if len == 0 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+
+        let mut dims = self.dims.clone();
+        dims[axis] = 1;
+        let src_strides = strides_as_isize(self.strides.as_slice());
+        let stride = src_strides[axis];
+        let src = self.ptr.as_ptr();
+        let backend = self.storage.backend();
+        unsafe {
+            let values = TensorBase::<OwnedStorage<T, B>, _>::uninit(dims.clone(), backend);
+            let indices = TensorBase::<OwnedStorage<Ix, B>, _>::uninit(dims, backend);
+            let out_dims = values.dims.as_slice();
+            let value_strides = strides_as_isize(values.strides.as_slice());
+            let index_strides = strides_as_isize(indices.strides.as_slice());
+
+            if lane_is_fastest(self.dims.as_slice(), src_strides, axis) {
+                strided::zip3(
+                    out_dims,
+                    values.ptr.as_ptr(),
+                    value_strides,
+                    indices.ptr.as_ptr(),
+                    index_strides,
+                    src,
+                    src_strides,
+                    |value, index, x| {
+                        let mut best = (*x, 0);
+                        for k in 1..len {
+                            let x = *x.wrapping_offset(k as isize * stride);
+                            if better(&x, &best.0) {
+                                best = (x, k);
+                            }
+                        }
+                        value.write(best.0);
+                        index.write(best.1);
+                    },
+                );
+            } else {
+                strided::zip3(
+                    out_dims,
+                    values.ptr.as_ptr(),
+                    value_strides,
+                    indices.ptr.as_ptr(),
+                    index_strides,
+                    src,
+                    src_strides,
+                    |value, index, x| {
+                        value.write(*x);
+                        index.write(0);
+                    },
+                );
+                for k in 1..len {
+                    strided::zip3(
+                        out_dims,
+                        values.ptr.as_ptr(),
+                        value_strides,
+                        indices.ptr.as_ptr(),
+                        index_strides,
+                        src.wrapping_offset(k as isize * stride),
+                        src_strides,
+                        |value, index, x| {
+                            if better(&*x, &*value) {
+                                *value = *x;
+                                *index = k;
+                            }
+                        },
+                    );
+                }
+            }
+            Ok((values, indices))
+        }
+    }
+
+    /// Sum of all elements; zero for empty tensors.
+    pub fn sum(&self) -> T {
+        self.fold(T::zero(), |a, b| a + b)
+    }
+
+    /// Product of all elements; one for empty tensors.
+    pub fn prod(&self) -> T {
+        self.fold(T::one(), |a, b| a * b)
+    }
+
+    /// Arithmetic mean of all elements, or `None` if the tensor is empty.
+    pub fn mean(&self) -> Option<T>
+    where
+        T: FromPrimitive,
+    {
+        if self.size() == 0 {
+            return None;
+        }
+        Some(self.sum() / T::from_usize(self.size())?)
+    }
+
+    /// Smallest element, or `None` if the tensor is empty. NaN if any
+    /// element is NaN.
+    pub fn min(&self) -> Option<T>
+    where
+        T: PartialOrd,
+    {
+        if self.size() == 0 {
+            return None;
+        }
+        let first = unsafe { *self.ptr.as_ptr() };
+        Some(self.fold(first, min_nan))
+    }
+
+    /// Largest element, or `None` if the tensor is empty. NaN if any element
+    /// is NaN.
+    pub fn max(&self) -> Option<T>
+    where
+        T: PartialOrd,
+    {
+        if self.size() == 0 {
+            return None;
+        }
+        let first = unsafe { *self.ptr.as_ptr() };
+        Some(self.fold(first, max_nan))
+    }
+
+    /// Sums along `axis`, keeping it with length 1.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn sum_axis_keepdims(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        self.fold_axis_keepdims(axis, Some(T::zero()), |a, b| a + b)
+    }
+
+    /// Multiplies along `axis`, keeping it with length 1.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn prod_axis_keepdims(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>> {
+        self.fold_axis_keepdims(axis, Some(T::one()), |a, b| a * b)
+    }
+
+    /// Averages along `axis`, keeping it with length 1.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn mean_axis_keepdims(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        T: FromPrimitive,
+    {
+        let len = self.dims[check_axis(axis, self.ndim())?];
+        let n = match T::from_usize(len) {
+            Some(n) if len != 0 => n,
+            _ => return Err(ShapeError::IncompatibleShape.into()),
+        };
+        let out = self.sum_axis_keepdims(axis)?;
+        unsafe {
+            strided::zip1(
+                out.dims.as_slice(),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+                |x| *x = *x / n,
+            );
+        }
+        Ok(out)
+    }
+
+    /// Minimum along `axis`, keeping it with length 1. NaN wherever a lane
+    /// contains NaN.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn min_axis_keepdims(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        T: PartialOrd,
+    {
+        self.fold_axis_keepdims(axis, None, min_nan)
+    }
+
+    /// Maximum along `axis`, keeping it with length 1. NaN wherever a lane
+    /// contains NaN.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn max_axis_keepdims(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+    where
+        T: PartialOrd,
+    {
+        self.fold_axis_keepdims(axis, None, max_nan)
+    }
+
+    /// Sums along `axis`, removing it.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn sum_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Smaller>> {
+        self.sum_axis_keepdims(axis)?.remove_axis(axis)
+    }
+
+    /// Multiplies along `axis`, removing it.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist.
+    pub fn prod_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Smaller>> {
+        self.prod_axis_keepdims(axis)?.remove_axis(axis)
+    }
+
+    /// Averages along `axis`, removing it.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn mean_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Smaller>>
+    where
+        T: FromPrimitive,
+    {
+        self.mean_axis_keepdims(axis)?.remove_axis(axis)
+    }
+
+    /// Minimum along `axis`, removing it. NaN wherever a lane contains NaN.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn min_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Smaller>>
+    where
+        T: PartialOrd,
+    {
+        self.min_axis_keepdims(axis)?.remove_axis(axis)
+    }
+
+    /// Maximum along `axis`, removing it. NaN wherever a lane contains NaN.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn max_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Smaller>>
+    where
+        T: PartialOrd,
+    {
+        self.max_axis_keepdims(axis)?.remove_axis(axis)
+    }
+
+    /// Row-major index of the smallest element in the flattened tensor, or
+    /// `None` if the tensor is empty.
+    pub fn argmin(&self) -> Option<Ix>
+    where
+        T: PartialOrd,
+    {
+        self.fold_with_index(is_less_nan).map(|(_, index)| index)
+    }
+
+    /// Row-major index of the largest element in the flattened tensor, or
+    /// `None` if the tensor is empty.
+    pub fn argmax(&self) -> Option<Ix>
+    where
+        T: PartialOrd,
+    {
+        self.fold_with_index(is_greater_nan).map(|(_, index)| index)
+    }
+
+    /// Positions of the smallest elements along `axis`, removing it.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn argmin_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<Ix, B>, D::Smaller>>
+    where
+        T: PartialOrd,
+    {
+        Ok(self.min_with_indices(axis)?.1)
+    }
+
+    /// Positions of the largest elements along `axis`, removing it.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    pub fn argmax_axis(&self, axis: Axis) -> OmniResult<TensorBase<OwnedStorage<Ix, B>, D::Smaller>>
+    where
+        T: PartialOrd,
+    {
+        Ok(self.max_with_indices(axis)?.1)
+    }
+
+    /// Minimum along `axis` together with its position in each lane,
+    /// removing the axis from both.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    #[allow(clippy::type_complexity)]
+    pub fn min_with_indices(
+        &self,
+        axis: Axis,
+    ) -> OmniResult<(
+        TensorBase<OwnedStorage<T, B>, D::Smaller>,
+        TensorBase<OwnedStorage<Ix, B>, D::Smaller>,
+    )>
+    where
+        T: PartialOrd,
+    {
+        let (values, indices) = self.fold_axis_with_index_keepdims(axis, is_less_nan)?;
+        Ok((values.remove_axis(axis)?, indices.remove_axis(axis)?))
+    }
+
+    /// Maximum along `axis` together with its position in each lane,
+    /// removing the axis from both.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if it has length 0.
+    #[allow(clippy::type_complexity)]
+    pub fn max_with_indices(
+        &self,
+        axis: Axis,
+    ) -> OmniResult<(
+        TensorBase<OwnedStorage<T, B>, D::Smaller>,
+        TensorBase<OwnedStorage<Ix, B>, D::Smaller>,
+    )>
+    where
+        T: PartialOrd,
+    {
+        let (values, indices) = self.fold_axis_with_index_keepdims(axis, is_greater_nan)?;
+        Ok((values.remove_axis(axis)?, indices.remove_axis(axis)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::OmniError, s, test_util::*};
+
+    /// Applies `f` to every lane along `axis` of the row-major `elems`, in
+    /// row-major order of the remaining axes.
+    fn reduce_lanes(elems: &[f64], shape: &[Ix], axis: usize, f: impl Fn(&[f64]) -> f64) -> Vec<f64> {
+        let len = shape[axis];
+        let outer: Ix = shape[..axis].iter().product();
+        let inner: Ix = shape[axis + 1..].iter().product();
+        let mut out = Vec::new();
+        for o in 0..outer {
+            for i in 0..inner {
+                let lane: Vec<f64> = (0..len).map(|a| elems[(o * len + a) * inner + i]).collect();
+                out.push(f(&lane));
+            }
+        }
+        out
+    }
+
+    fn sum(lane: &[f64]) -> f64 {
+        lane.iter().sum()
+    }
+
+    fn prod(lane: &[f64]) -> f64 {
+        lane.iter().product()
+    }
+
+    fn mean(lane: &[f64]) -> f64 {
+        sum(lane) / lane.len() as f64
+    }
+
+    fn min(lane: &[f64]) -> f64 {
+        lane.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    fn max(lane: &[f64]) -> f64 {
+        lane.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    #[test]
+    fn axis_reductions_match_reference() {
+        let t = noise([3, 4, 5]);
+        let views = [
+            t.view(),
+            t.view().permuted_axes([2, 0, 1]).unwrap(),
+            t.slice(s![..;-1, 1..;2, ..]),
+            t.slice(s![.., ..;-1, ..;2]).permuted_axes([1, 2, 0]).unwrap(),
+        ];
+        for v in &views {
+            let shape = v.shape().as_slice().to_vec();
+            let elems = to_vec(v);
+            assert_close(&[v.sum()], &[sum(&elems)]);
+            assert_close(&[v.prod()], &[prod(&elems)]);
+            assert_close(&[v.mean().unwrap()], &[mean(&elems)]);
+            assert_eq!(v.min(), Some(min(&elems)));
+            assert_eq!(v.max(), Some(max(&elems)));
+
+            for axis in 0..3 {
+                let mut kept = shape.clone();
+                kept[axis] = 1;
+                let mut removed = shape.clone();
+                removed.remove(axis);
+                let a = Axis(axis);
+                let reductions = [
+                    (v.sum_axis(a).unwrap(), v.sum_axis_keepdims(a).unwrap()),
+                    (v.prod_axis(a).unwrap(), v.prod_axis_keepdims(a).unwrap()),
+                    (v.mean_axis(a).unwrap(), v.mean_axis_keepdims(a).unwrap()),
+                    (v.min_axis(a).unwrap(), v.min_axis_keepdims(a).unwrap()),
+                    (v.max_axis(a).unwrap(), v.max_axis_keepdims(a).unwrap()),
+                ];
+                let references: [fn(&[f64]) -> f64; 5] = [sum, prod, mean, min, max];
+                for ((out, out_keepdims), f) in reductions.iter().zip(references) {
+                    let expected = reduce_lanes(&elems, &shape, axis, f);
+                    assert_eq!(out.shape().as_slice(), removed.as_slice());
+                    assert_eq!(out_keepdims.shape().as_slice(), kept.as_slice());
+                    assert_close(&to_vec(out), &expected);
+                    assert_close(&to_vec(out_keepdims), &expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn broadcast_views_reduce_every_copy() {
+        let row = arange([3]);
+        let b = row.broadcast_to([4, 3]).unwrap();
+        assert_eq!(b.sum(), 12.0);
+        assert_eq!(to_vec(&b.sum_axis(Axis(0)).unwrap()), [0.0, 4.0, 8.0]);
+        assert_eq!(to_vec(&b.max_axis(Axis(1)).unwrap()), [2.0; 4]);
+        assert_eq!(to_vec(&b.prod_axis(Axis(0)).unwrap()), [0.0, 1.0, 16.0]);
+    }
+
+    #[test]
+    fn min_and_max_propagate_nan() {
+        // NaN first in a lane, last in a lane, and alone in a column.
+        let t = from_fn([3, 4], |i| match (i[0], i[1]) {
+            (0, 0) | (1, 3) => f64::NAN,
+            _ => (i[0] * 4 + i[1]) as f64,
+        });
+        assert!(t.min().unwrap().is_nan());
+        assert!(t.max().unwrap().is_nan());
+        assert_eq!(t.slice(s![2.., ..]).min(), Some(8.0));
+
+        let by_rows = [t.min_axis(Axis(1)).unwrap(), t.max_axis(Axis(1)).unwrap()];
+        let by_cols = [t.min_axis(Axis(0)).unwrap(), t.max_axis(Axis(0)).unwrap()];
+        for (rows, cols) in by_rows.iter().zip(&by_cols) {
+            let rows = to_vec(rows);
+            assert!(rows[0].is_nan() && rows[1].is_nan() && !rows[2].is_nan());
+            let cols = to_vec(cols);
+            assert!(cols[0].is_nan() && cols[3].is_nan());
+            assert!(!cols[1].is_nan() && !cols[2].is_nan());
+        }
+    }
+
+    #[test]
+    fn empty_and_missing_axes() {
+        let t = from_fn([2, 0], |_| 1.0);
+        assert_eq!(t.sum(), 0.0);
+        assert_eq!(t.prod(), 1.0);
+        assert_eq!(t.mean(), None);
+        assert_eq!(t.min(), None);
+        assert_eq!(t.max(), None);
+        assert_eq!(to_vec(&t.sum_axis(Axis(1)).unwrap()), [0.0, 0.0]);
+        assert_eq!(to_vec(&t.prod_axis(Axis(1)).unwrap()), [1.0, 1.0]);
+        assert_eq!(t.sum_axis(Axis(0)).unwrap().shape().as_slice(), &[0]);
+        assert_eq!(t.max_axis_keepdims(Axis(0)).unwrap().shape().as_slice(), &[1, 0]);
+        for result in [t.mean_axis(Axis(1)), t.min_axis(Axis(1)), t.max_axis(Axis(1))] {
+            assert!(matches!(result, Err(OmniError::ShapeError(ShapeError::IncompatibleShape))));
+        }
+        assert!(matches!(
+            t.sum_axis(Axis(2)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(2, 2))),
+        ));
+        assert!(t.mean_axis_keepdims(Axis(2)).is_err());
+    }
+
+    #[test]
+    fn integer_reductions() {
+        let t = from_fn([2, 3], |i| (i[0] * 3 + i[1]) as i32 - 2);
+        assert_eq!(t.sum(), 3);
+        assert_eq!(t.prod(), 0);
+        assert_eq!(t.min(), Some(-2));
+        assert_eq!(t.max(), Some(3));
+        assert_eq!(to_vec(&t.sum_axis(Axis(0)).unwrap()), [-1, 1, 3]);
+        assert_eq!(to_vec(&t.prod_axis(Axis(1)).unwrap()), [0, 6]);
+        assert_eq!(to_vec(&t.min_axis(Axis(1)).unwrap()), [-2, 1]);
+        assert_eq!(to_vec(&t.max_axis(Axis(0)).unwrap()), [1, 2, 3]);
+        // Integer means truncate like integer division.
+        assert_eq!(to_vec(&t.mean_axis(Axis(1)).unwrap()), [-1, 2]);
+    }
+
+    /// Position of the first NaN in `lane`, or else of the first smallest
+    /// (or largest, if `largest` is set) element.
+    fn first_extreme(lane: &[f64], largest: bool) -> usize {
+        if let Some(k) = lane.iter().position(|x| x.is_nan()) {
+            return k;
+        }
+        let mut best = 0;
+        for (k, &x) in lane.iter().enumerate() {
+            if (largest && x > lane[best]) || (!largest && x < lane[best]) {
+                best = k;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn arg_reductions_match_reference() {
+        // Few distinct values, so that every lane has ties.
+        let t = from_fn([3, 4, 5], |i| ((i[0] * 7 + i[1] * 3 + i[2]) % 4) as f64);
+        let views = [
+            t.view(),
+            t.view().permuted_axes([2, 0, 1]).unwrap(),
+            t.slice(s![..;-1, 1..;2, ..]),
+            t.slice(s![.., ..;-1, ..;2]).permuted_axes([1, 2, 0]).unwrap(),
+        ];
+        for v in &views {
+            let shape = v.shape().as_slice().to_vec();
+            let elems = to_vec(v);
+            assert_eq!(v.argmin(), Some(first_extreme(&elems, false)));
+            assert_eq!(v.argmax(), Some(first_extreme(&elems, true)));
+
+            for axis in 0..3 {
+                let a = Axis(axis);
+                for largest in [false, true] {
+                    let (values, indices) = if largest {
+                        (v.max_with_indices(a).unwrap(), v.argmax_axis(a).unwrap())
+                    } else {
+                        (v.min_with_indices(a).unwrap(), v.argmin_axis(a).unwrap())
+                    };
+                    let expected = reduce_lanes(&elems, &shape, axis, |lane| first_extreme(lane, largest) as f64);
+                    let positions: Vec<f64> = to_vec(&values.1).into_iter().map(|k| k as f64).collect();
+                    assert_eq!(positions, expected);
+                    assert_eq!(to_vec(&indices), to_vec(&values.1));
+                    let extremes = reduce_lanes(&elems, &shape, axis, if largest { max } else { min });
+                    assert_eq!(to_vec(&values.0), extremes);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arg_reductions_pick_the_first_nan() {
+        let t = from_fn([2, 4], |i| match (i[0], i[1]) {
+            (0, 2) | (0, 3) | (1, 1) => f64::NAN,
+            (_, j) => j as f64,
+        });
+        assert_eq!(t.argmin(), Some(2));
+        assert_eq!(t.argmax(), Some(2));
+        assert_eq!(to_vec(&t.argmin_axis(Axis(1)).unwrap()), [2, 1]);
+        assert_eq!(to_vec(&t.argmax_axis(Axis(1)).unwrap()), [2, 1]);
+        assert_eq!(to_vec(&t.argmax_axis(Axis(0)).unwrap()), [0, 1, 0, 0]);
+        let (values, indices) = t.min_with_indices(Axis(0)).unwrap();
+        assert_eq!(to_vec(&indices), [0, 1, 0, 0]);
+        assert!(values[[1]].is_nan() && values[[2]].is_nan());
+        assert_eq!(values[[0]], 0.0);
+    }
+
+    #[test]
+    fn arg_reductions_of_empty_tensors() {
+        let t = from_fn([0, 3], |_| 0.0);
+        assert_eq!(t.argmin(), None);
+        assert_eq!(t.argmax(), None);
+        assert!(matches!(
+            t.argmin_axis(Axis(0)),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(t.max_with_indices(Axis(0)).is_err());
+        assert_eq!(t.argmax_axis(Axis(1)).unwrap().shape().as_slice(), &[0]);
+        assert!(matches!(
+            t.argmax_axis(Axis(2)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(2, 2))),
+        ));
+    }
+}
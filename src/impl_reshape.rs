@@ -0,0 +1,242 @@
+//! Changing the shape of a tensor while keeping its elements in order.
+//!
+//! Elements are read in row-major ("C") order by default, or in column-major
+//! ("F") order when the shape asks for it, e.g. `(2, 3).f()`. One axis length
+//! may be inferred from the element count with [`InferShape`].
+//!
+//! [`InferShape`]: crate::shape_builder::InferShape
+
+use crate::{
+    backend::Backend,
+    dimension::{dims_from_slice, reshape::reshape_strides, strides_as_isize, Dimensions},
+    error::{OmniResult, ShapeError},
+    shape_builder::ShapeArg,
+    storage::{
+        traits::{RawStorage, StorageOwned},
+        CowStorage,
+        OwnedStorage,
+    },
+    tensor::TensorBase,
+    tensor_view::CowTensor,
+};
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Changes the shape of the tensor without copying its elements.
+    ///
+    /// **Errors** with `IncompatibleShape` if the element count differs, and
+    /// with `IncompatibleLayout` if the current memory layout cannot be
+    /// viewed with the new shape; use [`reshape`](Self::reshape) then.
+    pub fn into_shape<Sh>(self, shape: Sh) -> OmniResult<TensorBase<S, Sh::Dims>>
+    where
+        Sh: ShapeArg,
+    {
+        let shape = shape.into_shape_with_size(self.size())?;
+        let strides = reshape_strides(
+            self.dims.as_slice(),
+            strides_as_isize(self.strides.as_slice()),
+            shape.dims.as_slice(),
+            shape.is_f(),
+        )
+        .ok_or(ShapeError::IncompatibleLayout)?;
+        let strides: Vec<_> = strides.into_iter().map(|s| s as usize).collect();
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            strides: dims_from_slice(&strides),
+            dims: shape.dims,
+        })
+    }
+
+    /// Returns the tensor with a new shape, as a view if the memory layout
+    /// allows it, and as a copy otherwise.
+    ///
+    /// **Errors** with `IncompatibleShape` if the element count differs.
+    pub fn reshape<Sh>(&self, shape: Sh) -> OmniResult<CowTensor<'_, T, B, Sh::Dims>>
+    where
+        Sh: ShapeArg,
+    {
+        let shape = shape.into_shape_with_size(self.size())?;
+        let is_f = shape.is_f();
+        if let Ok(view) = self.view().into_shape(shape.clone()) {
+            return Ok(TensorBase {
+                storage: CowStorage::View(view.storage),
+                ptr: view.ptr,
+                dims: view.dims,
+                strides: view.strides,
+            });
+        }
+
+        // Copy into a buffer that is contiguous in the requested order, which
+        // then holds the elements in the new shape as well.
+        let backend = self.storage.backend();
+        let src_strides = if is_f {
+            self.dims.fortran_strides()
+        } else {
+            self.dims.default_strides()
+        };
+        let (storage, ptr) = OwnedStorage::empty(self.size(), backend);
+        unsafe {
+            backend.copy_strided(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                ptr.as_ptr(),
+                strides_as_isize(src_strides.as_slice()),
+            );
+        }
+        Ok(TensorBase {
+            storage: CowStorage::Owned(storage),
+            ptr,
+            strides: shape.default_strides(),
+            dims: shape.dims,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CpuBackend,
+        dimension::{Dimensions, DynDims},
+        error::{OmniError, ShapeError},
+        index::Ix,
+        s,
+        shape_builder::{InferShape, ShapeBuilder},
+        tensor_view::TensorView,
+        test_util::*,
+    };
+
+    /// The elements of `x` in column-major order.
+    fn to_vec_f(x: &TensorView<'_, f64, CpuBackend, DynDims>) -> Vec<f64> {
+        to_vec(&x.clone().reversed_axes())
+    }
+
+    /// Checks `reshape` and `into_shape` of `x` to `dims` in both orders
+    /// against reading the elements out in that order and back in.
+    fn check(x: &TensorView<'_, f64, CpuBackend, DynDims>, dims: &[Ix], views: [bool; 2]) {
+        let c = x.reshape(dims.to_vec()).unwrap();
+        assert_eq!(c.shape().as_slice(), dims);
+        assert_eq!(to_vec(&c), to_vec(x), "{:?} -> {dims:?}", x.shape().as_slice());
+        assert_eq!(x.clone().into_shape(dims.to_vec()).is_ok(), c.is_view());
+
+        let f = x.reshape(dims.to_vec().f()).unwrap();
+        assert_eq!(f.shape().as_slice(), dims);
+        assert_eq!(to_vec_f(&f.view()), to_vec_f(x), "{:?} -> {dims:?} F", x.shape().as_slice());
+        assert_eq!(x.clone().into_shape(dims.to_vec().f()).is_ok(), f.is_view());
+
+        if views[0] {
+            assert!(c.is_view() && c.as_ptr() == x.as_ptr());
+        }
+        if views[1] {
+            assert!(f.is_view() && f.as_ptr() == x.as_ptr());
+        }
+    }
+
+    #[test]
+    fn reshape_matches_reference() {
+        let c = arange([2, 3, 4]).into_dyn();
+        let f = arange([4, 3, 2]).reversed_axes().into_dyn();
+        let wide = arange([2, 6, 4]).into_dyn();
+        let row = arange([4]);
+        let targets: [&[Ix]; 9] = [
+            &[24],
+            &[4, 6],
+            &[6, 4],
+            &[2, 12],
+            &[3, 2, 4],
+            &[1, 24, 1],
+            &[4, 1, 3, 2],
+            &[2, 3, 4],
+            &[8, 3],
+        ];
+        for dims in targets {
+            // Contiguous in C and F order: every shape is a view in that order.
+            check(&c.view(), dims, [true, false]);
+            check(&f.view(), dims, [false, true]);
+            // Permuted, reversed, stepped and broadcast: views only where the
+            // strides allow it.
+            check(&c.view().permuted_axes(vec![1, 0, 2]).unwrap(), dims, [false, false]);
+            check(&c.slice(s![..;-1, .., ..]), dims, [false, false]);
+            check(&wide.slice(s![.., ..;2, ..]), dims, [false, false]);
+            check(&row.broadcast_to(vec![2, 3, 4]).unwrap(), dims, [false, false]);
+        }
+    }
+
+    #[test]
+    fn views_of_strided_layouts() {
+        // Only the merged axes have to be contiguous with each other.
+        let t = arange([4, 6]);
+        let v = t.slice(s![.., ..;-2]);
+        let r = v.reshape([2, 2, 3]).unwrap();
+        assert!(r.is_view());
+        assert_eq!(r.strides(), &[12, 6, -2]);
+        assert_eq!(to_vec(&r), to_vec(&v));
+        assert!(matches!(
+            v.clone().into_shape([12]),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleLayout)),
+        ));
+
+        let r = v.reshape([4, 1, 3, 1]).unwrap();
+        assert!(r.is_view());
+        assert_eq!(to_vec(&r), to_vec(&v));
+
+        let owned = arange([3, 4]);
+        let ptr = owned.as_ptr();
+        let moved = owned.into_shape([2, 6]).unwrap();
+        assert_eq!(moved.as_ptr(), ptr);
+        assert_eq!(moved.strides(), &[6, 1]);
+        let moved = moved.reversed_axes().into_shape((3, 4).f()).unwrap();
+        assert_eq!(moved.as_ptr(), ptr);
+        assert_eq!(moved.strides(), &[1, 3]);
+    }
+
+    #[test]
+    fn empty_and_scalar_shapes() {
+        let e = arange([0, 3]);
+        assert_eq!(e.reshape([3, 0]).unwrap().shape().as_slice(), &[3, 0]);
+        assert_eq!(e.reshape([0]).unwrap().shape().as_slice(), &[0]);
+        assert!(matches!(e.reshape([1]), Err(OmniError::ShapeError(ShapeError::IncompatibleShape))));
+
+        let one = arange([1, 1]);
+        assert_eq!(to_vec(&one.reshape(()).unwrap()), [0.0]);
+        assert_eq!(to_vec(&arange(()).reshape([1, 1, 1]).unwrap()), [0.0]);
+    }
+
+    #[test]
+    fn inferred_axis() {
+        let t = arange([2, 3, 4]);
+        let r = t.reshape(InferShape::new([-1, 6])).unwrap();
+        assert_eq!(r.shape().as_slice(), &[4, 6]);
+        assert_eq!(to_vec(&r), to_vec(&t));
+
+        let r = t.reshape(InferShape::new([3, -1]).f()).unwrap();
+        assert_eq!(r.shape().as_slice(), &[3, 8]);
+        let expected = from_fn([3, 8], |i| {
+            // The k-th element in column-major order of both shapes.
+            let k = i[1] * 3 + i[0];
+            ((k % 2) * 12 + (k / 2 % 3) * 4 + k / 6) as f64
+        });
+        assert_eq!(to_vec(&r), to_vec(&expected));
+
+        let r = t.reshape(InferShape::from_slice(&[2, -1, 2, 1])).unwrap();
+        assert_eq!(r.shape().as_slice(), &[2, 6, 2, 1]);
+        assert_eq!(arange([0, 4]).reshape(InferShape::new([-1, 2])).unwrap().shape().as_slice(), &[0, 2]);
+
+        for bad in [[-1, -1], [5, -1], [0, -1], [-2, 12], [4, 5]] {
+            assert!(
+                matches!(t.reshape(InferShape::new(bad)), Err(OmniError::ShapeError(ShapeError::IncompatibleShape))),
+                "{bad:?}",
+            );
+        }
+        // The known lengths multiply to 2 modulo 2^64.
+        assert!(matches!(
+            t.reshape(InferShape::new([3, 6_148_914_691_236_517_206, -1])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+    }
+}
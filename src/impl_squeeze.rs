@@ -0,0 +1,208 @@
+//! Adding and removing axes of length 1.
+//!
+//! `insert_axis` and `remove_axis` keep the dimensionality static through
+//! `Dimensions::Larger` and `Dimensions::Smaller`, while `unsqueeze`,
+//! `squeeze` and `squeeze_axis` produce `DynDims` tensors.
+
+use crate::{
+    backend::Backend,
+    dimension::{
+        check_axis,
+        dyn_dims::DynDimsImpl,
+        insert_axis,
+        remove_axis,
+        Dimensions,
+        Dims,
+        DynDims,
+    },
+    error::{OmniResult, ShapeError},
+    index::{Axis, Ix},
+    storage::traits::RawStorage,
+    tensor::TensorBase,
+};
+
+/// The stride for a length-1 axis inserted before `axis`, chosen so that
+/// tensors in standard layout stay in standard layout.
+fn inserted_stride<D: Dimensions>(dims: &D, strides: &D, axis: usize) -> Ix {
+    if axis < dims.ndim() {
+        dims[axis].wrapping_mul(strides[axis])
+    } else {
+        1
+    }
+}
+
+/// Like `check_axis`, but also accepts the position just past the last axis.
+fn check_insert_axis(axis: Axis, ndim: usize) -> OmniResult<usize> {
+    if axis.index() <= ndim {
+        Ok(axis.index())
+    } else {
+        Err(ShapeError::AxisOutOfBounds(axis.index(), ndim).into())
+    }
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Inserts an axis of length 1 before position `axis`; `axis` may equal
+    /// the number of dimensions to append one.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if `axis` is past the last position.
+    pub fn insert_axis(self, axis: Axis) -> OmniResult<TensorBase<S, D::Larger>> {
+        let axis = check_insert_axis(axis, self.ndim())?;
+        let stride = inserted_stride(&self.dims, &self.strides, axis);
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: insert_axis(&self.dims, axis, 1),
+            strides: insert_axis(&self.strides, axis, stride),
+        })
+    }
+
+    /// Removes `axis`, which must have length 1.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `IncompatibleShape` if its length is not 1.
+    pub fn remove_axis(self, axis: Axis) -> OmniResult<TensorBase<S, D::Smaller>> {
+        let axis = check_axis(axis, self.ndim())?;
+        if self.dims[axis] != 1 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: remove_axis(&self.dims, axis),
+            strides: remove_axis(&self.strides, axis),
+        })
+    }
+
+    /// Like [`insert_axis`](Self::insert_axis), but returns a tensor with
+    /// dynamic dimensions.
+    pub fn unsqueeze(self, axis: Axis) -> OmniResult<TensorBase<S, DynDims>> {
+        let axis = check_insert_axis(axis, self.ndim())?;
+        let stride = inserted_stride(&self.dims, &self.strides, axis);
+        let mut strides = DynDimsImpl::from_slice(self.strides.as_slice()).unsqueeze(axis);
+        strides[axis] = stride;
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: Dims::new(DynDimsImpl::from_slice(self.dims.as_slice()).unsqueeze(axis)),
+            strides: Dims::new(strides),
+        })
+    }
+
+    /// Like [`remove_axis`](Self::remove_axis), but returns a tensor with
+    /// dynamic dimensions.
+    pub fn squeeze_axis(self, axis: Axis) -> OmniResult<TensorBase<S, DynDims>> {
+        let axis = check_axis(axis, self.ndim())?;
+        if self.dims[axis] != 1 {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        Ok(TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: Dims::new(DynDimsImpl::from_slice(self.dims.as_slice()).squeeze(axis)),
+            strides: Dims::new(DynDimsImpl::from_slice(self.strides.as_slice()).squeeze(axis)),
+        })
+    }
+
+    /// Removes every axis of length 1.
+    pub fn squeeze(self) -> TensorBase<S, DynDims> {
+        let mut dims = DynDimsImpl::from_slice(self.dims.as_slice());
+        let mut strides = DynDimsImpl::from_slice(self.strides.as_slice());
+        for axis in (0..self.ndim()).rev() {
+            if self.dims[axis] == 1 {
+                dims = dims.squeeze(axis);
+                strides = strides.squeeze(axis);
+            }
+        }
+        TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: Dims::new(dims),
+            strides: Dims::new(strides),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::OmniError, s, test_util::*};
+
+    #[test]
+    fn insert_axis_at_every_position() {
+        let t = arange([2, 3]);
+        for axis in 0..=2 {
+            let mut expected = vec![2, 3];
+            expected.insert(axis, 1);
+            let inserted = t.view().insert_axis(Axis(axis)).unwrap();
+            assert_eq!(inserted.shape().as_slice(), expected.as_slice());
+            assert!(inserted.is_standard_layout(), "axis {axis}");
+            assert_eq!(to_vec(&inserted), to_vec(&t));
+
+            let unsqueezed = t.view().unsqueeze(Axis(axis)).unwrap();
+            assert_eq!(unsqueezed.shape().as_slice(), expected.as_slice());
+            assert_eq!(unsqueezed.strides(), inserted.strides());
+        }
+        assert!(matches!(
+            t.view().insert_axis(Axis(3)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 2))),
+        ));
+        assert!(t.view().unsqueeze(Axis(3)).is_err());
+    }
+
+    #[test]
+    fn strided_views_keep_their_elements() {
+        let t = arange([3, 4]);
+        let v = t.slice(s![..;-1, 1..;2]);
+        let inserted = v.clone().insert_axis(Axis(1)).unwrap();
+        assert_eq!(inserted.shape().as_slice(), &[3, 1, 2]);
+        assert_eq!(to_vec(&inserted), to_vec(&v));
+        let removed = inserted.remove_axis(Axis(1)).unwrap();
+        assert_eq!(removed.strides(), v.strides());
+        assert_eq!(to_vec(&removed), to_vec(&v));
+    }
+
+    #[test]
+    fn remove_axis_needs_length_one() {
+        let t = arange([2, 1, 3]);
+        let removed = t.view().remove_axis(Axis(1)).unwrap();
+        assert_eq!(removed.shape().as_slice(), &[2, 3]);
+        assert!(matches!(
+            t.view().remove_axis(Axis(0)),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(matches!(
+            t.view().remove_axis(Axis(3)),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(3, 3))),
+        ));
+        assert!(t.view().squeeze_axis(Axis(2)).is_err());
+        assert_eq!(t.view().squeeze_axis(Axis(1)).unwrap().shape().as_slice(), &[2, 3]);
+    }
+
+    #[test]
+    fn squeeze_removes_every_length_one_axis() {
+        // More axes than dynamic dims store inline, before and after.
+        let t = arange([1, 2, 1, 3, 1, 1]);
+        let squeezed = t.view().squeeze();
+        assert_eq!(squeezed.shape().as_slice(), &[2, 3]);
+        assert_eq!(squeezed.strides(), &[3, 1]);
+        assert_eq!(to_vec(&squeezed), to_vec(&t));
+
+        let ones = arange([1, 1, 1, 1, 1]);
+        let squeezed = ones.view().squeeze();
+        assert_eq!(squeezed.ndim(), 0);
+        assert_eq!(to_vec(&squeezed), [0.0]);
+
+        let mut grown = arange([2, 3]).into_dyn();
+        for axis in [0, 3, 1, 5] {
+            grown = grown.unsqueeze(Axis(axis)).unwrap();
+        }
+        assert_eq!(grown.shape().as_slice(), &[1, 1, 2, 3, 1, 1]);
+        assert!(grown.is_standard_layout());
+        assert_eq!(to_vec(&grown.squeeze()), to_vec(&arange([2, 3])));
+    }
+}
@@ -1 +1,12 @@
 pub type Ix = usize;
+
+/// An axis index, used to pick the axis an operation works along.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Axis(pub usize);
+
+impl Axis {
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
@@ -0,0 +1,635 @@
+//! Element, lane and axis iterators.
+//!
+//! All iterators visit elements in logical (row-major) order, whatever the
+//! strides of the tensor are. Tensors in standard layout are iterated through
+//! a plain slice iterator.
+
+use std::{marker::PhantomData, ptr::NonNull, slice};
+
+use crate::{
+    backend::Backend,
+    dimension::{remove_axis, Dimensions, Dims1},
+    index::{Axis, Ix},
+    storage::traits::{RawStorage, Storage, StorageMut},
+    tensor::TensorBase,
+    tensor_view::{TensorView, TensorViewMut},
+};
+
+/// Strided walk over all indices of `dims` in row-major order, yielding the
+/// pointer of each element.
+pub(crate) struct Baseiter<T, D> {
+    dims: D,
+    strides: D,
+    index: Option<D>,
+    ptr: *mut T,
+    remaining: usize,
+}
+
+impl<T, D: Dimensions> Baseiter<T, D> {
+    /// Safety: `ptr` must be valid for every index of `dims` with `strides`.
+    pub(crate) unsafe fn new(ptr: *mut T, dims: D, strides: D) -> Self {
+        let remaining = dims.size();
+        let index = if remaining == 0 {
+            None
+        } else {
+            Some(D::zeros(dims.ndim()))
+        };
+        Baseiter {
+            dims,
+            strides,
+            index,
+            ptr,
+            remaining,
+        }
+    }
+
+    /// Returns the current index and pointer, then steps to the next index.
+    fn next_with_index(&mut self) -> Option<(D, *mut T)> {
+        let index = self.index.as_mut()?;
+        let current = (index.clone(), self.ptr);
+        self.remaining -= 1;
+
+        let mut axis = self.dims.ndim();
+        loop {
+            if axis == 0 {
+                self.index = None;
+                break;
+            }
+            axis -= 1;
+            let stride = self.strides[axis] as isize;
+            index[axis] += 1;
+            if index[axis] < self.dims[axis] {
+                self.ptr = self.ptr.wrapping_offset(stride);
+                break;
+            }
+            self.ptr = self.ptr.wrapping_offset(-stride * (self.dims[axis] - 1) as isize);
+            index[axis] = 0;
+        }
+        Some(current)
+    }
+}
+
+impl<T, D: Dimensions> Iterator for Baseiter<T, D> {
+    type Item = *mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<*mut T> {
+        self.next_with_index().map(|(_, ptr)| ptr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+enum ElementsRepr<S, B> {
+    Slice(S),
+    Strided(B),
+}
+
+/// Iterator over references to the elements of a tensor.
+pub struct Iter<'a, T, D> {
+    inner: ElementsRepr<slice::Iter<'a, T>, Baseiter<T, D>>,
+}
+
+impl<'a, T: 'a, D: Dimensions> Iterator for Iter<'a, T, D> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        match self.inner {
+            ElementsRepr::Slice(ref mut it) => it.next(),
+            ElementsRepr::Strided(ref mut it) => it.next().map(|ptr| unsafe { &*ptr }),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.inner {
+            ElementsRepr::Slice(ref it) => it.size_hint(),
+            ElementsRepr::Strided(ref it) => it.size_hint(),
+        }
+    }
+}
+
+impl<'a, T: 'a, D: Dimensions> ExactSizeIterator for Iter<'a, T, D> {}
+
+/// Iterator over mutable references to the elements of a tensor.
+pub struct IterMut<'a, T, D> {
+    inner: ElementsRepr<slice::IterMut<'a, T>, Baseiter<T, D>>,
+}
+
+impl<'a, T: 'a, D: Dimensions> Iterator for IterMut<'a, T, D> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        match self.inner {
+            ElementsRepr::Slice(ref mut it) => it.next(),
+            ElementsRepr::Strided(ref mut it) => it.next().map(|ptr| unsafe { &mut *ptr }),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.inner {
+            ElementsRepr::Slice(ref it) => it.size_hint(),
+            ElementsRepr::Strided(ref it) => it.size_hint(),
+        }
+    }
+}
+
+impl<'a, T: 'a, D: Dimensions> ExactSizeIterator for IterMut<'a, T, D> {}
+
+/// Iterator over the elements of a tensor together with their index.
+pub struct IndexedIter<'a, T, D> {
+    inner: Baseiter<T, D>,
+    life: PhantomData<&'a T>,
+}
+
+impl<'a, T: 'a, D: Dimensions> Iterator for IndexedIter<'a, T, D> {
+    type Item = (D::Pattern, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_with_index()
+            .map(|(index, ptr)| (index.into_pattern(), unsafe { &*ptr }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: 'a, D: Dimensions> ExactSizeIterator for IndexedIter<'a, T, D> {}
+
+/// Iterator over the 1-D lanes of a tensor along one axis.
+pub struct Lanes<'a, T, B, D> {
+    inner: Baseiter<T, D>,
+    len: Ix,
+    stride: Ix,
+    backend: B,
+    life: PhantomData<&'a T>,
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> Iterator for Lanes<'a, T, B, D> {
+    type Item = TensorView<'a, T, B, Dims1>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|ptr| unsafe {
+            TensorView::new(
+                NonNull::new_unchecked(ptr),
+                Dims1::new([self.len]),
+                Dims1::new([self.stride]),
+                self.backend,
+            )
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> ExactSizeIterator for Lanes<'a, T, B, D> {}
+
+/// Shared state of the axis iterators: the sub-tensors at positions
+/// `index..end` along one axis.
+struct AxisIterCore<T, D: Dimensions> {
+    ptr: *mut T,
+    stride: isize,
+    index: Ix,
+    end: Ix,
+    inner_dims: D::Smaller,
+    inner_strides: D::Smaller,
+}
+
+impl<T, D: Dimensions> AxisIterCore<T, D> {
+    fn new(ptr: *mut T, dims: &D, strides: &D, axis: Axis) -> Self {
+        let axis = axis.index();
+        assert!(
+            axis < dims.ndim(),
+            "axis {} is out of range for a tensor with {} axes",
+            axis,
+            dims.ndim(),
+        );
+        AxisIterCore {
+            ptr,
+            stride: strides[axis] as isize,
+            index: 0,
+            end: dims[axis],
+            inner_dims: remove_axis(dims, axis),
+            inner_strides: remove_axis(strides, axis),
+        }
+    }
+
+    fn ptr_at(&self, index: Ix) -> NonNull<T> {
+        unsafe { NonNull::new_unchecked(self.ptr.wrapping_offset(index as isize * self.stride)) }
+    }
+
+    fn next(&mut self) -> Option<NonNull<T>> {
+        if self.index == self.end {
+            return None;
+        }
+        let ptr = self.ptr_at(self.index);
+        self.index += 1;
+        Some(ptr)
+    }
+
+    fn next_back(&mut self) -> Option<NonNull<T>> {
+        if self.index == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.ptr_at(self.end))
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.index
+    }
+}
+
+/// Iterator over the sub-views at each position along an axis.
+pub struct AxisIter<'a, T, B, D: Dimensions> {
+    core: AxisIterCore<T, D>,
+    backend: B,
+    life: PhantomData<&'a T>,
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> Iterator for AxisIter<'a, T, B, D> {
+    type Item = TensorView<'a, T, B, D::Smaller>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.core.next().map(|ptr| {
+            TensorView::new(
+                ptr,
+                self.core.inner_dims.clone(),
+                self.core.inner_strides.clone(),
+                self.backend,
+            )
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.core.len(), Some(self.core.len()))
+    }
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> DoubleEndedIterator for AxisIter<'a, T, B, D> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.core.next_back().map(|ptr| {
+            TensorView::new(
+                ptr,
+                self.core.inner_dims.clone(),
+                self.core.inner_strides.clone(),
+                self.backend,
+            )
+        })
+    }
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> ExactSizeIterator for AxisIter<'a, T, B, D> {}
+
+/// Iterator over the mutable sub-views at each position along an axis.
+pub struct AxisIterMut<'a, T, B, D: Dimensions> {
+    core: AxisIterCore<T, D>,
+    backend: B,
+    life: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> Iterator for AxisIterMut<'a, T, B, D> {
+    type Item = TensorViewMut<'a, T, B, D::Smaller>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.core.next().map(|ptr| {
+            TensorViewMut::new(
+                ptr,
+                self.core.inner_dims.clone(),
+                self.core.inner_strides.clone(),
+                self.backend,
+            )
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.core.len(), Some(self.core.len()))
+    }
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> DoubleEndedIterator for AxisIterMut<'a, T, B, D> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.core.next_back().map(|ptr| {
+            TensorViewMut::new(
+                ptr,
+                self.core.inner_dims.clone(),
+                self.core.inner_strides.clone(),
+                self.backend,
+            )
+        })
+    }
+}
+
+impl<'a, T: 'a, B: Backend, D: Dimensions> ExactSizeIterator for AxisIterMut<'a, T, B, D> {}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Iterates over references to the elements in logical order.
+    pub fn iter(&self) -> Iter<'_, T, D>
+    where
+        S: Storage,
+    {
+        let inner = if D::is_standard_layout(&self.dims, &self.strides) {
+            let slice = unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.size()) };
+            ElementsRepr::Slice(slice.iter())
+        } else {
+            ElementsRepr::Strided(unsafe {
+                Baseiter::new(self.ptr.as_ptr(), self.dims.clone(), self.strides.clone())
+            })
+        };
+        Iter { inner }
+    }
+
+    /// Iterates over mutable references to the elements in logical order,
+    /// unsharing shared storage first.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, D>
+    where
+        S: StorageMut,
+    {
+        S::try_ensure_unique(self);
+        let inner = if D::is_standard_layout(&self.dims, &self.strides) {
+            let slice = unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.size()) };
+            ElementsRepr::Slice(slice.iter_mut())
+        } else {
+            ElementsRepr::Strided(unsafe {
+                Baseiter::new(self.ptr.as_ptr(), self.dims.clone(), self.strides.clone())
+            })
+        };
+        IterMut { inner }
+    }
+
+    /// Iterates over the elements in logical order, together with their
+    /// index as a `D::Pattern`.
+    pub fn indexed_iter(&self) -> IndexedIter<'_, T, D>
+    where
+        S: Storage,
+    {
+        IndexedIter {
+            inner: unsafe {
+                Baseiter::new(self.ptr.as_ptr(), self.dims.clone(), self.strides.clone())
+            },
+            life: PhantomData,
+        }
+    }
+
+    /// Iterates over all 1-D lanes along `axis`, i.e. the views obtained by
+    /// fixing every other index.
+    ///
+    /// **Panics** if `axis` is out of range.
+    pub fn lanes(&self, axis: Axis) -> Lanes<'_, T, B, D>
+    where
+        S: Storage,
+    {
+        let axis = axis.index();
+        assert!(
+            axis < self.ndim(),
+            "axis {} is out of range for a tensor with {} axes",
+            axis,
+            self.ndim(),
+        );
+        let len = self.dims[axis];
+        let stride = self.strides[axis];
+        let mut dims = self.dims.clone();
+        dims[axis] = 1;
+        Lanes {
+            inner: unsafe { Baseiter::new(self.ptr.as_ptr(), dims, self.strides.clone()) },
+            len,
+            stride,
+            backend: self.storage.backend(),
+            life: PhantomData,
+        }
+    }
+
+    /// Iterates over the sub-views along the first axis.
+    ///
+    /// **Panics** if the tensor is 0-dimensional.
+    pub fn outer_iter(&self) -> AxisIter<'_, T, B, D>
+    where
+        S: Storage,
+    {
+        self.axis_iter(Axis(0))
+    }
+
+    /// Iterates over the sub-views at each position along `axis`, each with
+    /// that axis removed.
+    ///
+    /// **Panics** if `axis` is out of range.
+    pub fn axis_iter(&self, axis: Axis) -> AxisIter<'_, T, B, D>
+    where
+        S: Storage,
+    {
+        AxisIter {
+            core: AxisIterCore::new(self.ptr.as_ptr(), &self.dims, &self.strides, axis),
+            backend: self.storage.backend(),
+            life: PhantomData,
+        }
+    }
+
+    /// Iterates over the mutable sub-views along the first axis.
+    ///
+    /// **Panics** if the tensor is 0-dimensional.
+    pub fn outer_iter_mut(&mut self) -> AxisIterMut<'_, T, B, D>
+    where
+        S: StorageMut,
+    {
+        self.axis_iter_mut(Axis(0))
+    }
+
+    /// Iterates over the mutable sub-views at each position along `axis`,
+    /// each with that axis removed. Shared storage is unshared first.
+    ///
+    /// **Panics** if `axis` is out of range.
+    pub fn axis_iter_mut(&mut self, axis: Axis) -> AxisIterMut<'_, T, B, D>
+    where
+        S: StorageMut,
+    {
+        S::try_ensure_unique(self);
+        AxisIterMut {
+            core: AxisIterCore::new(self.ptr.as_ptr(), &self.dims, &self.strides, axis),
+            backend: self.storage.backend(),
+            life: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, B, S, D> IntoIterator for &'a TensorBase<S, D>
+where
+    T: 'a,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, B, S, D> IntoIterator for &'a mut TensorBase<S, D>
+where
+    T: 'a,
+    B: Backend,
+    S: StorageMut<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dimension::Dimensions,
+        index::{Axis, Ix},
+        s,
+        test_util::*,
+    };
+
+    /// The row-major indices of `dims`.
+    fn indices(dims: &[Ix]) -> Vec<Vec<Ix>> {
+        let mut out = vec![vec![]];
+        for &len in dims {
+            out = out.into_iter().flat_map(|i| (0..len).map(move |k| [&i[..], &[k]].concat())).collect();
+        }
+        out
+    }
+
+    #[test]
+    fn iter_matches_reference() {
+        let t = arange([3, 4, 5]);
+        let row = arange([5]);
+        // Each view with the source index of its elements.
+        let views = [
+            (t.view(), &(|i: &[Ix]| [i[0], i[1], i[2]]) as &dyn Fn(&[Ix]) -> [Ix; 3]),
+            (t.slice(s![..;-1, 1..;2, ..;-3]), &|i| [2 - i[0], 1 + 2 * i[1], 3 - 3 * i[2]]),
+            (t.view().permuted_axes([2, 0, 1]).unwrap(), &|i| [i[1], i[2], i[0]]),
+            (t.slice(s![1..2, .., 4..]), &|i| [1, i[1], 4]),
+            (t.slice(s![.., 2..2, ..]), &|i| [i[0], i[1], i[2]]),
+        ];
+        for (v, source) in views {
+            let dims = v.shape().as_slice().to_vec();
+            let expected: Vec<f64> = indices(&dims)
+                .iter()
+                .map(|i| {
+                    let [a, b, c] = source(i);
+                    (a * 20 + b * 5 + c) as f64
+                })
+                .collect();
+            assert_eq!(v.iter().len(), expected.len());
+            assert_eq!(to_vec(&v), expected);
+            let indexed: Vec<_> = v.indexed_iter().map(|((a, b, c), &x)| (vec![a, b, c], x)).collect();
+            assert_eq!(indexed, indices(&dims).into_iter().zip(expected).collect::<Vec<_>>());
+        }
+
+        let b = row.broadcast_to([2, 5]).unwrap();
+        assert_eq!(to_vec(&b), [0.0, 1.0, 2.0, 3.0, 4.0, 0.0, 1.0, 2.0, 3.0, 4.0]);
+        let scalar = arange(());
+        assert_eq!(to_vec(&scalar), [0.0]);
+        assert_eq!(scalar.indexed_iter().next(), Some(((), &0.0)));
+    }
+
+    #[test]
+    fn iter_mut_in_logical_order() {
+        let mut t = arange([3, 4]);
+        let mut k = 100.0;
+        for x in t.slice_mut(s![..;-1, 1..;2]).iter_mut() {
+            *x = k;
+            k += 1.0;
+        }
+        assert_eq!(to_vec(&t), [0.0, 104.0, 2.0, 105.0, 4.0, 102.0, 6.0, 103.0, 8.0, 100.0, 10.0, 101.0]);
+
+        let before: Vec<f64> = (&t).into_iter().copied().collect();
+        for x in &mut t {
+            *x = -*x;
+        }
+        assert_eq!(to_vec(&t), before.iter().map(|x| -x).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_mut_unshares() {
+        let shared = arange([2, 3]).into_shared();
+        let mut alias = shared.clone().reversed_axes();
+        alias.iter_mut().for_each(|x| *x += 1.0);
+        assert_eq!(to_vec(&alias), [1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn lanes_match_reference() {
+        let t = arange([2, 3, 4]);
+        let v = t.view().permuted_axes([1, 0, 2]).unwrap();
+        for axis in 0..3 {
+            let dims = v.shape().as_slice();
+            let lanes: Vec<Vec<f64>> = v.lanes(Axis(axis)).map(|lane| to_vec(&lane)).collect();
+            let mut outer = dims.to_vec();
+            outer[axis] = 1;
+            let expected: Vec<Vec<f64>> = indices(&outer)
+                .into_iter()
+                .map(|mut i| {
+                    (0..dims[axis])
+                        .map(|k| {
+                            i[axis] = k;
+                            v[(i[0], i[1], i[2])]
+                        })
+                        .collect()
+                })
+                .collect();
+            assert_eq!(lanes, expected, "axis {axis}");
+            assert_eq!(v.lanes(Axis(axis)).len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn axis_iter_both_ends() {
+        let t = arange([2, 3, 2]);
+        let v = t.slice(s![.., ..;-1, ..]);
+        let mut it = v.axis_iter(Axis(1));
+        assert_eq!(it.len(), 3);
+        assert_eq!(to_vec(&it.next().unwrap()), [4.0, 5.0, 10.0, 11.0]);
+        assert_eq!(to_vec(&it.next_back().unwrap()), [0.0, 1.0, 6.0, 7.0]);
+        assert_eq!(it.len(), 1);
+        assert_eq!(to_vec(&it.next_back().unwrap()), [2.0, 3.0, 8.0, 9.0]);
+        assert!(it.next().is_none() && it.next_back().is_none());
+
+        let outer: Vec<Vec<f64>> = t.outer_iter().rev().map(|x| to_vec(&x)).collect();
+        assert_eq!(outer[0], (6..12).map(|x| x as f64).collect::<Vec<_>>());
+        assert_eq!(arange([0, 2]).outer_iter().count(), 0);
+    }
+
+    #[test]
+    fn axis_iter_mut_writes_through() {
+        let mut t = arange([2, 3]);
+        for (k, mut col) in t.axis_iter_mut(Axis(1)).rev().enumerate() {
+            col.map_inplace(|x| *x += 10.0 * k as f64);
+        }
+        assert_eq!(to_vec(&t), [20.0, 11.0, 2.0, 23.0, 14.0, 5.0]);
+        for mut row in t.outer_iter_mut() {
+            row[1] = 0.0;
+        }
+        assert_eq!(to_vec(&t), [20.0, 0.0, 2.0, 23.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn axis_iter_out_of_range() {
+        let _ = arange([2, 2]).axis_iter(Axis(2));
+    }
+}
@@ -1,11 +1,35 @@
+
 pub mod allocator;
 pub mod backend;
+pub mod conv;
 pub mod dimension;
 pub mod elem;
 pub mod error;
+mod impl_cast;
+mod impl_complex;
+mod impl_float;
+mod impl_gather;
+mod impl_index;
+mod impl_map;
+mod impl_nn;
+mod impl_ops;
+mod impl_permute;
+mod impl_reduce;
+mod impl_reshape;
+mod impl_squeeze;
 pub mod index;
+pub mod iterators;
+pub mod linalg;
+pub mod logical;
 // pub mod ops;
+pub mod pool;
 pub mod shape_builder;
+pub mod slice;
+pub mod stacking;
 pub mod storage;
+mod strided;
 pub mod tensor;
 pub mod tensor_view;
+#[cfg(test)]
+mod test_util;
+pub mod zip;
@@ -0,0 +1,593 @@
+//! Matrix and vector products.
+//!
+//! [`matmul`](TensorBase::matmul) follows NumPy: the last two axes of each
+//! operand are the matrix axes, all leading axes are batch axes that are
+//! broadcast against each other, and a 1-D operand acts as a row vector on
+//! the left or a column vector on the right. Every product runs through the
+//! backend's `gemm`, which reads the operands through their strides, so
+//! transposed views are multiplied without being copied.
+//!
+//! The vector operations `dot`, `scaled_add`, `norm_l1` and `norm_l2` treat
+//! tensors of any rank as flat vectors and run the backend's level-1 kernels
+//! over their lanes.
+
+use num_traits::{Float, Signed};
+
+use crate::{
+    backend::Backend,
+    dimension::{
+        broadcast_shapes,
+        dims_from_slice,
+        strides_as_isize,
+        Dimensions,
+        MatMulDims,
+        MatMulOf,
+    },
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    index::Ix,
+    storage::{
+        traits::{RawStorageMut, Storage, StorageMut},
+        OwnedStorage,
+    },
+    strided,
+    tensor::TensorBase,
+};
+
+/// An operand viewed as a stack of matrices.
+struct Matrices {
+    batch_dims: Vec<Ix>,
+    batch_strides: Vec<isize>,
+    rows: Ix,
+    cols: Ix,
+    strides: [isize; 2],
+    is_vector: bool,
+}
+
+impl Matrices {
+    fn new(dims: &[Ix], strides: &[isize], is_lhs: bool) -> OmniResult<Self> {
+        let ndim = dims.len();
+        match ndim {
+            0 => Err(ShapeError::IncompatibleShape.into()),
+            1 if is_lhs => Ok(Matrices {
+                batch_dims: vec![],
+                batch_strides: vec![],
+                rows: 1,
+                cols: dims[0],
+                strides: [0, strides[0]],
+                is_vector: true,
+            }),
+            1 => Ok(Matrices {
+                batch_dims: vec![],
+                batch_strides: vec![],
+                rows: dims[0],
+                cols: 1,
+                strides: [strides[0], 0],
+                is_vector: true,
+            }),
+            _ => Ok(Matrices {
+                batch_dims: dims[..ndim - 2].to_vec(),
+                batch_strides: strides[..ndim - 2].to_vec(),
+                rows: dims[ndim - 2],
+                cols: dims[ndim - 1],
+                strides: [strides[ndim - 2], strides[ndim - 1]],
+                is_vector: false,
+            }),
+        }
+    }
+
+    /// Batch strides for iterating over the broadcast `batch_dims`.
+    fn broadcast_batch_strides(&self, batch_dims: &[Ix]) -> Vec<isize> {
+        let mut strides = vec![0; batch_dims.len()];
+        let offset = batch_dims.len() - self.batch_dims.len();
+        for (i, (&dim, &stride)) in self.batch_dims.iter().zip(&self.batch_strides).enumerate() {
+            if dim == batch_dims[offset + i] {
+                strides[offset + i] = stride;
+            }
+        }
+        strides
+    }
+}
+
+/// The shape of a product, split into the broadcast batch axes, the matrix
+/// dimensions, and the axes of the result.
+struct Product {
+    lhs: Matrices,
+    rhs: Matrices,
+    batch_dims: Vec<Ix>,
+    out_dims: Vec<Ix>,
+}
+
+impl Product {
+    fn new(lhs_dims: &[Ix], lhs_strides: &[isize], rhs_dims: &[Ix], rhs_strides: &[isize]) -> OmniResult<Self> {
+        let lhs = Matrices::new(lhs_dims, lhs_strides, true)?;
+        let rhs = Matrices::new(rhs_dims, rhs_strides, false)?;
+        if lhs.cols != rhs.rows {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let batch_dims = broadcast_shapes(&[&lhs.batch_dims, &rhs.batch_dims])?
+            .as_slice()
+            .to_vec();
+        let mut out_dims = batch_dims.clone();
+        if !lhs.is_vector {
+            out_dims.push(lhs.rows);
+        }
+        if !rhs.is_vector {
+            out_dims.push(rhs.cols);
+        }
+        Ok(Product {
+            lhs,
+            rhs,
+            batch_dims,
+            out_dims,
+        })
+    }
+
+    /// Computes `out = alpha * lhs * rhs + beta * out`, where `out` has the
+    /// axes `out_dims` laid out with `out_strides`.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn run<T: Elem, B: Backend>(
+        &self,
+        backend: B,
+        alpha: T,
+        lhs: *const T,
+        rhs: *const T,
+        beta: T,
+        out: *mut T,
+        out_strides: &[isize],
+    ) {
+        let nb = self.batch_dims.len();
+        let rs = if self.lhs.is_vector { 0 } else { out_strides[nb] };
+        let cs = if self.rhs.is_vector { 0 } else { out_strides[out_strides.len() - 1] };
+        strided::zip3(
+            &self.batch_dims,
+            out,
+            &out_strides[..nb],
+            lhs as *mut T,
+            &self.lhs.broadcast_batch_strides(&self.batch_dims),
+            rhs as *mut T,
+            &self.rhs.broadcast_batch_strides(&self.batch_dims),
+            |out, lhs, rhs| {
+                backend.gemm(
+                    self.lhs.rows,
+                    self.lhs.cols,
+                    self.rhs.cols,
+                    alpha,
+                    lhs,
+                    self.lhs.strides,
+                    rhs,
+                    self.rhs.strides,
+                    beta,
+                    out,
+                    [rs, cs],
+                )
+            },
+        );
+    }
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Matrix product of `self` and `rhs`, with NumPy's `matmul` semantics.
+    ///
+    /// **Errors** with `IncompatibleShape` if an operand is 0-D or the inner
+    /// dimensions differ, and with `IncompatibleBroadcast` if the batch axes
+    /// cannot be broadcast together.
+    pub fn matmul<S2, E>(
+        &self,
+        rhs: &TensorBase<S2, E>,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, MatMulOf<D, E>>>
+    where
+        S2: Storage<Elem = T, Backend = B>,
+        D: MatMulDims<E>,
+        E: Dimensions,
+    {
+        let product = Product::new(
+            self.dims.as_slice(),
+            strides_as_isize(self.strides.as_slice()),
+            rhs.dims.as_slice(),
+            strides_as_isize(rhs.strides.as_slice()),
+        )?;
+        let backend = self.storage.backend();
+        unsafe {
+            let out = TensorBase::<OwnedStorage<T, B>, MatMulOf<D, E>>::uninit(
+                dims_from_slice(&product.out_dims),
+                backend,
+            );
+            product.run(
+                backend,
+                T::one(),
+                self.ptr.as_ptr(),
+                rhs.ptr.as_ptr(),
+                T::zero(),
+                out.ptr.as_ptr(),
+                strides_as_isize(out.strides.as_slice()),
+            );
+            Ok(out)
+        }
+    }
+
+    /// Inner product of `self` and `rhs`, taken over all elements in logical
+    /// order. For 1-D tensors this is the vector dot product.
+    ///
+    /// **Errors** with `IncompatibleShape` if the shapes differ.
+    pub fn dot<S2, E>(&self, rhs: &TensorBase<S2, E>) -> OmniResult<T>
+    where
+        S2: Storage<Elem = T, Backend = B>,
+        E: Dimensions,
+    {
+        if self.dims.as_slice() != rhs.dims.as_slice() {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let backend = self.storage.backend();
+        let mut acc = T::zero();
+        unsafe {
+            strided::lanes2(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                rhs.ptr.as_ptr(),
+                strides_as_isize(rhs.strides.as_slice()),
+                |x, y, n, incx, incy| acc = acc + backend.dot(n, x, incx, y, incy),
+            );
+        }
+        Ok(acc)
+    }
+
+    /// Computes `self = self + alpha * rhs`, broadcasting `rhs` to the shape
+    /// of `self`.
+    ///
+    /// **Errors** with `IncompatibleBroadcast` if `rhs` cannot be broadcast
+    /// to the shape of `self`.
+    pub fn scaled_add<S2, E>(&mut self, alpha: T, rhs: &TensorBase<S2, E>) -> OmniResult<()>
+    where
+        S: RawStorageMut,
+        S2: Storage<Elem = T, Backend = B>,
+        E: Dimensions,
+    {
+        let rhs = rhs.broadcast_view(&self.dims)?;
+        S::try_ensure_unique(self);
+        let backend = self.storage.backend();
+        unsafe {
+            strided::lanes2(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                rhs.ptr.as_ptr(),
+                strides_as_isize(rhs.strides.as_slice()),
+                |y, x, n, incy, incx| backend.axpy(n, alpha, x, incx, y, incy),
+            );
+        }
+        Ok(())
+    }
+
+    /// Sum of the absolute values of all elements.
+    pub fn norm_l1(&self) -> T
+    where
+        T: Signed,
+    {
+        let backend = self.storage.backend();
+        let mut acc = T::zero();
+        unsafe {
+            strided::lanes1(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |x, n, incx| acc = acc + backend.asum(n, x, incx),
+            );
+        }
+        acc
+    }
+
+    /// Euclidean norm of all elements.
+    pub fn norm_l2(&self) -> T
+    where
+        T: Float,
+    {
+        let backend = self.storage.backend();
+        let mut acc = T::zero();
+        unsafe {
+            strided::lanes1(
+                self.dims.as_slice(),
+                self.ptr.as_ptr(),
+                strides_as_isize(self.strides.as_slice()),
+                |x, n, incx| acc = acc.hypot(backend.nrm2(n, x, incx)),
+            );
+        }
+        acc
+    }
+}
+
+/// Computes `c = alpha * a.matmul(b) + beta * c` in place.
+///
+/// `c` may have any strides, but must not overlap `a` or `b`. When `beta` is
+/// zero, the previous contents of `c` are ignored.
+///
+/// **Errors** like [`matmul`](TensorBase::matmul), and with
+/// `IncompatibleShape` if `c` does not have the shape of the product.
+pub fn general_mat_mul<T, B, S, S2, S3, D, E>(
+    alpha: T,
+    a: &TensorBase<S, D>,
+    b: &TensorBase<S2, E>,
+    beta: T,
+    c: &mut TensorBase<S3, MatMulOf<D, E>>,
+) -> OmniResult<()>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    S2: Storage<Elem = T, Backend = B>,
+    S3: StorageMut<Elem = T, Backend = B>,
+    D: Dimensions + MatMulDims<E>,
+    E: Dimensions,
+{
+    let product = Product::new(
+        a.dims.as_slice(),
+        strides_as_isize(a.strides.as_slice()),
+        b.dims.as_slice(),
+        strides_as_isize(b.strides.as_slice()),
+    )?;
+    if c.dims.as_slice() != product.out_dims {
+        return Err(ShapeError::IncompatibleShape.into());
+    }
+    S3::ensure_unique(c);
+    unsafe {
+        product.run(
+            c.storage.backend(),
+            alpha,
+            a.ptr.as_ptr(),
+            b.ptr.as_ptr(),
+            beta,
+            c.ptr.as_ptr(),
+            strides_as_isize(c.strides.as_slice()),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::CpuBackend,
+        dimension::DynDims,
+        error::OmniError,
+        s,
+        tensor_view::TensorView,
+        test_util::*,
+    };
+
+    type View<'a> = TensorView<'a, f64, CpuBackend, DynDims>;
+
+    /// NumPy's `matmul` with one loop per index of the product.
+    fn reference(a: &View<'_>, b: &View<'_>) -> Tensor<f64, DynDims> {
+        let (a_dims, b_dims) = (a.shape().as_slice(), b.shape().as_slice());
+        // Promote vectors to matrices, and drop the added axis at the end.
+        let a_mat = if a_dims.len() == 1 { vec![1, a_dims[0]] } else { a_dims.to_vec() };
+        let b_mat = if b_dims.len() == 1 { vec![b_dims[0], 1] } else { b_dims.to_vec() };
+        let (m, k, n) = (a_mat[a_mat.len() - 2], a_mat[a_mat.len() - 1], b_mat[b_mat.len() - 1]);
+        let (a_batch, b_batch) = (&a_mat[..a_mat.len() - 2], &b_mat[..b_mat.len() - 2]);
+        let batch = broadcast_shapes(&[a_batch, b_batch]).unwrap().as_slice().to_vec();
+        let element = |x: &View<'_>, x_batch: &[Ix], batch_index: &[Ix], row: Ix, col: Ix| {
+            let skip = batch_index.len() - x_batch.len();
+            let mut index: Vec<Ix> =
+                x_batch.iter().zip(&batch_index[skip..]).map(|(&len, &i)| if len == 1 { 0 } else { i }).collect();
+            match x.ndim() {
+                1 if row == 0 => index.push(col),
+                1 => index.push(row),
+                _ => index.extend([row, col]),
+            }
+            x[index.as_slice()]
+        };
+        let product = from_fn([batch.as_slice(), &[m, n]].concat(), |i| {
+            let (batch_index, (row, col)) = (&i[..batch.len()], (i[batch.len()], i[batch.len() + 1]));
+            (0..k).fold(0.0, |acc, p| {
+                acc + element(a, a_batch, batch_index, row, p) * element(b, b_batch, batch_index, p, col)
+            })
+        });
+        let mut out_dims = batch;
+        if a_dims.len() > 1 {
+            out_dims.push(m);
+        }
+        if b_dims.len() > 1 {
+            out_dims.push(n);
+        }
+        product.into_shape(out_dims).unwrap()
+    }
+
+    #[track_caller]
+    fn check(a: &View<'_>, b: &View<'_>) {
+        let expected = reference(a, b);
+        let product = a.matmul(b).unwrap();
+        assert_eq!(product.shape().as_slice(), expected.shape().as_slice());
+        assert_close(&to_vec(&product), &to_vec(&expected));
+    }
+
+    #[test]
+    fn matmul_matches_reference() {
+        let cases: [(&[Ix], &[Ix]); 12] = [
+            (&[3, 4], &[4, 5]),
+            (&[1, 1], &[1, 1]),
+            (&[4], &[4, 5]),
+            (&[3, 4], &[4]),
+            (&[4], &[4]),
+            (&[2, 3, 4], &[4, 5]),
+            (&[3, 4], &[2, 4, 5]),
+            (&[2, 1, 3, 4], &[5, 4, 2]),
+            (&[2, 3, 4], &[4]),
+            (&[4], &[3, 4, 2]),
+            (&[0, 3, 4], &[4, 2]),
+            (&[3, 0], &[0, 2]),
+        ];
+        for (a_shape, b_shape) in cases {
+            let (a, b) = (noise(a_shape.to_vec()), noise(b_shape.to_vec()));
+            check(&a.view(), &b.view());
+        }
+    }
+
+    #[test]
+    fn matmul_of_strided_operands() {
+        // Transposed, reversed and stepped views are read through their
+        // strides.
+        let a = noise([5, 4]).into_dyn();
+        let b = noise([6, 5]).into_dyn();
+        check(&a.view().reversed_axes(), &b.view().reversed_axes());
+        check(&a.slice(s![..;-1, ..;2]).reversed_axes(), &b.view().reversed_axes().slice(s![..;-1, 1..;2]));
+        check(&a.slice(s![.., -1]), &a.view());
+
+        // Batched operands with permuted and broadcast batch axes.
+        let batched = noise([3, 4, 2, 5]).into_dyn();
+        let lhs = batched.view().permuted_axes(vec![2, 0, 3, 1]).unwrap();
+        let rhs = noise([1, 3, 4, 6]).into_dyn();
+        check(&lhs, &rhs.view());
+        let col = noise([6, 1]).into_dyn();
+        check(&rhs.view(), &col.broadcast_to(vec![2, 1, 6, 3]).unwrap());
+    }
+
+    #[test]
+    fn matmul_of_fixed_dims() {
+        let a = arange([2, 3]);
+        let v = arange([3]);
+        let mv = a.matmul(&v).unwrap();
+        assert_eq!(to_vec(&mv), [5.0, 14.0]);
+        let vm = v.matmul(&a.view().reversed_axes()).unwrap();
+        assert_eq!(to_vec(&vm), [5.0, 14.0]);
+        let dot = v.matmul(&v).unwrap();
+        assert_eq!(dot.ndim(), 0);
+        assert_eq!(to_vec(&dot), [5.0]);
+        let batched = arange([2, 2, 3]).matmul(&arange([3, 1])).unwrap();
+        assert_eq!(to_vec(&batched), [5.0, 14.0, 23.0, 32.0]);
+    }
+
+    #[test]
+    fn matmul_errors() {
+        let a = arange([2, 3]).into_dyn();
+        let incompatible: [&[Ix]; 3] = [&[2, 3], &[2], &[]];
+        for shape in incompatible {
+            assert!(
+                matches!(a.matmul(&arange(shape.to_vec())), Err(OmniError::ShapeError(ShapeError::IncompatibleShape))),
+                "{shape:?}",
+            );
+        }
+        assert!(matches!(
+            arange(vec![2, 2, 3]).matmul(&arange(vec![3, 3, 1])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+    }
+
+    #[test]
+    fn general_mat_mul_accumulates() {
+        let a = noise([4, 3]);
+        let b = noise([3, 5]);
+        let mut c = noise([5, 4]).reversed_axes();
+        let mut expected = to_vec(&c);
+        let product = to_vec(&reference(&a.view().into_dyn(), &b.view().into_dyn()));
+        for (e, p) in expected.iter_mut().zip(&product) {
+            *e = 2.0 * p - 0.5 * *e;
+        }
+        general_mat_mul(2.0, &a, &b, -0.5, &mut c).unwrap();
+        assert_close(&to_vec(&c), &expected);
+
+        let mut c = from_fn([4, 5], |_| f64::NAN);
+        general_mat_mul(1.0, &a, &b, 0.0, &mut c).unwrap();
+        assert_close(&to_vec(&c), &product);
+
+        let mut wrong = arange([5, 4]);
+        assert!(matches!(
+            general_mat_mul(1.0, &a, &b, 0.0, &mut wrong),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+    }
+
+    #[test]
+    fn general_mat_mul_unshares_the_output() {
+        let shared = arange([2, 2]).into_shared();
+        let mut c = shared.clone();
+        let identity = from_fn([2, 2], |i| if i[0] == i[1] { 1.0 } else { 0.0 });
+        general_mat_mul(1.0, &identity, &arange([2, 2]), 1.0, &mut c).unwrap();
+        assert_eq!(to_vec(&c), [0.0, 2.0, 4.0, 6.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn dot_and_norms_match_reference() {
+        let (t, u) = (noise([4, 6]), noise([6, 4]));
+        let pairs = [
+            (t.view(), u.t()),
+            (t.slice(s![.., ..;-1]), u.t()),
+            (t.slice(s![..;-1, ..]), t.slice(s![.., ..;-1])),
+            (u.t(), t.broadcast_to([4, 6]).unwrap()),
+        ];
+        for (a, b) in &pairs {
+            let (x, y) = (to_vec(a), to_vec(b));
+            let inner: f64 = x.iter().zip(&y).map(|(x, y)| x * y).sum();
+            assert_close(&[a.dot(b).unwrap()], &[inner]);
+            assert_close(&[a.norm_l1()], &[x.iter().map(|x| x.abs()).sum()]);
+            assert_close(&[b.norm_l2()], &[y.iter().map(|y| y * y).sum::<f64>().sqrt()]);
+        }
+
+        let v = arange([5]);
+        assert_eq!(v.dot(&v.slice(s![..;-1])).unwrap(), 10.0);
+        assert!(matches!(
+            t.dot(&u),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(t.dot(&t.slice(s![.., ..5])).is_err());
+    }
+
+    #[test]
+    fn norms_across_lanes() {
+        // Lanes that only overflow or underflow once squared and summed.
+        for scale in [1e300, 1e-300] {
+            let t = from_fn([3, 4], |i| if (i[0] + i[1]) % 2 == 0 { 3.0 * scale } else { -4.0 * scale });
+            let v = t.slice(s![.., ..;2]);
+            let v = v.t();
+            let norm = (4.0f64 * 3.0 * 3.0 + 2.0 * 4.0 * 4.0).sqrt();
+            assert_close(&[v.norm_l2() / scale], &[norm]);
+            assert_close(&[v.norm_l1() / scale], &[20.0]);
+        }
+        let t = from_fn([2, 3], |i| i[1] as i64 - i[0] as i64 * 3);
+        assert_eq!(t.t().norm_l1(), 9);
+        assert_eq!(from_fn([0, 3], |_| 1.0).norm_l2(), 0.0);
+    }
+
+    #[test]
+    fn scaled_add_matches_reference() {
+        let mut t = noise([3, 4]);
+        let row = arange([4]);
+        let before = to_vec(&t);
+        t.scaled_add(-2.0, &row).unwrap();
+        let expected: Vec<f64> = before.iter().enumerate().map(|(k, x)| x - 2.0 * (k % 4) as f64).collect();
+        assert_close(&to_vec(&t), &expected);
+
+        // A strided output and a transposed input.
+        let mut t = noise([4, 6]);
+        let rhs = noise([3, 4]);
+        let expected = from_fn([4, 6], |i| {
+            let x = t[[i[0], i[1]]];
+            if i[1] % 2 == 1 {
+                x + 0.5 * rhs[[2 - i[1] / 2, 3 - i[0]]]
+            } else {
+                x
+            }
+        });
+        t.slice_mut(s![..;-1, 1..;2]).scaled_add(0.5, &rhs.slice(s![..;-1, ..]).t()).unwrap();
+        assert_close(&to_vec(&t), &to_vec(&expected));
+
+        assert!(matches!(
+            t.scaled_add(1.0, &arange([6, 1])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+
+        let shared = arange([2, 2]).into_shared();
+        let mut u = shared.clone();
+        u.scaled_add(1.0, &arange([2])).unwrap();
+        assert_eq!(to_vec(&u), [0.0, 2.0, 2.0, 4.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,242 @@
+//! Comparisons, boolean masks and selection.
+//!
+//! The comparisons `eq`, `ne`, `lt`, `le`, `gt` and `ge` broadcast their
+//! operands like the arithmetic operators and return `bool` tensors. Masks
+//! combine with `logical_and`, `logical_or`, `logical_xor` and
+//! `logical_not`, and [`where_`] picks elements from one of two tensors
+//! depending on a mask.
+
+use crate::{
+    backend::Backend,
+    dimension::{broadcast::co_broadcast, Dimensions, DimsMax, DimsMaxOf},
+    error::OmniResult,
+    storage::{traits::Storage, OwnedStorage},
+    tensor::TensorBase,
+    zip::Zip,
+};
+
+/// A `bool` tensor with the broadcast shape of operands with dimensions `D`
+/// and `E`.
+pub type Mask<B, D, E> = TensorBase<OwnedStorage<bool, B>, DimsMaxOf<D, E>>;
+
+/// Applies `f` to each pair of broadcast elements of `lhs` and `rhs`.
+fn zip_with<T, U, V, B, S, S2, D, E, F>(
+    lhs: &TensorBase<S, D>,
+    rhs: &TensorBase<S2, E>,
+    f: F,
+) -> OmniResult<TensorBase<OwnedStorage<V, B>, DimsMaxOf<D, E>>>
+where
+    V: Copy,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    S2: Storage<Elem = U, Backend = B>,
+    D: Dimensions + DimsMax<E>,
+    E: Dimensions,
+    F: FnMut(&T, &U) -> V,
+{
+    let (lhs, rhs) = lhs.broadcast_with(rhs)?;
+    Ok(Zip::from(lhs).and(rhs).map_collect(f))
+}
+
+macro_rules! impl_comparison {
+    ($($mth:ident, $op:tt, $trt:ident, $doc:expr;)+) => {
+        impl<T, B, S, D> TensorBase<S, D>
+        where
+            B: Backend,
+            S: Storage<Elem = T, Backend = B>,
+            D: Dimensions,
+        {
+            $(
+                #[doc = concat!("Element-wise `", stringify!($op), "` (", $doc, ") with broadcasting.")]
+                ///
+                /// **Errors** with `IncompatibleBroadcast` if the shapes cannot
+                /// be broadcast together.
+                pub fn $mth<S2, E>(&self, rhs: &TensorBase<S2, E>) -> OmniResult<Mask<B, D, E>>
+                where
+                    T: $trt,
+                    S2: Storage<Elem = T, Backend = B>,
+                    D: DimsMax<E>,
+                    E: Dimensions,
+                {
+                    zip_with(self, rhs, |a, b| a $op b)
+                }
+            )+
+        }
+    };
+}
+
+impl_comparison! {
+    eq, ==, PartialEq, "equal to";
+    ne, !=, PartialEq, "not equal to";
+    lt, <, PartialOrd, "less than";
+    le, <=, PartialOrd, "less than or equal to";
+    gt, >, PartialOrd, "greater than";
+    ge, >=, PartialOrd, "greater than or equal to";
+}
+
+macro_rules! impl_logical {
+    ($($mth:ident, $op:tt, $doc:expr;)+) => {
+        impl<B, S, D> TensorBase<S, D>
+        where
+            B: Backend,
+            S: Storage<Elem = bool, Backend = B>,
+            D: Dimensions,
+        {
+            $(
+                #[doc = concat!("Element-wise logical ", $doc, " of two masks with broadcasting.")]
+                ///
+                /// **Errors** with `IncompatibleBroadcast` if the shapes cannot
+                /// be broadcast together.
+                pub fn $mth<S2, E>(&self, rhs: &TensorBase<S2, E>) -> OmniResult<Mask<B, D, E>>
+                where
+                    S2: Storage<Elem = bool, Backend = B>,
+                    D: DimsMax<E>,
+                    E: Dimensions,
+                {
+                    zip_with(self, rhs, |&a, &b| a $op b)
+                }
+            )+
+        }
+    };
+}
+
+impl_logical! {
+    logical_and, &, "and";
+    logical_or, |, "or";
+    logical_xor, ^, "exclusive or";
+}
+
+impl<B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: Storage<Elem = bool, Backend = B>,
+    D: Dimensions,
+{
+    /// Element-wise logical negation of a mask.
+    pub fn logical_not(&self) -> TensorBase<OwnedStorage<bool, B>, D> {
+        self.mapv(|a| !a)
+    }
+
+    /// Selects elements from `a` where `self` is true and from `b` elsewhere;
+    /// see [`where_`].
+    #[allow(clippy::type_complexity)]
+    pub fn select<T, S2, S3, E, F>(
+        &self,
+        a: &TensorBase<S2, E>,
+        b: &TensorBase<S3, F>,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, DimsMaxOf<DimsMaxOf<D, E>, F>>>
+    where
+        T: Copy,
+        S2: Storage<Elem = T, Backend = B>,
+        S3: Storage<Elem = T, Backend = B>,
+        D: DimsMax<E>,
+        DimsMaxOf<D, E>: DimsMax<F>,
+        E: Dimensions,
+        F: Dimensions,
+    {
+        where_(self, a, b)
+    }
+}
+
+/// Selects elements from `a` where `cond` is true and from `b` elsewhere,
+/// broadcasting all three tensors together.
+///
+/// **Errors** with `IncompatibleBroadcast` if the shapes cannot be broadcast
+/// together.
+#[allow(clippy::type_complexity)]
+pub fn where_<T, B, S, S2, S3, D, E, F>(
+    cond: &TensorBase<S, D>,
+    a: &TensorBase<S2, E>,
+    b: &TensorBase<S3, F>,
+) -> OmniResult<TensorBase<OwnedStorage<T, B>, DimsMaxOf<DimsMaxOf<D, E>, F>>>
+where
+    T: Copy,
+    B: Backend,
+    S: Storage<Elem = bool, Backend = B>,
+    S2: Storage<Elem = T, Backend = B>,
+    S3: Storage<Elem = T, Backend = B>,
+    D: Dimensions + DimsMax<E>,
+    DimsMaxOf<D, E>: DimsMax<F>,
+    E: Dimensions,
+    F: Dimensions,
+{
+    let dims: DimsMaxOf<D, E> = co_broadcast(&cond.dims, &a.dims)?;
+    let dims = co_broadcast(&dims, &b.dims)?;
+    Ok(Zip::from(cond.broadcast_view(&dims)?)
+        .and(a.broadcast_view(&dims)?)
+        .and(b.broadcast_view(&dims)?)
+        .map_collect(|&c, &x, &y| if c { x } else { y }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::{OmniError, ShapeError}, s, test_util::*};
+
+    #[test]
+    fn comparisons_match_reference() {
+        // Few distinct values for ties, and a NaN that compares false.
+        let a = from_fn([3, 4], |i| if i == [1, 2] { f64::NAN } else { ((i[0] + 2 * i[1]) % 3) as f64 });
+        let rows = from_fn([4], |i| (i[0] % 3) as f64);
+        let cols = arange([3, 1]);
+        let full = rows.broadcast_to([3, 4]).unwrap();
+        let masks = [a.eq(&full), a.ne(&full), a.lt(&full), a.le(&full), a.gt(&full), a.ge(&full)];
+        let references: [fn(f64, f64) -> bool; 6] =
+            [|x, y| x == y, |x, y| x != y, |x, y| x < y, |x, y| x <= y, |x, y| x > y, |x, y| x >= y];
+        for (k, (mask, f)) in masks.into_iter().zip(references).enumerate() {
+            let expected = from_fn([3, 4], |i| f(a[[i[0], i[1]]], rows[[i[1]]]));
+            let mask = mask.unwrap();
+            assert_eq!(to_vec(&mask), to_vec(&expected), "op {k}");
+        }
+
+        let expected = from_fn([3, 4], |i| a[[i[0], i[1]]] < cols[[i[0], 0]]);
+        assert_eq!(to_vec(&a.lt(&cols).unwrap()), to_vec(&expected));
+        let expected = from_fn([3, 4], |i| a[[i[0], i[1]]] >= rows[[i[1]]]);
+        assert_eq!(to_vec(&a.ge(&rows).unwrap()), to_vec(&expected));
+        let flipped = a.slice(s![..;-1, ..]);
+        let expected = from_fn([3, 4], |i| rows[[i[1]]] == a[[2 - i[0], i[1]]]);
+        let mask = rows.eq(&flipped).unwrap();
+        assert_eq!(mask.shape().as_slice(), &[3, 4]);
+        assert_eq!(to_vec(&mask), to_vec(&expected));
+        assert!(matches!(
+            a.eq(&arange([3])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+    }
+
+    #[test]
+    fn logical_ops_broadcast() {
+        let col = from_fn([2, 1], |i| i[0] == 1);
+        let row = from_fn([2], |i| i[0] == 1);
+        assert_eq!(to_vec(&col.logical_and(&row).unwrap()), [false, false, false, true]);
+        assert_eq!(to_vec(&col.logical_or(&row).unwrap()), [false, true, true, true]);
+        assert_eq!(to_vec(&col.logical_xor(&row).unwrap()), [false, true, true, false]);
+        let grid = col.logical_xor(&row).unwrap();
+        assert_eq!(to_vec(&grid.t().logical_not()), [true, false, false, true]);
+        assert_eq!(to_vec(&grid.slice(s![.., ..;-1]).logical_not()), [false, true, true, false]);
+        assert!(grid.logical_and(&from_fn([3], |_| true)).is_err());
+    }
+
+    #[test]
+    fn where_matches_reference() {
+        let cond = from_fn([3, 1], |i| i[0] != 1);
+        let a = noise([4]);
+        let b = noise([3, 4]);
+        let b = b.slice(s![..;-1, ..]);
+        let expected = from_fn([3, 4], |i| if i[0] != 1 { a[[i[1]]] } else { b[[i[0], i[1]]] });
+        let picked = where_(&cond, &a, &b).unwrap();
+        assert_eq!(picked.shape().as_slice(), &[3, 4]);
+        assert_eq!(to_vec(&picked), to_vec(&expected));
+        assert_eq!(to_vec(&cond.select(&a, &b).unwrap()), to_vec(&expected));
+
+        // The mask of a comparison selects like a clamp.
+        let t = noise([2, 5]);
+        let zero = from_fn([1], |_| 0.0);
+        let relu = t.gt(&zero).unwrap().select(&t, &zero).unwrap();
+        assert_eq!(to_vec(&relu), to_vec(&t).iter().map(|&x| x.max(0.0)).collect::<Vec<_>>());
+        assert!(matches!(
+            where_(&cond, &a, &arange([2, 4])),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleBroadcast(..))),
+        ));
+    }
+}
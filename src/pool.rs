@@ -0,0 +1,507 @@
+//! Pooling over the spatial axes of `NCHW` tensors.
+//!
+//! Every pooling reads each window straight from the input through its
+//! strides; no patches are materialised. [`max_pool2d`](TensorBase::max_pool2d)
+//! and [`avg_pool2d`](TensorBase::avg_pool2d) slide a window with a stride
+//! over an input with implicit padding, where `ceil_mode` keeps a last,
+//! partial window at the end of an axis. Padding never takes part in a
+//! maximum. [`adaptive_avg_pool2d`](TensorBase::adaptive_avg_pool2d) picks
+//! the windows itself so that the output has a given size.
+
+use num_traits::{Float, NumCast};
+
+use crate::{
+    backend::Backend,
+    conv::window_output,
+    dimension::{strides_as_isize, Dimensions, Dims, Dims4},
+    elem::Elem,
+    error::{OmniResult, ShapeError},
+    impl_reduce::is_greater_nan,
+    index::Ix,
+    storage::{traits::Storage, OwnedStorage},
+    strided,
+    tensor::TensorBase,
+};
+
+/// One spatial plane of the input.
+struct Plane<T> {
+    ptr: *const T,
+    dims: [Ix; 2],
+    strides: [isize; 2],
+}
+
+impl<T: Copy> Plane<T> {
+    /// Folds the elements in rows `h` and columns `w` into `init`, passing
+    /// their row-major index within the plane along.
+    unsafe fn fold<A, F>(&self, h: (Ix, Ix), w: (Ix, Ix), init: A, mut f: F) -> A
+    where
+        F: FnMut(A, T, Ix) -> A,
+    {
+        let mut acc = init;
+        for i in h.0..h.1 {
+            let row = self.ptr.offset(i as isize * self.strides[0]);
+            for j in w.0..w.1 {
+                acc = f(acc, *row.offset(j as isize * self.strides[1]), i * self.dims[1] + j);
+            }
+        }
+        acc
+    }
+}
+
+/// The windows of a strided pooling along one axis.
+#[derive(Clone, Copy)]
+struct Windows {
+    input: Ix,
+    kernel: Ix,
+    stride: Ix,
+    padding: Ix,
+}
+
+impl Windows {
+    /// The `o`-th window: its length within the padded input, and its range
+    /// within the input.
+    fn range(&self, o: Ix) -> (Ix, (Ix, Ix)) {
+        let start = (o * self.stride) as isize - self.padding as isize;
+        let end = (start + self.kernel as isize).min((self.input + self.padding) as isize);
+        let clip = |x: isize| x.clamp(0, self.input as isize) as Ix;
+        ((end - start) as Ix, (clip(start), clip(end)))
+    }
+}
+
+/// `count` as a float divisor. Every float type can represent a window size,
+/// if only rounded or as infinity, so the fallback is never reached by the
+/// float types this crate provides.
+fn divisor<T: Float>(count: Ix) -> T {
+    <T as NumCast>::from(count).unwrap_or_else(T::infinity)
+}
+
+/// The window of adaptive pooling that produces output `o` of `output` from
+/// `input` elements: `[floor(o * input / output), ceil((o + 1) * input /
+/// output))`.
+fn adaptive_range(o: Ix, input: Ix, output: Ix) -> (Ix, Ix) {
+    (o * input / output, ((o + 1) * input).div_ceil(output))
+}
+
+impl<T, B, S> TensorBase<S, Dims4>
+where
+    T: Elem,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+{
+    /// Allocates a tensor of `N x C x out_h x out_w` elements of type `U`.
+    fn pool_output<U>(&self, output: [Ix; 2]) -> TensorBase<OwnedStorage<U, B>, Dims4> {
+        let dims = Dims([self.dims[0], self.dims[1], output[0], output[1]]);
+        unsafe { TensorBase::uninit(dims, self.storage.backend()) }
+    }
+
+    /// Calls `f` with each input plane and the matching planes of `out` and
+    /// `aux`, as pointers to their first elements.
+    fn for_each_plane<U, V, F>(
+        &self,
+        out: &TensorBase<OwnedStorage<U, B>, Dims4>,
+        aux: (*mut V, [isize; 2]),
+        mut f: F,
+    ) where
+        F: FnMut(&Plane<T>, *mut U, *mut V),
+    {
+        let strides = strides_as_isize(self.strides.as_slice());
+        unsafe {
+            strided::zip3(
+                &self.dims.as_slice()[..2],
+                self.ptr.as_ptr(),
+                &strides[..2],
+                out.ptr.as_ptr(),
+                &strides_as_isize(out.strides.as_slice())[..2],
+                aux.0,
+                &aux.1,
+                |x, y, z| {
+                    let plane = Plane {
+                        ptr: x,
+                        dims: [self.dims[2], self.dims[3]],
+                        strides: [strides[2], strides[3]],
+                    };
+                    f(&plane, y, z)
+                },
+            );
+        }
+    }
+
+    /// Validates the options of a strided pooling, returning the windows
+    /// along both spatial axes and the output size.
+    fn windows(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        ceil_mode: bool,
+    ) -> OmniResult<([Windows; 2], [Ix; 2])> {
+        if padding.iter().zip(&kernel).any(|(&p, &k)| 2 * p > k) {
+            return Err(ShapeError::InvalidPadding(padding.to_vec(), kernel.to_vec()).into());
+        }
+        let input = [self.dims[2], self.dims[3]];
+        if input.contains(&0) {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let output = window_output(&input, &kernel, &stride, &padding, &[1, 1], ceil_mode)?;
+        let windows = std::array::from_fn(|d| Windows {
+            input: input[d],
+            kernel: kernel[d],
+            stride: stride[d],
+            padding: padding[d],
+        });
+        Ok((windows, [output[0], output[1]]))
+    }
+
+    /// Writes the maximum of each window to `out`, and its row-major index
+    /// within the input plane to `indices`.
+    fn max_pool_into(
+        &self,
+        [wh, ww]: [Windows; 2],
+        out: &TensorBase<OwnedStorage<T, B>, Dims4>,
+        indices: (*mut Ix, [isize; 4]),
+    ) where
+        T: PartialOrd,
+    {
+        let [oh, ow] = [out.dims[2], out.dims[3]];
+        let out_strides = strides_as_isize(out.strides.as_slice());
+        let (idx, idx_strides) = indices;
+        self.for_each_plane(out, (idx, [idx_strides[0], idx_strides[1]]), |plane, y, k| unsafe {
+            for i in 0..oh {
+                let (_, h) = wh.range(i);
+                for j in 0..ow {
+                    let (_, w) = ww.range(j);
+                    // Windows start before the right padding, so each holds an
+                    // input element to start from.
+                    let first = *plane.ptr.offset(h.0 as isize * plane.strides[0] + w.0 as isize * plane.strides[1]);
+                    let init = (first, h.0 * plane.dims[1] + w.0);
+                    let (max, at) = plane.fold(h, w, init, |(max, at), x, i| {
+                        if is_greater_nan(&x, &max) {
+                            (x, i)
+                        } else {
+                            (max, at)
+                        }
+                    });
+                    *y.offset(i as isize * out_strides[2] + j as isize * out_strides[3]) = max;
+                    *k.offset(i as isize * idx_strides[2] + j as isize * idx_strides[3]) = at;
+                }
+            }
+        });
+    }
+
+    /// 2-D max pooling with windows of size `kernel`, `stride` apart, over
+    /// the input padded by `padding` on both sides of each spatial axis. NaN
+    /// propagates.
+    ///
+    /// **Errors** with `IncompatibleShape` if the input is empty along a
+    /// spatial axis, with `InvalidPadding` if the padding exceeds half of the
+    /// kernel, with `InvalidWindowParams` if a kernel size or stride is
+    /// zero, and with `InvalidWindow` if the kernel does not fit the padded
+    /// input.
+    pub fn max_pool2d(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        ceil_mode: bool,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims4>>
+    where
+        T: PartialOrd,
+    {
+        let (windows, output) = self.windows(kernel, stride, padding, ceil_mode)?;
+        let out = self.pool_output(output);
+        // The indices are not wanted, so they all go to the same slot.
+        let mut sink: Ix = 0;
+        self.max_pool_into(windows, &out, (&mut sink, [0; 4]));
+        Ok(out)
+    }
+
+    /// Like [`max_pool2d`](Self::max_pool2d), but also returns where each
+    /// maximum was found, as its row-major index within its `H x W` input
+    /// plane.
+    #[allow(clippy::type_complexity)]
+    pub fn max_pool2d_with_indices(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        ceil_mode: bool,
+    ) -> OmniResult<(TensorBase<OwnedStorage<T, B>, Dims4>, TensorBase<OwnedStorage<Ix, B>, Dims4>)>
+    where
+        T: PartialOrd,
+    {
+        let (windows, output) = self.windows(kernel, stride, padding, ceil_mode)?;
+        let out = self.pool_output(output);
+        let indices = self.pool_output::<Ix>(output);
+        let idx_strides = strides_as_isize(indices.strides.as_slice());
+        self.max_pool_into(
+            windows,
+            &out,
+            (indices.ptr.as_ptr(), [idx_strides[0], idx_strides[1], idx_strides[2], idx_strides[3]]),
+        );
+        Ok((out, indices))
+    }
+
+    /// 2-D average pooling of a float tensor with windows of size `kernel`,
+    /// `stride` apart, over the input padded with zeros by `padding` on both
+    /// sides of each spatial axis. With `count_include_pad`, each sum is
+    /// divided by the size of its window within the padded input, and
+    /// otherwise by the number of input elements in it.
+    ///
+    /// **Errors** like [`max_pool2d`](Self::max_pool2d).
+    pub fn avg_pool2d(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        ceil_mode: bool,
+        count_include_pad: bool,
+    ) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims4>>
+    where
+        T: Float,
+    {
+        let ([wh, ww], output) = self.windows(kernel, stride, padding, ceil_mode)?;
+        let out = self.pool_output::<T>(output);
+        let out_strides = strides_as_isize(out.strides.as_slice());
+        self.for_each_plane(&out, (std::ptr::null_mut::<()>(), [0; 2]), |plane, y, _| unsafe {
+            for i in 0..output[0] {
+                let (ph, h) = wh.range(i);
+                for j in 0..output[1] {
+                    let (pw, w) = ww.range(j);
+                    let sum = plane.fold(h, w, T::zero(), |acc, x, _| acc + x);
+                    let count = if count_include_pad {
+                        ph * pw
+                    } else {
+                        (h.1 - h.0) * (w.1 - w.0)
+                    };
+                    *y.offset(i as isize * out_strides[2] + j as isize * out_strides[3]) =
+                        sum / divisor(count);
+                }
+            }
+        });
+        Ok(out)
+    }
+
+    /// 2-D average pooling of a float tensor to an output of size `output`,
+    /// with windows chosen like PyTorch's: output `i` of an axis of input
+    /// length `n` averages the elements from `floor(i * n / output)` up to
+    /// `ceil((i + 1) * n / output)`, so windows may differ in size and
+    /// overlap.
+    ///
+    /// **Errors** with `IncompatibleShape` if the input is empty along a
+    /// spatial axis while the output is not.
+    pub fn adaptive_avg_pool2d(&self, output: [usize; 2]) -> OmniResult<TensorBase<OwnedStorage<T, B>, Dims4>>
+    where
+        T: Float,
+    {
+        let input = [self.dims[2], self.dims[3]];
+        if (0..2).any(|d| input[d] == 0 && output[d] != 0) {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        let out = self.pool_output::<T>(output);
+        let out_strides = strides_as_isize(out.strides.as_slice());
+        self.for_each_plane(&out, (std::ptr::null_mut::<()>(), [0; 2]), |plane, y, _| unsafe {
+            for i in 0..output[0] {
+                let h = adaptive_range(i, input[0], output[0]);
+                for j in 0..output[1] {
+                    let w = adaptive_range(j, input[1], output[1]);
+                    let sum = plane.fold(h, w, T::zero(), |acc, x, _| acc + x);
+                    let count = (h.1 - h.0) * (w.1 - w.0);
+                    *y.offset(i as isize * out_strides[2] + j as isize * out_strides[3]) =
+                        sum / divisor(count);
+                }
+            }
+        });
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::CpuBackend,
+        dimension::{Dimensions, Dims4},
+        error::{OmniError, ShapeError},
+        storage::traits::Storage,
+        tensor::TensorBase,
+        test_util::*,
+    };
+
+    /// Output length of a pooling along one axis, following PyTorch.
+    fn output_len(input: usize, kernel: usize, stride: usize, padding: usize, ceil_mode: bool) -> usize {
+        let room = input + 2 * padding - kernel;
+        let mut len = if ceil_mode { room.div_ceil(stride) + 1 } else { room / stride + 1 };
+        if ceil_mode && (len - 1) * stride >= input + padding {
+            len -= 1;
+        }
+        len
+    }
+
+    /// Checks every output of the strided poolings of `x` against loops over
+    /// its windows.
+    fn check_strided<S>(
+        x: &TensorBase<S, Dims4>,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        ceil_mode: bool,
+    ) where
+        S: Storage<Elem = f64, Backend = CpuBackend>,
+    {
+        let [n, c, h, w] = [x.dims[0], x.dims[1], x.dims[2], x.dims[3]];
+        let oh = output_len(h, kernel[0], stride[0], padding[0], ceil_mode);
+        let ow = output_len(w, kernel[1], stride[1], padding[1], ceil_mode);
+        let (max, indices) = x.max_pool2d_with_indices(kernel, stride, padding, ceil_mode).unwrap();
+        let max_only = x.max_pool2d(kernel, stride, padding, ceil_mode).unwrap();
+        let avg = x.avg_pool2d(kernel, stride, padding, ceil_mode, false).unwrap();
+        let avg_pad = x.avg_pool2d(kernel, stride, padding, ceil_mode, true).unwrap();
+        assert_eq!(max.shape().as_slice(), &[n, c, oh, ow]);
+        for index in (0..n * c * oh * ow).map(|k| (k / (c * oh * ow), k / (oh * ow) % c, k / ow % oh, k % ow)) {
+            let (b, ch, i, j) = index;
+            let top = (i * stride[0]) as isize - padding[0] as isize;
+            let left = (j * stride[1]) as isize - padding[1] as isize;
+            let bottom = (top + kernel[0] as isize).min((h + padding[0]) as isize);
+            let right = (left + kernel[1] as isize).min((w + padding[1]) as isize);
+            let (mut best, mut at, mut sum, mut count) = (f64::NEG_INFINITY, 0, 0.0, 0);
+            for y in top.max(0) as usize..bottom.min(h as isize) as usize {
+                for z in left.max(0) as usize..right.min(w as isize) as usize {
+                    let v = x[(b, ch, y, z)];
+                    if v > best {
+                        (best, at) = (v, y * w + z);
+                    }
+                    sum += v;
+                    count += 1;
+                }
+            }
+            assert_eq!(max[index], best);
+            assert_eq!(max_only[index], best);
+            assert_eq!(indices[index], at);
+            assert_close(&[avg[index]], &[sum / count as f64]);
+            assert_close(&[avg_pad[index]], &[sum / ((bottom - top) * (right - left)) as f64]);
+        }
+    }
+
+    #[test]
+    fn strided_pooling_matches_reference() {
+        for (h, w) in [(5, 4), (6, 7), (1, 3)] {
+            // NCHW over NHWC storage, so the spatial axes are not contiguous.
+            let nhwc = noise([2, h, w, 3]);
+            let x = nhwc.view().permuted_axes([0, 3, 1, 2]).unwrap();
+            for kernel in [[1, 1], [2, 2], [3, 2], [1, 3]] {
+                for stride in [[1, 1], [2, 1], [3, 2]] {
+                    for padding in [[0, 0], [kernel[0] / 2, kernel[1] / 2]] {
+                        for ceil_mode in [false, true] {
+                            if h + 2 * padding[0] >= kernel[0] && w + 2 * padding[1] >= kernel[1] {
+                                check_strided(&x, kernel, stride, padding, ceil_mode);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ceil_mode_keeps_a_partial_window() {
+        let x = arange([1, 1, 1, 5]);
+        let floor = x.max_pool2d([1, 2], [1, 2], [0, 0], false).unwrap();
+        assert_eq!(to_vec(&floor), [1.0, 3.0]);
+        let ceil = x.max_pool2d([1, 2], [1, 2], [0, 0], true).unwrap();
+        assert_eq!(to_vec(&ceil), [1.0, 3.0, 4.0]);
+        let avg = x.avg_pool2d([1, 2], [1, 2], [0, 0], true, true).unwrap();
+        assert_eq!(to_vec(&avg), [0.5, 2.5, 4.0]);
+    }
+
+    #[test]
+    fn padding_never_wins_a_maximum() {
+        let x = from_fn([1, 1, 2, 2], |i| -1.0 - (i[2] * 2 + i[3]) as f64);
+        let (max, indices) = x.max_pool2d_with_indices([2, 2], [1, 1], [1, 1], false).unwrap();
+        assert_eq!(to_vec(&max), [-1.0, -1.0, -2.0, -1.0, -1.0, -2.0, -3.0, -3.0, -4.0]);
+        assert_eq!(to_vec(&indices), [0, 0, 1, 0, 0, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn max_pool_propagates_nan() {
+        let x = from_fn([1, 1, 2, 2], |i| if i == [0, 0, 1, 0] { f64::NAN } else { 1.0 });
+        let (max, indices) = x.max_pool2d_with_indices([2, 2], [2, 2], [0, 0], false).unwrap();
+        assert!(max[(0, 0, 0, 0)].is_nan());
+        assert_eq!(indices[(0, 0, 0, 0)], 2);
+    }
+
+    #[test]
+    fn max_pool_of_integers() {
+        let x = from_fn([1, 2, 3, 3], |i| (i[1] * 9 + i[2] * 3 + i[3]) as i8 - 9);
+        let max = x.max_pool2d([2, 2], [1, 1], [0, 0], false).unwrap();
+        assert_eq!(to_vec(&max), [-5, -4, -2, -1, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn adaptive_matches_reference() {
+        for (h, w) in [(5, 3), (4, 8), (1, 1), (7, 2)] {
+            let nhwc = noise([2, h, w, 2]);
+            let x = nhwc.view().permuted_axes([0, 3, 1, 2]).unwrap();
+            for (oh, ow) in [(3, 3), (1, 1), (2, 5), (h, w), (2 * h, 1)] {
+                let out = x.adaptive_avg_pool2d([oh, ow]).unwrap();
+                assert_eq!(out.shape().as_slice(), &[2, 2, oh, ow]);
+                for b in 0..2 {
+                    for c in 0..2 {
+                        for i in 0..oh {
+                            for j in 0..ow {
+                                let (top, bottom) = (i * h / oh, ((i + 1) * h).div_ceil(oh));
+                                let (left, right) = (j * w / ow, ((j + 1) * w).div_ceil(ow));
+                                let mut sum = 0.0;
+                                for y in top..bottom {
+                                    for x_ in left..right {
+                                        sum += x[(b, c, y, x_)];
+                                    }
+                                }
+                                let count = ((bottom - top) * (right - left)) as f64;
+                                assert_close(&[out[(b, c, i, j)]], &[sum / count]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_options() {
+        let x = arange([1, 1, 2, 2]);
+        assert!(matches!(
+            x.max_pool2d([2, 2], [1, 1], [2, 0], false),
+            Err(OmniError::ShapeError(ShapeError::InvalidPadding(..))),
+        ));
+        assert!(matches!(
+            x.avg_pool2d([3, 2], [1, 1], [0, 0], false, true),
+            Err(OmniError::ShapeError(ShapeError::InvalidWindow(..))),
+        ));
+        assert!(matches!(
+            x.max_pool2d([2, 2], [0, 1], [0, 0], false),
+            Err(OmniError::ShapeError(ShapeError::InvalidWindowParams(..))),
+        ));
+        let empty = arange([1, 1, 0, 2]);
+        assert!(matches!(
+            empty.adaptive_avg_pool2d([1, 1]),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert_eq!(empty.adaptive_avg_pool2d([0, 1]).unwrap().shape().as_slice(), &[1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn empty_planes_are_rejected() {
+        // Padding would give these windows room, but they would hold no input.
+        let empty = Tensor::<f64, Dims4>::zeros([1, 1, 0, 3]);
+        assert!(matches!(
+            empty.max_pool2d([2, 2], [1, 1], [1, 1], false),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(matches!(
+            empty.max_pool2d_with_indices([2, 2], [1, 1], [1, 1], false),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+        assert!(matches!(
+            empty.avg_pool2d([2, 2], [1, 1], [1, 1], false, false),
+            Err(OmniError::ShapeError(ShapeError::IncompatibleShape)),
+        ));
+    }
+}
@@ -1,4 +1,10 @@
-use crate::dimension::{Dimensions, IntoDimension};
+use std::marker::PhantomData;
+
+use crate::{
+    dimension::{dims_from_slice, Dimensions, Dims, DynDims, IntoDimension},
+    error::{OmniResult, ShapeError},
+    index::Ix,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Shape<D> {
@@ -34,6 +40,35 @@ impl<D> Shape<D> where D: Dimensions {
     pub fn size(&self) -> usize {
         self.dims.size()
     }
+
+    /// Whether the shape asks for column-major ("F") order.
+    pub fn is_f(&self) -> bool {
+        matches!(self.strides, Strides::F)
+    }
+
+    /// The contiguous strides for the requested order.
+    pub(crate) fn default_strides(&self) -> D {
+        match self.strides {
+            Strides::C => self.dims.default_strides(),
+            Strides::F => self.dims.fortran_strides(),
+            Strides::Custom(never) => match never {},
+        }
+    }
+}
+
+impl<D> StridedShape<D> where D: Dimensions {
+    pub fn raw_dims(&self) -> &D {
+        &self.dims
+    }
+
+    pub fn size(&self) -> usize {
+        self.dims.size()
+    }
+
+    /// The strides of the shape, in elements.
+    pub fn raw_strides(&self) -> D {
+        self.strides.clone().strides_for_dims(&self.dims)
+    }
 }
 
 impl<D> Strides<D> {
@@ -119,3 +154,110 @@ impl<D> ShapeBuilder for Shape<D> where D: Dimensions {
         }
     }
 }
+
+/// A shape where at most one axis length is given as `-1` and inferred from
+/// the number of elements of the tensor being reshaped.
+#[derive(Clone, Debug)]
+pub struct InferShape<D> {
+    dims: Vec<isize>,
+    is_f: bool,
+    marker: PhantomData<D>,
+}
+
+impl<const N: usize> InferShape<Dims<[Ix; N]>>
+where
+    Dims<[Ix; N]>: Dimensions,
+{
+    pub fn new(dims: [isize; N]) -> Self {
+        InferShape {
+            dims: dims.to_vec(),
+            is_f: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl InferShape<DynDims> {
+    pub fn from_slice(dims: &[isize]) -> Self {
+        InferShape {
+            dims: dims.to_vec(),
+            is_f: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<D> InferShape<D> {
+    /// Use column-major ("F") order.
+    pub fn f(mut self) -> Self {
+        self.is_f = true;
+        self
+    }
+}
+
+/// Shape argument of `reshape` and `into_shape`: any [`ShapeBuilder`], or an
+/// [`InferShape`] with one axis length left to infer.
+pub trait ShapeArg {
+    type Dims: Dimensions;
+
+    /// Resolves the target shape for a tensor of `size` elements, checking
+    /// that the element count is preserved.
+    fn into_shape_with_size(self, size: usize) -> OmniResult<Shape<Self::Dims>>;
+}
+
+impl<T> ShapeArg for T where T: ShapeBuilder {
+    type Dims = T::Dims;
+
+    fn into_shape_with_size(self, size: usize) -> OmniResult<Shape<Self::Dims>> {
+        let shape = self.into_shape();
+        if shape.size() != size {
+            return Err(ShapeError::IncompatibleShape.into());
+        }
+        Ok(shape)
+    }
+}
+
+impl<D> ShapeArg for InferShape<D> where D: Dimensions {
+    type Dims = D;
+
+    fn into_shape_with_size(self, size: usize) -> OmniResult<Shape<D>> {
+        let mut infer_axis = None;
+        let mut known: usize = 1;
+        for (axis, &dim) in self.dims.iter().enumerate() {
+            match dim {
+                -1 if infer_axis.is_none() => infer_axis = Some(axis),
+                dim if dim >= 0 => {
+                    known = known.checked_mul(dim as usize).ok_or(ShapeError::IncompatibleShape)?;
+                },
+                _ => return Err(ShapeError::IncompatibleShape.into()),
+            }
+        }
+        let mut dims: Vec<Ix> = self.dims.iter().map(|&d| d.max(0) as Ix).collect();
+        match infer_axis {
+            Some(axis) => {
+                if known == 0 || !size.is_multiple_of(known) {
+                    return Err(ShapeError::IncompatibleShape.into());
+                }
+                dims[axis] = size / known;
+            },
+            None if known != size => return Err(ShapeError::IncompatibleShape.into()),
+            None => {},
+        }
+        Ok(dims_from_slice::<D>(&dims).set_f(self.is_f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShapeBuilder;
+    use crate::dimension::{Dimensions, IntoDimension};
+
+    #[test]
+    fn strided_shapes() {
+        let shape = [2, 3].strides([1, 2]);
+        assert_eq!(shape.raw_dims().as_slice(), &[2, 3]);
+        assert_eq!(shape.raw_strides().as_slice(), &[1, 2]);
+        assert_eq!(shape.size(), 6);
+        assert_eq!([2, 3].f().strides([4, 1].into_dimension()).raw_strides().as_slice(), &[4, 1]);
+    }
+}
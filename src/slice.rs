@@ -0,0 +1,674 @@
+//! Slicing of tensors into sub-views, driven by the [`s!`](crate::s) macro.
+
+use std::{
+    marker::PhantomData,
+    ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
+    ptr::NonNull,
+};
+
+use crate::{
+    backend::Backend,
+    dimension::{dims_from_slice, Dimensions},
+    index::Ix,
+    storage::traits::{RawStorage, RawStorageMut},
+    tensor::TensorBase,
+    tensor_view::{TensorView, TensorViewMut},
+};
+
+/// Inserts a new axis of length 1 when used in [`s!`](crate::s).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NewAxis;
+
+/// Stands for as many full slices as needed to cover the remaining axes when
+/// used in [`s!`](crate::s). At most one ellipsis may appear in a slice.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ellipsis;
+
+/// A single element of a slice description.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliceInfoElem {
+    /// Keeps the axis, selecting `start..end` with the given `step`.
+    ///
+    /// Negative `start`/`end` count from the end of the axis and a missing
+    /// `end` means the end of the axis. A negative `step` selects the same
+    /// elements as its positive counterpart, in reverse order.
+    Slice {
+        start: isize,
+        end: Option<isize>,
+        step: isize,
+    },
+    /// Selects a single position and removes the axis. Negative positions
+    /// count from the end of the axis.
+    Index(isize),
+    /// Inserts a new axis of length 1.
+    NewAxis,
+    /// Expands to full slices over the axes that are not otherwise covered.
+    Ellipsis,
+}
+
+/// Type-level marker for slice elements that keep an axis.
+#[derive(Copy, Clone, Debug)]
+pub enum KeepAxis {}
+
+/// Type-level marker for slice elements that remove an axis.
+#[derive(Copy, Clone, Debug)]
+pub enum RemoveAxis {}
+
+/// Type-level marker for slice elements that insert an axis.
+#[derive(Copy, Clone, Debug)]
+pub enum InsertAxis {}
+
+/// Conversion of the values accepted by [`s!`](crate::s) into slice elements.
+pub trait SliceArg {
+    /// How the element changes the dimensionality: [`KeepAxis`],
+    /// [`RemoveAxis`] or [`InsertAxis`].
+    type Kind;
+
+    fn into_elem(self) -> SliceInfoElem;
+}
+
+/// Ranges that accept a step in [`s!`](crate::s).
+pub trait SliceRange: SliceArg<Kind = KeepAxis> {
+    fn with_step(self, step: isize) -> SliceInfoElem;
+}
+
+/// Computes the dimensionality produced by a slice on a tensor of type `D`.
+///
+/// Implemented on the type-level list built by [`SliceInfo::push`]: each
+/// removed axis goes through `Dimensions::Smaller` and each inserted axis
+/// through `Dimensions::Larger`.
+pub trait SliceDims<D: Dimensions> {
+    type Output: Dimensions;
+}
+
+impl<D: Dimensions> SliceDims<D> for () {
+    type Output = D;
+}
+
+impl<D: Dimensions, K: SliceDims<D>> SliceDims<D> for (K, KeepAxis) {
+    type Output = K::Output;
+}
+
+impl<D: Dimensions, K: SliceDims<D>> SliceDims<D> for (K, RemoveAxis) {
+    type Output = <K::Output as Dimensions>::Smaller;
+}
+
+impl<D: Dimensions, K: SliceDims<D>> SliceDims<D> for (K, InsertAxis) {
+    type Output = <K::Output as Dimensions>::Larger;
+}
+
+/// A slice description, usually built with the [`s!`](crate::s) macro.
+///
+/// `K` records at the type level which elements remove or insert axes, so
+/// that the dimensionality of the sliced tensor is known statically.
+#[derive(Clone, Debug)]
+pub struct SliceInfo<K> {
+    elems: Vec<SliceInfoElem>,
+    kind: PhantomData<K>,
+}
+
+impl SliceInfo<()> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        SliceInfo {
+            elems: Vec::new(),
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<K> SliceInfo<K> {
+    pub fn push<A: SliceArg>(mut self, arg: A) -> SliceInfo<(K, A::Kind)> {
+        self.elems.push(arg.into_elem());
+        SliceInfo {
+            elems: self.elems,
+            kind: PhantomData,
+        }
+    }
+
+    pub fn push_step<R: SliceRange>(mut self, range: R, step: isize) -> SliceInfo<(K, KeepAxis)> {
+        self.elems.push(range.with_step(step));
+        SliceInfo {
+            elems: self.elems,
+            kind: PhantomData,
+        }
+    }
+
+    pub fn elems(&self) -> &[SliceInfoElem] {
+        &self.elems
+    }
+
+    /// Resolves the ellipsis (or the implicit trailing full slices) for a
+    /// tensor with `ndim` axes.
+    ///
+    /// **Panics** if there is more than one ellipsis or the description
+    /// covers more than `ndim` axes.
+    fn expand(&self, ndim: usize) -> Vec<SliceInfoElem> {
+        let n_ellipsis = self.elems.iter().filter(|e| **e == SliceInfoElem::Ellipsis).count();
+        assert!(n_ellipsis <= 1, "at most one ellipsis is allowed in a slice");
+        let n_covered = self
+            .elems
+            .iter()
+            .filter(|e| matches!(e, SliceInfoElem::Slice { .. } | SliceInfoElem::Index(_)))
+            .count();
+        assert!(
+            n_covered <= ndim,
+            "slice covers {} axes but the tensor has {}",
+            n_covered,
+            ndim,
+        );
+        let full = SliceInfoElem::Slice {
+            start: 0,
+            end: None,
+            step: 1,
+        };
+        let mut out = Vec::with_capacity(self.elems.len() + ndim - n_covered);
+        for &elem in &self.elems {
+            if elem == SliceInfoElem::Ellipsis {
+                out.extend(std::iter::repeat_n(full, ndim - n_covered));
+            } else {
+                out.push(elem);
+            }
+        }
+        if n_ellipsis == 0 {
+            out.extend(std::iter::repeat_n(full, ndim - n_covered));
+        }
+        out
+    }
+}
+
+/// Builds a [`SliceInfo`] for [`TensorBase::slice`] and friends.
+///
+/// Each comma-separated element is one of:
+///
+/// - a range (`a..b`, `a..`, `..b`, `..`, `a..=b`, ...), optionally followed
+///   by `;step`, which keeps the axis;
+/// - an integer, which selects one position and removes the axis;
+/// - [`NewAxis`](crate::slice::NewAxis), which inserts an axis of length 1;
+/// - [`Ellipsis`](crate::slice::Ellipsis), which stands for full slices over
+///   all the axes that are not otherwise covered.
+///
+/// Negative positions count from the end of the axis. Axes that are not
+/// covered at the end are sliced fully.
+///
+/// ```ignore
+/// let view = tensor.slice(s![1..;2, -1, NewAxis, ..;-1]);
+/// ```
+#[macro_export]
+macro_rules! s {
+    (@build $acc:expr;) => {
+        $acc
+    };
+    (@build $acc:expr; $r:expr; $step:expr) => {
+        $acc.push_step($r, $step as isize)
+    };
+    (@build $acc:expr; $r:expr; $step:expr, $($rest:tt)*) => {
+        $crate::s!(@build $acc.push_step($r, $step as isize); $($rest)*)
+    };
+    (@build $acc:expr; $r:expr) => {
+        $acc.push($r)
+    };
+    (@build $acc:expr; $r:expr, $($rest:tt)*) => {
+        $crate::s!(@build $acc.push($r); $($rest)*)
+    };
+    ($($t:tt)*) => {
+        $crate::s!(@build $crate::slice::SliceInfo::new(); $($t)*)
+    };
+}
+
+impl SliceArg for NewAxis {
+    type Kind = InsertAxis;
+
+    fn into_elem(self) -> SliceInfoElem {
+        SliceInfoElem::NewAxis
+    }
+}
+
+impl SliceArg for Ellipsis {
+    type Kind = KeepAxis;
+
+    fn into_elem(self) -> SliceInfoElem {
+        SliceInfoElem::Ellipsis
+    }
+}
+
+impl SliceArg for RangeFull {
+    type Kind = KeepAxis;
+
+    fn into_elem(self) -> SliceInfoElem {
+        self.with_step(1)
+    }
+}
+
+impl SliceRange for RangeFull {
+    fn with_step(self, step: isize) -> SliceInfoElem {
+        SliceInfoElem::Slice {
+            start: 0,
+            end: None,
+            step,
+        }
+    }
+}
+
+macro_rules! impl_slice_arg {
+    ($($t:ty),*) => {
+        $(
+            impl SliceArg for $t {
+                type Kind = RemoveAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    SliceInfoElem::Index(self as isize)
+                }
+            }
+
+            impl SliceArg for Range<$t> {
+                type Kind = KeepAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    self.with_step(1)
+                }
+            }
+
+            impl SliceRange for Range<$t> {
+                fn with_step(self, step: isize) -> SliceInfoElem {
+                    SliceInfoElem::Slice {
+                        start: self.start as isize,
+                        end: Some(self.end as isize),
+                        step,
+                    }
+                }
+            }
+
+            impl SliceArg for RangeInclusive<$t> {
+                type Kind = KeepAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    self.with_step(1)
+                }
+            }
+
+            impl SliceRange for RangeInclusive<$t> {
+                fn with_step(self, step: isize) -> SliceInfoElem {
+                    let end = *self.end() as isize + 1;
+                    SliceInfoElem::Slice {
+                        start: *self.start() as isize,
+                        end: if end == 0 { None } else { Some(end) },
+                        step,
+                    }
+                }
+            }
+
+            impl SliceArg for RangeFrom<$t> {
+                type Kind = KeepAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    self.with_step(1)
+                }
+            }
+
+            impl SliceRange for RangeFrom<$t> {
+                fn with_step(self, step: isize) -> SliceInfoElem {
+                    SliceInfoElem::Slice {
+                        start: self.start as isize,
+                        end: None,
+                        step,
+                    }
+                }
+            }
+
+            impl SliceArg for RangeTo<$t> {
+                type Kind = KeepAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    self.with_step(1)
+                }
+            }
+
+            impl SliceRange for RangeTo<$t> {
+                fn with_step(self, step: isize) -> SliceInfoElem {
+                    SliceInfoElem::Slice {
+                        start: 0,
+                        end: Some(self.end as isize),
+                        step,
+                    }
+                }
+            }
+
+            impl SliceArg for RangeToInclusive<$t> {
+                type Kind = KeepAxis;
+
+                fn into_elem(self) -> SliceInfoElem {
+                    self.with_step(1)
+                }
+            }
+
+            impl SliceRange for RangeToInclusive<$t> {
+                fn with_step(self, step: isize) -> SliceInfoElem {
+                    let end = self.end as isize + 1;
+                    SliceInfoElem::Slice {
+                        start: 0,
+                        end: if end == 0 { None } else { Some(end) },
+                        step,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_slice_arg!(isize, usize, i32);
+
+/// Turns a possibly negative position into an absolute one.
+fn abs_index(len: Ix, index: isize) -> isize {
+    if index < 0 {
+        len as isize + index
+    } else {
+        index
+    }
+}
+
+/// Resolves a slice on an axis of length `len` and stride `stride`.
+///
+/// Returns the offset of the first selected element, the new length and the
+/// new stride.
+fn slice_axis(len: Ix, stride: isize, start: isize, end: Option<isize>, step: isize) -> (isize, Ix, isize) {
+    assert!(step != 0, "slice step cannot be zero");
+    let start = abs_index(len, start);
+    let end = end.map_or(len as isize, |end| abs_index(len, end));
+    assert!(
+        0 <= start && start <= len as isize,
+        "slice start {} is out of bounds for axis of length {}",
+        start,
+        len,
+    );
+    assert!(
+        0 <= end && end <= len as isize,
+        "slice end {} is out of bounds for axis of length {}",
+        end,
+        len,
+    );
+    let span = end.max(start) - start;
+    let abs_step = step.unsigned_abs();
+    let new_len = (span as usize).div_ceil(abs_step);
+    let mut offset = start * stride;
+    if step < 0 && new_len > 0 {
+        // Start from the last selected element and walk backwards.
+        offset += (new_len - 1) as isize * abs_step as isize * stride;
+    }
+    (offset, new_len, stride * step)
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// Returns a view of the region described by `info`.
+    ///
+    /// **Panics** if a position is out of bounds or `info` covers more axes
+    /// than `self` has.
+    pub fn slice<K>(&self, info: SliceInfo<K>) -> TensorView<'_, T, B, K::Output>
+    where
+        K: SliceDims<D>,
+    {
+        self.view().slice_move(info)
+    }
+
+    /// Returns a mutable view of the region described by `info`.
+    ///
+    /// **Panics** if a position is out of bounds or `info` covers more axes
+    /// than `self` has.
+    pub fn slice_mut<K>(&mut self, info: SliceInfo<K>) -> TensorViewMut<'_, T, B, K::Output>
+    where
+        S: RawStorageMut,
+        K: SliceDims<D>,
+    {
+        self.view_mut().slice_move(info)
+    }
+
+    /// Slices `self` in place and returns it with the new dimensionality.
+    ///
+    /// **Panics** if a position is out of bounds or `info` covers more axes
+    /// than `self` has.
+    pub fn slice_move<K>(self, info: SliceInfo<K>) -> TensorBase<S, K::Output>
+    where
+        K: SliceDims<D>,
+    {
+        let elems = info.expand(self.ndim());
+        let in_dims = self.dims.as_slice();
+        let in_strides = self.strides();
+        let mut dims = Vec::with_capacity(elems.len());
+        let mut strides = Vec::with_capacity(elems.len());
+        let mut offset = 0;
+        let mut axis = 0;
+        for elem in elems {
+            match elem {
+                SliceInfoElem::Slice { start, end, step } => {
+                    let (off, len, stride) = slice_axis(in_dims[axis], in_strides[axis], start, end, step);
+                    offset += off;
+                    dims.push(len);
+                    strides.push(stride as Ix);
+                    axis += 1;
+                },
+                SliceInfoElem::Index(index) => {
+                    let len = in_dims[axis];
+                    let i = abs_index(len, index);
+                    assert!(
+                        0 <= i && i < len as isize,
+                        "index {} is out of bounds for axis {} with length {}",
+                        index,
+                        axis,
+                        len,
+                    );
+                    offset += i * in_strides[axis];
+                    axis += 1;
+                },
+                SliceInfoElem::NewAxis => {
+                    dims.push(1);
+                    strides.push(0);
+                },
+                SliceInfoElem::Ellipsis => unreachable!(),
+            }
+        }
+        if let Some(ndim) = K::Output::NDIM {
+            assert_eq!(ndim, dims.len(), "slice does not match the tensor dimensionality");
+        }
+        // Like `slice_collapse`, an empty result keeps the original pointer.
+        let ptr = if dims.contains(&0) {
+            self.ptr
+        } else {
+            unsafe { NonNull::new_unchecked(self.ptr.as_ptr().wrapping_offset(offset)) }
+        };
+        TensorBase {
+            storage: self.storage,
+            ptr,
+            dims: dims_from_slice(&dims),
+            strides: dims_from_slice(&strides),
+        }
+    }
+
+    /// Slices `self` in place without changing its dimensionality: indexed
+    /// axes are kept with length 1.
+    ///
+    /// **Panics** if a position is out of bounds, `info` covers more axes
+    /// than `self` has or `info` inserts new axes.
+    pub fn slice_collapse<K>(&mut self, info: SliceInfo<K>) {
+        let elems = info.expand(self.ndim());
+        let mut offset = 0;
+        for (axis, elem) in elems.into_iter().enumerate() {
+            let len = self.dims[axis];
+            let stride = self.strides()[axis];
+            match elem {
+                SliceInfoElem::Slice { start, end, step } => {
+                    let (off, len, stride) = slice_axis(len, stride, start, end, step);
+                    offset += off;
+                    self.dims[axis] = len;
+                    self.strides[axis] = stride as Ix;
+                },
+                SliceInfoElem::Index(index) => {
+                    let i = abs_index(len, index);
+                    assert!(
+                        0 <= i && i < len as isize,
+                        "index {} is out of bounds for axis {} with length {}",
+                        index,
+                        axis,
+                        len,
+                    );
+                    offset += i * stride;
+                    self.dims[axis] = 1;
+                },
+                SliceInfoElem::NewAxis => panic!("slice_collapse cannot insert new axes"),
+                SliceInfoElem::Ellipsis => unreachable!(),
+            }
+        }
+        if self.dims.size() != 0 {
+            self.ptr = unsafe { NonNull::new_unchecked(self.ptr.as_ptr().wrapping_offset(offset)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dimension::{Dims2, Dims3, Dims4},
+        test_util::*,
+    };
+
+    /// The positions that `start..end;step` selects from an axis of length
+    /// `len`, in order, or `None` if the bounds are out of range.
+    fn reference(len: Ix, start: isize, end: Option<isize>, step: isize) -> Option<Vec<Ix>> {
+        let resolve = |i: isize| if i < 0 { i + len as isize } else { i };
+        let (start, end) = (resolve(start), end.map_or(len as isize, resolve));
+        if !(0..=len as isize).contains(&start) || !(0..=len as isize).contains(&end) {
+            return None;
+        }
+        let mut positions: Vec<Ix> = (start..end.max(start)).step_by(step.unsigned_abs()).map(|i| i as Ix).collect();
+        if step < 0 {
+            positions.reverse();
+        }
+        Some(positions)
+    }
+
+    #[test]
+    fn single_axis_matches_reference() {
+        let len = 6;
+        let t = arange([len]);
+        for start in -(len as isize)..=len as isize {
+            for end in (-(len as isize)..=len as isize).map(Some).chain([None]) {
+                for step in [-3, -2, -1, 1, 2, 4] {
+                    let Some(positions) = reference(len, start, end, step) else {
+                        continue;
+                    };
+                    let info = match end {
+                        Some(end) => SliceInfo::new().push_step(start..end, step),
+                        None => SliceInfo::new().push_step(start.., step),
+                    };
+                    let v = t.slice(info);
+                    let expected: Vec<f64> = positions.iter().map(|&i| i as f64).collect();
+                    assert_eq!(to_vec(&v), expected, "{start}..{end:?};{step}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_elements() {
+        let t = arange([3, 4, 5]);
+        let v: TensorView<'_, f64, _, Dims3> = t.slice(s![1..;-1, NewAxis, -1, ..;2]);
+        assert_eq!(v.shape().as_slice(), &[2, 1, 3]);
+        let expected = from_fn([2, 1, 3], |i| ((2 - i[0]) * 20 + 15 + 2 * i[2]) as f64);
+        assert_eq!(to_vec(&v), to_vec(&expected));
+
+        let v: TensorView<'_, f64, _, Dims4> = t.slice(s![NewAxis, 2, NewAxis, ..=1, -2..;-1]);
+        assert_eq!(v.shape().as_slice(), &[1, 1, 2, 2]);
+        assert_eq!(to_vec(&v), [44.0, 43.0, 49.0, 48.0]);
+
+        // Axes that are not covered are sliced fully.
+        let v = t.slice(s![..;-2]);
+        let expected = from_fn([2, 4, 5], |i| ((2 - 2 * i[0]) * 20 + i[1] * 5 + i[2]) as f64);
+        assert_eq!(to_vec(&v), to_vec(&expected));
+    }
+
+    #[test]
+    fn ellipsis() {
+        let t = arange([2, 3, 4]);
+        let v = t.slice(s![Ellipsis, 1]);
+        assert_eq!(to_vec(&v), to_vec(&from_fn([2, 3], |i| (i[0] * 12 + i[1] * 4 + 1) as f64)));
+        let v = t.slice(s![1, Ellipsis, ..;-3]);
+        assert_eq!(to_vec(&v), [15.0, 12.0, 19.0, 16.0, 23.0, 20.0]);
+        let v = t.slice(s![NewAxis, Ellipsis, NewAxis]);
+        assert_eq!(v.shape().as_slice(), &[1, 2, 3, 4, 1]);
+        let v = t.slice(s![0, 1, Ellipsis, 2]);
+        assert_eq!(to_vec(&v), [6.0]);
+    }
+
+    #[test]
+    fn slices_of_strided_views_compose() {
+        let t = arange([6, 5]);
+        let outer = t.slice(s![1..;2, ..;-1]);
+        let inner = outer.slice(s![..;-1, 1..4;2]);
+        let expected = from_fn([3, 2], |i| ((5 - 2 * i[0]) * 5 + 3 - 2 * i[1]) as f64);
+        assert_eq!(to_vec(&inner), to_vec(&expected));
+
+        let empty: TensorView<'_, f64, _, Dims2> = t.slice(s![3..3, ..;-1]);
+        assert_eq!(empty.shape().as_slice(), &[0, 5]);
+        assert!(to_vec(&empty.slice(s![..;-1, 2])).is_empty());
+    }
+
+    #[test]
+    fn slice_mut_and_collapse() {
+        let mut t = arange([3, 4]);
+        t.slice_mut(s![..;-2, 1..3]).map_inplace(|x| *x = -*x);
+        assert_eq!(to_vec(&t), [0.0, -1.0, -2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, -9.0, -10.0, 11.0]);
+
+        let mut v = t.view();
+        v.slice_collapse(s![-1, ..;-3]);
+        let v: TensorView<'_, f64, _, Dims2> = v;
+        assert_eq!(v.shape().as_slice(), &[1, 2]);
+        assert_eq!(to_vec(&v), [11.0, 8.0]);
+    }
+
+    #[test]
+    fn empty_slices_keep_the_pointer() {
+        let t = arange([4, 3]);
+        let moved: TensorView<'_, f64, _, Dims2> = t.view().slice_move(s![2..2, 1..]);
+        let mut collapsed = t.view();
+        collapsed.slice_collapse(s![2..2, 1..]);
+        assert_eq!(moved.shape().as_slice(), &[0, 2]);
+        assert_eq!(collapsed.shape().as_slice(), &[0, 2]);
+        assert_eq!(moved.as_ptr(), t.as_ptr());
+        assert_eq!(collapsed.as_ptr(), t.as_ptr());
+    }
+
+    #[test]
+    #[should_panic(expected = "step cannot be zero")]
+    fn zero_step_panics() {
+        let _ = arange([3]).slice(s![..;0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn index_out_of_bounds_panics() {
+        let _ = arange([3, 2]).slice(s![.., -3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn range_out_of_bounds_panics() {
+        let _ = arange([3]).slice(s![1..5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most one ellipsis")]
+    fn two_ellipses_panic() {
+        let _ = arange([3, 2]).slice(s![Ellipsis, 0, Ellipsis]);
+    }
+
+    #[test]
+    #[should_panic(expected = "covers 3 axes")]
+    fn too_many_axes_panic() {
+        let _ = arange([3, 2]).slice(s![.., 0, 1]);
+    }
+}
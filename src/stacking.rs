@@ -0,0 +1,324 @@
+//! Joining tensors along an axis, and splitting them into views.
+
+use std::ptr::NonNull;
+
+use crate::{
+    backend::Backend,
+    dimension::{check_axis, strides_as_isize, Dimensions},
+    error::{OmniResult, ShapeError},
+    index::{Axis, Ix},
+    storage::{traits::RawStorage, OwnedStorage},
+    tensor::TensorBase,
+    tensor_view::TensorView,
+};
+
+/// Joins `tensors` along the existing `axis`. All other axes must have the
+/// same lengths.
+///
+/// **Errors** with `NoTensors` if `tensors` is empty, `AxisOutOfBounds` if
+/// the axis does not exist, and `IncompatibleConcatenate` naming the first
+/// pair of shapes that cannot be joined.
+pub fn concatenate<T, B, D>(
+    axis: Axis,
+    tensors: &[TensorView<'_, T, B, D>],
+) -> OmniResult<TensorBase<OwnedStorage<T, B>, D>>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    let first = tensors.first().ok_or(ShapeError::NoTensors)?;
+    let axis = check_axis(axis, first.ndim())?;
+
+    let mut dims = first.dims.clone();
+    dims[axis] = 0;
+    for tensor in tensors {
+        let compatible = tensor.ndim() == first.ndim()
+            && (0..first.ndim()).all(|i| i == axis || tensor.dims[i] == first.dims[i]);
+        if !compatible {
+            return Err(ShapeError::IncompatibleConcatenate(
+                first.dims.as_slice().to_vec(),
+                tensor.dims.as_slice().to_vec(),
+                axis,
+            )
+            .into());
+        }
+        dims[axis] += tensor.dims[axis];
+    }
+
+    let backend = first.storage.backend();
+    unsafe {
+        let out = TensorBase::<OwnedStorage<T, B>, D>::uninit(dims, backend);
+        let out_strides = strides_as_isize(out.strides.as_slice());
+        let mut offset = 0;
+        for tensor in tensors {
+            backend.copy_strided(
+                tensor.dims.as_slice(),
+                tensor.ptr.as_ptr(),
+                strides_as_isize(tensor.strides.as_slice()),
+                out.ptr.as_ptr().wrapping_offset(offset as isize * out_strides[axis]),
+                out_strides,
+            );
+            offset += tensor.dims[axis];
+        }
+        Ok(out)
+    }
+}
+
+/// Joins `tensors` along a new axis inserted at position `axis`. All
+/// tensors must have the same shape.
+///
+/// **Errors** with `NoTensors` if `tensors` is empty, `AxisOutOfBounds` if
+/// `axis` is past the last position, and `IncompatibleStack` naming the first
+/// pair of differing shapes.
+pub fn stack<T, B, D>(
+    axis: Axis,
+    tensors: &[TensorView<'_, T, B, D>],
+) -> OmniResult<TensorBase<OwnedStorage<T, B>, D::Larger>>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    let first = tensors.first().ok_or(ShapeError::NoTensors)?;
+    let views = tensors
+        .iter()
+        .map(|tensor| {
+            if tensor.dims.as_slice() != first.dims.as_slice() {
+                return Err(ShapeError::IncompatibleStack(
+                    first.dims.as_slice().to_vec(),
+                    tensor.dims.as_slice().to_vec(),
+                )
+                .into());
+            }
+            tensor.view().insert_axis(axis)
+        })
+        .collect::<OmniResult<Vec<_>>>()?;
+    concatenate(axis, &views)
+}
+
+impl<T, B, S, D> TensorBase<S, D>
+where
+    B: Backend,
+    S: RawStorage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    /// View of the indices `start..end` along `axis`, which must be in bounds.
+    fn axis_range_view(&self, axis: usize, start: Ix, end: Ix) -> TensorView<'_, T, B, D> {
+        debug_assert!(start <= end && end <= self.dims[axis]);
+        let mut dims = self.dims.clone();
+        dims[axis] = end - start;
+        let offset = start as isize * self.strides[axis] as isize;
+        let ptr = unsafe { NonNull::new_unchecked(self.ptr.as_ptr().wrapping_offset(offset)) };
+        TensorView::new(ptr, dims, self.strides.clone(), self.storage.backend())
+    }
+
+    /// Splits the tensor into the views before and after `index` along
+    /// `axis`.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `InvalidSplit` if `index` is past the end of the axis.
+    #[allow(clippy::type_complexity)]
+    pub fn split_at(
+        &self,
+        axis: Axis,
+        index: Ix,
+    ) -> OmniResult<(TensorView<'_, T, B, D>, TensorView<'_, T, B, D>)> {
+        let axis = check_axis(axis, self.ndim())?;
+        let len = self.dims[axis];
+        if index > len {
+            return Err(ShapeError::InvalidSplit(vec![index], len).into());
+        }
+        Ok((
+            self.axis_range_view(axis, 0, index),
+            self.axis_range_view(axis, index, len),
+        ))
+    }
+
+    /// Splits the tensor at each of the sorted `indices` along `axis`,
+    /// returning `indices.len() + 1` views.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `InvalidSplit` if the indices are not sorted or exceed the axis.
+    pub fn split(&self, axis: Axis, indices: &[Ix]) -> OmniResult<Vec<TensorView<'_, T, B, D>>> {
+        let axis = check_axis(axis, self.ndim())?;
+        let len = self.dims[axis];
+        let is_valid = indices.windows(2).all(|w| w[0] <= w[1])
+            && indices.last().is_none_or(|&last| last <= len);
+        if !is_valid {
+            return Err(ShapeError::InvalidSplit(indices.to_vec(), len).into());
+        }
+        let starts = std::iter::once(0).chain(indices.iter().copied());
+        let ends = indices.iter().copied().chain(std::iter::once(len));
+        Ok(starts
+            .zip(ends)
+            .map(|(start, end)| self.axis_range_view(axis, start, end))
+            .collect())
+    }
+
+    /// Splits the tensor into `chunks` views of equal length along `axis`,
+    /// except for the last one, which is shorter if the length does not
+    /// divide evenly. Fewer views are returned if the axis is too short to
+    /// make `chunks` non-empty ones.
+    ///
+    /// **Errors** with `AxisOutOfBounds` if the axis does not exist, and with
+    /// `InvalidChunks` if `chunks` is zero.
+    pub fn chunk(&self, axis: Axis, chunks: usize) -> OmniResult<Vec<TensorView<'_, T, B, D>>> {
+        let axis = check_axis(axis, self.ndim())?;
+        if chunks == 0 {
+            return Err(ShapeError::InvalidChunks(chunks).into());
+        }
+        let len = self.dims[axis];
+        let size = len.div_ceil(chunks).max(1);
+        Ok((0..len.max(1))
+            .step_by(size)
+            .map(|start| self.axis_range_view(axis, start, (start + size).min(len)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::CpuBackend, dimension::{Dims2, Dims3}, error::OmniError, s, test_util::*};
+
+    type View<'a, D> = TensorView<'a, f64, CpuBackend, D>;
+
+    /// The piece holding index `i` of the concatenation along `axis`, and
+    /// the index within that piece.
+    fn locate(pieces: &[View<'_, Dims3>], axis: usize, i: &[Ix]) -> (usize, [Ix; 3]) {
+        let mut index = [i[0], i[1], i[2]];
+        for (k, piece) in pieces.iter().enumerate() {
+            let len = piece.shape().as_slice()[axis];
+            if index[axis] < len {
+                return (k, index);
+            }
+            index[axis] -= len;
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn concatenate_matches_reference() {
+        for axis in 0..3 {
+            // Pieces of lengths 2, 0 and 3 along `axis`, each in a different layout.
+            let shape = |len: Ix| {
+                let mut shape = [2, 3, 4];
+                shape[axis] = len;
+                shape
+            };
+            let a = noise(shape(2));
+            let b = noise(shape(0));
+            let c = arange([shape(3)[2], shape(3)[0], shape(3)[1]]);
+            let pieces = [
+                a.slice(s![..;-1, ..;-1, ..]),
+                b.view(),
+                c.view().permuted_axes([1, 2, 0]).unwrap(),
+            ];
+            let joined = concatenate(Axis(axis), &pieces).unwrap();
+            assert_eq!(joined.shape().as_slice(), &shape(5));
+            let expected = from_fn(shape(5), |i| {
+                let (k, index) = locate(&pieces, axis, i);
+                pieces[k][index]
+            });
+            assert_eq!(to_vec(&joined), to_vec(&expected), "axis {axis}");
+        }
+    }
+
+    #[test]
+    fn stack_matches_reference() {
+        let (a, b) = (noise([3, 4]), arange([4, 3]));
+        let pieces = [a.view(), b.t(), a.slice(s![..;-1, ..;-1])];
+        for axis in 0..3 {
+            let stacked = stack(Axis(axis), &pieces).unwrap();
+            let mut shape = vec![3, 4];
+            shape.insert(axis, 3);
+            assert_eq!(stacked.shape().as_slice(), shape.as_slice());
+            let expected = from_fn(shape, |i| {
+                let mut index = i.to_vec();
+                let k = index.remove(axis);
+                pieces[k][[index[0], index[1]]]
+            });
+            assert_eq!(to_vec(&stacked), to_vec(&expected), "axis {axis}");
+        }
+    }
+
+    #[test]
+    fn joining_errors() {
+        let (a, b) = (arange([2, 3]), arange([2, 4]));
+        let none: [View<'_, Dims2>; 0] = [];
+        assert!(matches!(
+            concatenate(Axis(0), &none),
+            Err(OmniError::ShapeError(ShapeError::NoTensors)),
+        ));
+        assert!(matches!(stack(Axis(0), &none), Err(OmniError::ShapeError(ShapeError::NoTensors))));
+        assert_eq!(concatenate(Axis(1), &[a.view(), b.view()]).unwrap().shape().as_slice(), &[2, 7]);
+        match concatenate(Axis(0), &[a.view(), a.view(), b.view()]) {
+            Ok(_) => panic!("joined shapes [2, 3] and [2, 4] along axis 0"),
+            Err(e) => assert!(matches!(
+                e,
+                OmniError::ShapeError(ShapeError::IncompatibleConcatenate(ref x, ref y, 0))
+                    if x == &[2, 3] && y == &[2, 4]
+            )),
+        }
+        assert!(matches!(
+            concatenate(Axis(2), &[a.view()]),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(2, 2))),
+        ));
+        match stack(Axis(1), &[a.view(), b.view()]) {
+            Ok(_) => panic!("stacked shapes [2, 3] and [2, 4]"),
+            Err(e) => assert!(matches!(
+                e,
+                OmniError::ShapeError(ShapeError::IncompatibleStack(ref x, ref y)) if x == &[2, 3] && y == &[2, 4]
+            )),
+        }
+        assert!(stack(Axis(2), &[a.view()]).is_ok());
+        assert!(stack(Axis(3), &[a.view()]).is_err());
+    }
+
+    #[test]
+    fn splits_are_views_in_order() {
+        let t = arange([7, 3]);
+        let v = t.slice(s![..;-1, ..]);
+        let elems = to_vec(&v);
+        let rows = |views: &[View<'_, Dims2>]| views.iter().map(|v| v.shape().as_slice()[0]).collect::<Vec<_>>();
+
+        let (head, tail) = v.split_at(Axis(0), 2).unwrap();
+        assert_eq!(rows(&[head.clone(), tail.clone()]), [2, 5]);
+        assert_eq!([to_vec(&head), to_vec(&tail)].concat(), elems);
+
+        let parts = v.split(Axis(0), &[0, 3, 3, 7]).unwrap();
+        assert_eq!(rows(&parts), [0, 3, 0, 4, 0]);
+        assert_eq!(parts.iter().flat_map(to_vec).collect::<Vec<_>>(), elems);
+        assert_eq!(parts[1].strides(), v.strides());
+
+        let columns = v.split(Axis(1), &[1]).unwrap();
+        assert_eq!(to_vec(&columns[0]), (0..7).rev().map(|r| r as f64 * 3.0).collect::<Vec<_>>());
+
+        for (chunks, expected) in [(3, vec![3, 3, 1]), (7, vec![1; 7]), (10, vec![1; 7]), (1, vec![7])] {
+            let parts = v.chunk(Axis(0), chunks).unwrap();
+            assert_eq!(rows(&parts), expected, "{chunks} chunks");
+            assert_eq!(parts.iter().flat_map(to_vec).collect::<Vec<_>>(), elems);
+        }
+        let empty = arange([0, 3]);
+        assert_eq!(rows(&empty.chunk(Axis(0), 2).unwrap()), [0]);
+    }
+
+    #[test]
+    fn splitting_errors() {
+        let t = arange([4, 3]);
+        assert!(matches!(
+            t.split_at(Axis(0), 5),
+            Err(OmniError::ShapeError(ShapeError::InvalidSplit(_, 4))),
+        ));
+        assert!(t.split_at(Axis(0), 4).is_ok());
+        assert!(matches!(
+            t.split(Axis(1), &[2, 1]),
+            Err(OmniError::ShapeError(ShapeError::InvalidSplit(_, 3))),
+        ));
+        assert!(t.split(Axis(1), &[4]).is_err());
+        assert!(matches!(t.chunk(Axis(0), 0), Err(OmniError::ShapeError(ShapeError::InvalidChunks(0)))));
+        assert!(matches!(
+            t.split(Axis(2), &[]),
+            Err(OmniError::ShapeError(ShapeError::AxisOutOfBounds(2, 2))),
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+use std::ptr::NonNull;
+
+use crate::{tensor::TensorBase, dimension::Dimensions, backend::Backend};
+use super::{
+    OwnedStorage,
+    ViewStorage,
+    traits::{
+        RawStorage,
+        RawStorageClone,
+        Storage,
+    },
+};
+
+/// Storage that either borrows its elements or owns a copy of them.
+pub enum CowStorage<'a, T, B> where B: Backend {
+    View(ViewStorage<&'a T, B>),
+    Owned(OwnedStorage<T, B>),
+}
+
+impl<'a, T, B> CowStorage<'a, T, B> where B: Backend {
+    pub fn is_view(&self) -> bool {
+        matches!(self, CowStorage::View(_))
+    }
+
+    pub fn is_owned(&self) -> bool {
+        matches!(self, CowStorage::Owned(_))
+    }
+}
+
+unsafe impl<'a, T, B> RawStorage for CowStorage<'a, T, B> where B: Backend {
+    type Elem = T;
+    type Backend = B;
+
+    fn _is_pointer_inbounds(&self, ptr: *const Self::Elem) -> bool {
+        match self {
+            CowStorage::View(storage) => storage._is_pointer_inbounds(ptr),
+            CowStorage::Owned(storage) => storage._is_pointer_inbounds(ptr),
+        }
+    }
+
+    fn backend(&self) -> Self::Backend {
+        match self {
+            CowStorage::View(storage) => storage.backend(),
+            CowStorage::Owned(storage) => storage.backend(),
+        }
+    }
+}
+
+unsafe impl<'a, T, B> RawStorageClone for CowStorage<'a, T, B> where B: Backend {
+    unsafe fn clone_with_ptr(&self, ptr: NonNull<Self::Elem>) -> (Self, NonNull<Self::Elem>) {
+        match self {
+            CowStorage::View(storage) => {
+                let (storage, ptr) = storage.clone_with_ptr(ptr);
+                (CowStorage::View(storage), ptr)
+            },
+            CowStorage::Owned(storage) => {
+                let (storage, ptr) = storage.clone_with_ptr(ptr);
+                (CowStorage::Owned(storage), ptr)
+            },
+        }
+    }
+}
+
+unsafe impl<'a, T, B> Storage for CowStorage<'a, T, B> where B: Backend {
+    fn into_owned<D>(
+        self_: TensorBase<Self, D>
+    ) -> TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>
+    where
+        D: Dimensions
+    {
+        match Self::try_into_owned_nocopy(self_) {
+            Ok(owned) => owned,
+            Err(view) => view.to_owned(),
+        }
+    }
+
+    fn try_into_owned_nocopy<D>(
+        self_: TensorBase<Self, D>
+    ) -> Result<TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>, TensorBase<Self, D>> {
+        match self_.storage {
+            CowStorage::Owned(storage) => Ok(TensorBase {
+                storage,
+                ptr: self_.ptr,
+                dims: self_.dims,
+                strides: self_.strides,
+            }),
+            storage => Err(TensorBase {
+                storage,
+                ptr: self_.ptr,
+                dims: self_.dims,
+                strides: self_.strides,
+            }),
+        }
+    }
+}
@@ -1,6 +1,8 @@
+pub mod cow_storage;
 pub mod owned_storage;
 pub mod traits;
 pub mod view_storage;
 
+pub use cow_storage::CowStorage;
 pub use owned_storage::{OwnedStorage, OwnedArcStorage};
 pub use view_storage::ViewStorage;
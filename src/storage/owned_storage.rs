@@ -1,7 +1,5 @@
 use std::{ptr::NonNull, sync::Arc};
 
-use rawpointer::PointerExt;
-
 use crate::{
     backend::Backend,
     tensor::TensorBase,
@@ -43,14 +41,24 @@ impl<T, B> OwnedStorage<T, B> where B: Backend {
         self.ptr
     }
 
-    pub(crate) fn release_memory(&mut self) {
-        if self.capacity == 0 {
-            return;
+    /// Allocates room for `capacity` elements, handing out a dangling pointer
+    /// for zero-sized allocations which the allocator must never see.
+    fn allocate(capacity: usize, backend: B) -> NonNull<T> {
+        let layout = std::alloc::Layout::array::<T>(capacity).unwrap();
+        if layout.size() == 0 {
+            return NonNull::dangling();
         }
-        unsafe {
-            let layout = std::alloc::Layout::array::<T>(self.capacity).unwrap();
-            self.backend.dealloc(self.ptr.as_ptr() as *mut u8, layout);
+        unsafe { NonNull::new_unchecked(backend.alloc(layout) as *mut T) }
+    }
+
+    pub(crate) fn release_memory(&mut self) {
+        let layout = std::alloc::Layout::array::<T>(self.capacity).unwrap();
+        if layout.size() != 0 {
+            unsafe {
+                self.backend.dealloc(self.ptr.as_ptr() as *mut u8, layout);
+            }
         }
+        self.ptr = NonNull::dangling();
         self.size = 0;
         self.capacity = 0;
     }
@@ -58,12 +66,10 @@ impl<T, B> OwnedStorage<T, B> where B: Backend {
 
 impl<T, B> Clone for OwnedStorage<T, B> where B: Backend {
     fn clone(&self) -> Self {
-        let ptr = unsafe {
-            let layout = std::alloc::Layout::array::<T>(self.size).unwrap();
-            let ptr = self.backend.alloc(layout) as *mut T;
-            self.backend.copy(self.as_ptr() as _, ptr as _, layout.size());
-            NonNull::new_unchecked(ptr)
-        };
+        let ptr = Self::allocate(self.size, self.backend);
+        unsafe {
+            self.backend.copy(self.as_ptr(), ptr.as_ptr(), self.size);
+        }
         Self {
             ptr,
             size: self.size,
@@ -73,21 +79,17 @@ impl<T, B> Clone for OwnedStorage<T, B> where B: Backend {
     }
 
     fn clone_from(&mut self, other: &Self) {
-        if self.capacity >= other.capacity {
+        if self.capacity >= other.size {
             unsafe {
-                self.backend.copy(
-                    other.as_ptr() as _, self.as_ptr() as _, self.size
-                );
+                self.backend.copy(other.as_ptr(), self.ptr.as_ptr(), other.size);
             }
         } else {
             self.release_memory();
 
-            let ptr = unsafe {
-                let layout = std::alloc::Layout::array::<T>(other.size).unwrap();
-                let ptr = self.backend.alloc(layout) as *mut T;
-                self.backend.copy(other.as_ptr() as _, ptr as _, layout.size());
-                NonNull::new_unchecked(ptr)
-            };
+            let ptr = Self::allocate(other.size, other.backend);
+            unsafe {
+                other.backend.copy(other.as_ptr(), ptr.as_ptr(), other.size);
+            }
             self.ptr = ptr;
             self.capacity = other.size;
         }
@@ -185,10 +187,7 @@ where
 {
     fn empty(size: usize, backend: Self::Backend) -> (Self, NonNull<T>) {
         let capacity = size;
-        let ptr = unsafe {
-            let layout = std::alloc::Layout::array::<T>(size).unwrap();
-            NonNull::new_unchecked(backend.alloc(layout) as *mut T)
-        };
+        let ptr = Self::allocate(capacity, backend);
         (Self {
             ptr,
             size,
@@ -209,9 +208,13 @@ where
         }
     }
 
-    fn fill(&mut self, value: Self::Elem) {
+    fn fill(&mut self, value: Self::Elem)
+    where
+        Self::Elem: Clone,
+    {
+        let backend = self.backend;
         unsafe {
-            self.backend.fill(self.as_mut_ptr(), value, self.size);
+            backend.fill(self.as_mut_ptr(), value, self.size);
         }
     }
 
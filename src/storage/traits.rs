@@ -3,6 +3,14 @@ use std::ptr::NonNull;
 use crate::{dimension::Dimensions, tensor::TensorBase, backend::Backend};
 use super::{OwnedStorage, OwnedArcStorage};
 
+/// Memory holding the elements of a tensor.
+///
+/// # Safety
+///
+/// Tensors trust `_is_pointer_inbounds` and `backend` when they hand their
+/// pointer to the backend: an implementation must answer truthfully for the
+/// memory it holds, and must keep that memory alive and in place for as long
+/// as the storage exists.
 pub unsafe trait RawStorage: Sized {
     type Elem;
     type Backend: Backend;
@@ -12,10 +20,26 @@ pub unsafe trait RawStorage: Sized {
     fn backend(&self) -> Self::Backend;
 }
 
+/// Storage that can be cloned, such as a shared or borrowed one.
+///
+/// # Safety
+///
+/// The clone must give access to the same elements, so that a pointer into
+/// the original remains valid for the clone.
 pub unsafe trait RawStorageClone: RawStorage {
-    /// Safety: `ptr` must point inside the current storage.
+    /// Clones the storage along with a pointer into it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point inside the current storage.
     unsafe fn clone_with_ptr(&self, ptr: NonNull<Self::Elem>) -> (Self, NonNull<Self::Elem>);
 
+    /// Replaces the storage with a clone of `other`, returning the clone of
+    /// `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point inside `other`.
     unsafe fn clone_from_with_ptr(
         &mut self, other: &Self, ptr: NonNull<Self::Elem>
     ) -> NonNull<Self::Elem> {
@@ -29,7 +53,13 @@ pub unsafe trait RawStorageClone: RawStorage {
     // )-> OwnedStorage<Self::Elem, Self::Backend>;
 }
 
-pub unsafe trait RawStorageMut: RawStorageClone {
+/// Storage whose elements can be written.
+///
+/// # Safety
+///
+/// Once `try_ensure_unique` returns, no other storage may read or write the
+/// elements the tensor refers to.
+pub unsafe trait RawStorageMut: RawStorage {
     fn try_ensure_unique<D>(self_: &mut TensorBase<Self, D>)
     where
         D: Dimensions;
@@ -37,13 +67,20 @@ pub unsafe trait RawStorageMut: RawStorageClone {
     fn try_is_unique(&mut self) -> Option<bool>;
 }
 
-pub unsafe trait Storage: RawStorageClone {
+/// Storage whose elements are initialized.
+///
+/// # Safety
+///
+/// Every element the tensor can reach must be initialized and valid to read
+/// for as long as the storage exists.
+pub unsafe trait Storage: RawStorage {
     fn into_owned<D>(
         self_: TensorBase<Self, D>
     ) -> TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>
     where
         D: Dimensions;
 
+    #[allow(clippy::type_complexity)]
     fn try_into_owned_nocopy<D>(
         self_: TensorBase<Self, D>
     ) -> Result<TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>, TensorBase<Self, D>>;
@@ -59,6 +96,11 @@ pub unsafe trait Storage: RawStorageClone {
     }
 }
 
+/// Storage whose initialized elements can be written.
+///
+/// # Safety
+///
+/// As for [`Storage`] and [`RawStorageMut`].
 pub unsafe trait StorageMut: Storage + RawStorageMut {
     // ensure_unique
     fn ensure_unique<D>(self_: &mut TensorBase<Self, D>)
@@ -73,14 +115,35 @@ pub unsafe trait StorageMut: Storage + RawStorageMut {
     }
 }
 
+/// Storage that owns its allocation.
+///
+/// # Safety
+///
+/// `empty` must return a pointer to an allocation of `size` elements that
+/// the storage owns and frees on drop.
 pub unsafe trait StorageOwned: Storage {
     fn empty(size: usize, backend: Self::Backend) -> (Self, NonNull<Self::Elem>);
 
+    /// Takes ownership of `size` elements at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by `backend` for exactly `size`
+    /// elements of `Self::Elem`, or be dangling if that takes no bytes, and
+    /// all the elements must be initialized. Nothing else may free or use the
+    /// allocation afterwards.
     unsafe fn from_raw_ptr(ptr: NonNull<Self::Elem>, size: usize, backend: Self::Backend) -> Self;
 
-    fn fill(&mut self, value: Self::Elem);
+    fn fill(&mut self, value: Self::Elem)
+    where
+        Self::Elem: Clone;
 
     fn into_shared(self) -> OwnedArcStorage<Self::Elem, Self::Backend>;
 }
 
+/// Storage whose elements are shared between clones.
+///
+/// # Safety
+///
+/// As for [`Storage`] and [`RawStorageClone`].
 pub unsafe trait StorageShared: Storage + RawStorageClone + Clone {}
@@ -15,18 +15,34 @@ use super::{
     },
 };
 
-#[derive(Copy, Clone)]
 pub struct RawViewStorage<T, B> {
     ptr: PhantomData<T>,
     backend: B,
 }
 
-#[derive(Copy, Clone)]
 pub struct ViewStorage<T, B> {
     ptr: PhantomData<T>,
     backend: B,
 }
 
+// Implemented by hand: the derives would require `T: Copy`, which `&mut T`
+// never is, although only the `PhantomData` marker is copied.
+impl<T, B: Copy> Clone for RawViewStorage<T, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, B: Copy> Copy for RawViewStorage<T, B> {}
+
+impl<T, B: Copy> Clone for ViewStorage<T, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, B: Copy> Copy for ViewStorage<T, B> {}
+
 // impl<T, A> RawViewStorage<T, A> {
 //     pub(crate) fn new() -> Self {
 //         Self {
@@ -92,7 +108,7 @@ unsafe impl<T, B> RawStorageMut for RawViewStorage<*mut T, B> where B: Backend {
     fn try_is_unique(&mut self) -> Option<bool> { None }
 }
 
-unsafe impl<'a, T, B> RawStorage for ViewStorage<&'a T, B> where B: Backend {
+unsafe impl<T, B> RawStorage for ViewStorage<&T, B> where B: Backend {
     type Elem = T;
     type Backend = B;
 
@@ -104,13 +120,13 @@ unsafe impl<'a, T, B> RawStorage for ViewStorage<&'a T, B> where B: Backend {
     }
 }
 
-unsafe impl<'a, T, B> RawStorageClone for ViewStorage<&'a T, B> where B: Backend {
+unsafe impl<T, B> RawStorageClone for ViewStorage<&T, B> where B: Backend {
     unsafe fn clone_with_ptr(&self, ptr: NonNull<Self::Elem>) -> (Self, NonNull<Self::Elem>) {
         (*self, ptr)
     }
 }
 
-unsafe impl<'a, T, B> Storage for ViewStorage<&'a T, B> where B: Backend {
+unsafe impl<T, B> Storage for ViewStorage<&T, B> where B: Backend {
     fn into_owned<D>(
         self_: TensorBase<Self, D>
     ) -> TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>
@@ -127,7 +143,7 @@ unsafe impl<'a, T, B> Storage for ViewStorage<&'a T, B> where B: Backend {
     }
 }
 
-unsafe impl<'a, T, B> RawStorage for ViewStorage<&'a mut T, B> where B: Backend {
+unsafe impl<T, B> RawStorage for ViewStorage<&mut T, B> where B: Backend {
     type Elem = T;
     type Backend = B;
 
@@ -139,13 +155,7 @@ unsafe impl<'a, T, B> RawStorage for ViewStorage<&'a mut T, B> where B: Backend
     }
 }
 
-unsafe impl<'a, T, B> RawStorageClone for ViewStorage<&'a mut T, B> where B: Backend {
-    unsafe fn clone_with_ptr(&self, ptr: NonNull<Self::Elem>) -> (Self, NonNull<Self::Elem>) {
-        (*self, ptr)
-    }
-}
-
-unsafe impl<'a, T, B> RawStorageMut for ViewStorage<&'a mut T, B> where B: Backend {
+unsafe impl<T, B> RawStorageMut for ViewStorage<&mut T, B> where B: Backend {
     #[inline]
     fn try_ensure_unique<D>(_: &mut TensorBase<Self, D>)
     where
@@ -156,7 +166,7 @@ unsafe impl<'a, T, B> RawStorageMut for ViewStorage<&'a mut T, B> where B: Backe
     fn try_is_unique(&mut self) -> Option<bool> { Some(true) }
 }
 
-unsafe impl<'a, T, B> Storage for ViewStorage<&'a mut T, B> where B: Backend {
+unsafe impl<T, B> Storage for ViewStorage<&mut T, B> where B: Backend {
     fn into_owned<D>(
         self_: TensorBase<Self, D>
     ) -> TensorBase<OwnedStorage<Self::Elem, Self::Backend>, D>
@@ -173,4 +183,4 @@ unsafe impl<'a, T, B> Storage for ViewStorage<&'a mut T, B> where B: Backend {
     }
 }
 
-unsafe impl<'a, T, B> StorageMut for ViewStorage<&'a mut T, B> where B: Backend {}
+unsafe impl<T, B> StorageMut for ViewStorage<&mut T, B> where B: Backend {}
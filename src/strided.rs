@@ -0,0 +1,416 @@
+//! Raw strided traversal shared by the CPU kernels and the host-side iterators.
+//!
+//! All strides are expressed in elements of the respective operand and may be
+//! negative or zero (broadcast axes).
+
+use crate::index::Ix;
+
+/// Collapses the layout shared by all operands into as few axes as possible.
+///
+/// Axes of length 1 are dropped and adjacent axes are merged whenever every
+/// operand steps through them as if they were a single axis.
+fn simplify<const N: usize>(
+    dims: &[Ix],
+    strides: [&[isize]; N],
+) -> (Vec<Ix>, [Vec<isize>; N]) {
+    let mut out_dims: Vec<Ix> = Vec::with_capacity(dims.len());
+    let mut out_strides: [Vec<isize>; N] = std::array::from_fn(|_| Vec::with_capacity(dims.len()));
+    for (axis, &dim) in dims.iter().enumerate() {
+        if dim == 1 {
+            continue;
+        }
+        if let Some(last) = out_dims.last_mut() {
+            let mergeable = (0..N).all(|k| {
+                *out_strides[k].last().unwrap() == strides[k][axis] * dim as isize
+            });
+            if mergeable {
+                *last *= dim;
+                for k in 0..N {
+                    *out_strides[k].last_mut().unwrap() = strides[k][axis];
+                }
+                continue;
+            }
+        }
+        out_dims.push(dim);
+        for k in 0..N {
+            out_strides[k].push(strides[k][axis]);
+        }
+    }
+    (out_dims, out_strides)
+}
+
+/// Visits every logical index of `dims` in row-major order, handing `f` the
+/// pointers of all `N` operands at that index.
+///
+/// # Safety
+///
+/// Every operand must be valid for all offsets reachable through `dims` and
+/// its strides, and `elem_sizes[k]` must be the element size of operand `k`.
+pub(crate) unsafe fn walk<const N: usize, F>(
+    dims: &[Ix],
+    ptrs: [*mut u8; N],
+    strides: [&[isize]; N],
+    elem_sizes: [usize; N],
+    mut f: F,
+) where
+    F: FnMut([*mut u8; N]),
+{
+    debug_assert!(strides.iter().all(|s| s.len() == dims.len()));
+    if dims.contains(&0) {
+        return;
+    }
+    let (dims, elem_strides) = simplify(dims, strides);
+    if dims.is_empty() {
+        f(ptrs);
+        return;
+    }
+    let byte_strides: [Vec<isize>; N] = std::array::from_fn(|k| {
+        elem_strides[k].iter().map(|&s| s * elem_sizes[k] as isize).collect()
+    });
+
+    let last = dims.len() - 1;
+    let inner_len = dims[last];
+    let inner_strides: [isize; N] = std::array::from_fn(|k| byte_strides[k][last]);
+    let mut index = vec![0; last];
+    let mut base = ptrs;
+    loop {
+        let mut cur = base;
+        for _ in 0..inner_len {
+            f(cur);
+            for k in 0..N {
+                cur[k] = cur[k].wrapping_offset(inner_strides[k]);
+            }
+        }
+
+        // Advance the outer index like an odometer.
+        let mut axis = last;
+        loop {
+            if axis == 0 {
+                return;
+            }
+            axis -= 1;
+            index[axis] += 1;
+            if index[axis] < dims[axis] {
+                for k in 0..N {
+                    base[k] = base[k].wrapping_offset(byte_strides[k][axis]);
+                }
+                break;
+            }
+            let rewind = (dims[axis] - 1) as isize;
+            for k in 0..N {
+                base[k] = base[k].wrapping_offset(-byte_strides[k][axis] * rewind);
+            }
+            index[axis] = 0;
+        }
+    }
+}
+
+/// Single-operand form of [`walk`].
+pub(crate) unsafe fn zip1<A, F>(dims: &[Ix], a: *mut A, a_strides: &[isize], mut f: F)
+where
+    F: FnMut(*mut A),
+{
+    walk(
+        dims,
+        [a as *mut u8],
+        [a_strides],
+        [std::mem::size_of::<A>()],
+        |[a]| f(a as *mut A),
+    )
+}
+
+/// Two-operand form of [`walk`].
+pub(crate) unsafe fn zip2<A, B, F>(
+    dims: &[Ix],
+    a: *mut A,
+    a_strides: &[isize],
+    b: *mut B,
+    b_strides: &[isize],
+    mut f: F,
+) where
+    F: FnMut(*mut A, *mut B),
+{
+    walk(
+        dims,
+        [a as *mut u8, b as *mut u8],
+        [a_strides, b_strides],
+        [std::mem::size_of::<A>(), std::mem::size_of::<B>()],
+        |[a, b]| f(a as *mut A, b as *mut B),
+    )
+}
+
+/// Three-operand form of [`walk`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn zip3<A, B, C, F>(
+    dims: &[Ix],
+    a: *mut A,
+    a_strides: &[isize],
+    b: *mut B,
+    b_strides: &[isize],
+    c: *mut C,
+    c_strides: &[isize],
+    mut f: F,
+) where
+    F: FnMut(*mut A, *mut B, *mut C),
+{
+    walk(
+        dims,
+        [a as *mut u8, b as *mut u8, c as *mut u8],
+        [a_strides, b_strides, c_strides],
+        [
+            std::mem::size_of::<A>(),
+            std::mem::size_of::<B>(),
+            std::mem::size_of::<C>(),
+        ],
+        |[a, b, c]| f(a as *mut A, b as *mut B, c as *mut C),
+    )
+}
+
+/// Like [`walk`], but hands `f` whole lanes along the innermost axis left
+/// after merging: the pointers at the start of each lane, its length, and
+/// the element strides of all operands along it.
+///
+/// # Safety
+///
+/// Same as [`walk`].
+pub(crate) unsafe fn walk_lanes<const N: usize, F>(
+    dims: &[Ix],
+    ptrs: [*mut u8; N],
+    strides: [&[isize]; N],
+    elem_sizes: [usize; N],
+    mut f: F,
+) where
+    F: FnMut([*mut u8; N], usize, [isize; N]),
+{
+    debug_assert!(strides.iter().all(|s| s.len() == dims.len()));
+    if dims.contains(&0) {
+        return;
+    }
+    let (dims, elem_strides) = simplify(dims, strides);
+    let Some((&len, outer_dims)) = dims.split_last() else {
+        f(ptrs, 1, [0; N]);
+        return;
+    };
+    let last = outer_dims.len();
+    let lane_strides: [isize; N] = std::array::from_fn(|k| elem_strides[k][last]);
+    walk(
+        outer_dims,
+        ptrs,
+        std::array::from_fn(|k| &elem_strides[k][..last]),
+        elem_sizes,
+        |ptrs| f(ptrs, len, lane_strides),
+    )
+}
+
+/// Single-operand form of [`walk_lanes`].
+pub(crate) unsafe fn lanes1<A, F>(dims: &[Ix], a: *mut A, a_strides: &[isize], mut f: F)
+where
+    F: FnMut(*mut A, usize, isize),
+{
+    walk_lanes(
+        dims,
+        [a as *mut u8],
+        [a_strides],
+        [std::mem::size_of::<A>()],
+        |[a], len, [a_stride]| f(a as *mut A, len, a_stride),
+    )
+}
+
+/// Two-operand form of [`walk_lanes`].
+pub(crate) unsafe fn lanes2<A, B, F>(
+    dims: &[Ix],
+    a: *mut A,
+    a_strides: &[isize],
+    b: *mut B,
+    b_strides: &[isize],
+    mut f: F,
+) where
+    F: FnMut(*mut A, *mut B, usize, isize, isize),
+{
+    walk_lanes(
+        dims,
+        [a as *mut u8, b as *mut u8],
+        [a_strides, b_strides],
+        [std::mem::size_of::<A>(), std::mem::size_of::<B>()],
+        |[a, b], len, [a_stride, b_stride]| f(a as *mut A, b as *mut B, len, a_stride, b_stride),
+    )
+}
+
+/// Raw operand pointers that may be handed to other threads.
+#[derive(Clone, Copy)]
+struct SendPtrs<const N: usize>([*mut u8; N]);
+
+unsafe impl<const N: usize> Send for SendPtrs<N> {}
+unsafe impl<const N: usize> Sync for SendPtrs<N> {}
+
+impl<const N: usize> SendPtrs<N> {
+    /// Taking `self` makes closures capture the whole wrapper rather than
+    /// the bare array inside it.
+    fn get(self) -> [*mut u8; N] {
+        self.0
+    }
+}
+
+/// Parallel form of [`walk`]: the outermost axis left after merging is split
+/// across the rayon thread pool, and each thread walks its part in row-major
+/// order. `f` sees every index exactly once, in no particular order.
+///
+/// # Safety
+///
+/// Same as [`walk`]; in addition, calling `f` concurrently for different
+/// indices must be sound.
+pub(crate) unsafe fn par_walk<const N: usize, F>(
+    dims: &[Ix],
+    ptrs: [*mut u8; N],
+    strides: [&[isize]; N],
+    elem_sizes: [usize; N],
+    f: F,
+) where
+    F: Fn([*mut u8; N]) + Sync,
+{
+    use rayon::prelude::*;
+
+    /// Elements per task below which splitting is not worth it.
+    const MIN_TASK_SIZE: usize = 1 << 12;
+
+    debug_assert!(strides.iter().all(|s| s.len() == dims.len()));
+    if dims.contains(&0) {
+        return;
+    }
+    let (dims, elem_strides) = simplify(dims, strides);
+    let Some((&outer_len, inner_dims)) = dims.split_first() else {
+        f(ptrs);
+        return;
+    };
+    let inner_size: usize = inner_dims.iter().product();
+    let outer_strides: [isize; N] =
+        std::array::from_fn(|k| elem_strides[k][0] * elem_sizes[k] as isize);
+    let inner_strides: [&[isize]; N] = std::array::from_fn(|k| &elem_strides[k][1..]);
+    let base = SendPtrs(ptrs);
+    (0..outer_len)
+        .into_par_iter()
+        .with_min_len((MIN_TASK_SIZE / inner_size.max(1)).max(1))
+        .for_each(|i| {
+            let base = base.get();
+            let ptrs: [*mut u8; N] =
+                std::array::from_fn(|k| base[k].wrapping_offset(i as isize * outer_strides[k]));
+            walk(inner_dims, ptrs, inner_strides, elem_sizes, &f);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Element offsets of every index of `dims` in row-major order, one loop
+    /// per index.
+    fn reference(dims: &[Ix], strides: &[isize]) -> Vec<isize> {
+        let size: Ix = dims.iter().product();
+        (0..size)
+            .map(|mut k| {
+                let mut offset = 0;
+                for (&dim, &stride) in dims.iter().zip(strides).rev() {
+                    offset += (k % dim) as isize * stride;
+                    k /= dim;
+                }
+                offset
+            })
+            .collect()
+    }
+
+    /// Layouts of shape `[3, 1, 4, 2]`: standard, reversed, permuted,
+    /// with a gap, and broadcast along two axes.
+    const LAYOUTS: [[isize; 4]; 5] = [[8, 8, 2, 1], [-8, 8, -2, -1], [1, 5, 6, 3], [16, 99, 4, 1], [0, 0, 1, 0]];
+    const DIMS: [Ix; 4] = [3, 1, 4, 2];
+
+    /// A pointer from which every layout above stays in bounds of a buffer
+    /// of 80 elements.
+    fn base<T>(buffer: &mut [T; 80]) -> *mut T {
+        buffer.as_mut_ptr().wrapping_add(32)
+    }
+
+    #[test]
+    fn simplify_merges_contiguous_axes() {
+        let (dims, [a]) = simplify(&DIMS, [&LAYOUTS[0]]);
+        assert_eq!((dims, a), (vec![24], vec![1]));
+        // The gap only splits off the innermost axis.
+        let (dims, [a, b]) = simplify(&DIMS, [&LAYOUTS[0], &LAYOUTS[3]]);
+        assert_eq!((dims, a, b), (vec![12, 2], vec![2, 1], vec![4, 1]));
+        let (dims, [a]) = simplify(&DIMS, [&LAYOUTS[1]]);
+        assert_eq!((dims, a), (vec![24], vec![-1]));
+        let (dims, [a]) = simplify(&DIMS, [&LAYOUTS[4]]);
+        assert_eq!((dims, a), (vec![3, 4, 2], vec![0, 1, 0]));
+        let (dims, [a]) = simplify(&[1, 1], [&[5, 7]]);
+        assert!(dims.is_empty() && a.is_empty());
+    }
+
+    #[test]
+    fn walks_visit_row_major_order() {
+        // Operands of different element sizes.
+        let (mut words, mut bytes) = ([0u32; 80], [0u8; 80]);
+        let (base, byte_base) = (base(&mut words), base(&mut bytes));
+        for a in &LAYOUTS {
+            for b in &LAYOUTS {
+                let mut seen = (Vec::new(), Vec::new());
+                unsafe {
+                    zip2(&DIMS, base, a, byte_base, b, |x, y| {
+                        seen.0.push(x.offset_from(base));
+                        seen.1.push(y.offset_from(byte_base));
+                    });
+                }
+                assert_eq!(seen.0, reference(&DIMS, a), "{a:?} {b:?}");
+                assert_eq!(seen.1, reference(&DIMS, b), "{a:?} {b:?}");
+
+                let mut lanes = (Vec::new(), Vec::new());
+                unsafe {
+                    lanes2(&DIMS, base, a, base, b, |x, y, len, incx, incy| {
+                        for i in 0..len as isize {
+                            lanes.0.push(x.offset_from(base) + i * incx);
+                            lanes.1.push(y.offset_from(base) + i * incy);
+                        }
+                    });
+                }
+                assert_eq!(lanes.0, reference(&DIMS, a), "{a:?} {b:?}");
+                assert_eq!(lanes.1, reference(&DIMS, b), "{a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_and_zero_dimensional_walks() {
+        let mut buffer = [0u32; 80];
+        let base = base(&mut buffer);
+        let mut calls = 0;
+        unsafe {
+            zip1(&[2, 0, 3], base, &[0, 0, 0], |_| calls += 1);
+            lanes1(&[0], base, &[1], |_, _, _| calls += 1);
+        }
+        assert_eq!(calls, 0);
+        unsafe {
+            zip1(&[], base, &[], |x| assert_eq!(x, base));
+            lanes1(&[1, 1], base, &[3, 3], |x, len, inc| assert_eq!((x, len, inc), (base, 1, 0)));
+        }
+    }
+
+    #[test]
+    fn par_walk_visits_every_index_once() {
+        // Enough elements to be split into several tasks.
+        let dims = [64, 3, 50];
+        let strides = [1, -64 * 50, 64];
+        let mut buffer = vec![0u8; 64 * 3 * 50];
+        let base = buffer.as_mut_ptr().wrapping_add(64 * 50 * 2);
+        let seen = Mutex::new(Vec::new());
+        // Raw pointers are not `Sync`, so compare addresses.
+        let origin = base as usize as isize;
+        unsafe {
+            par_walk(&dims, [base], [&strides], [1], |[p]| seen.lock().unwrap().push(p as usize as isize - origin));
+        }
+        let mut seen = seen.into_inner().unwrap();
+        let mut expected = reference(&dims, &strides);
+        seen.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(seen, expected);
+    }
+}
@@ -1,17 +1,31 @@
 use std::ptr::NonNull;
 
 use num_traits::{One, Zero};
-use rawpointer::PointerExt;
 
 use crate::{
     backend::Backend,
-    dimension::{Dimensions, offset_from_low_addr_ptr_to_logical_ptr},
+    dimension::{
+        broadcast::{co_broadcast, upcast},
+        broadcast_shapes,
+        dims_from_slice,
+        Dimensions,
+        DimsMax,
+        DimsMaxOf,
+        DynDims,
+        IntoDimension,
+        into_dimensionality,
+        offset_from_low_addr_ptr_to_logical_ptr,
+        strides_as_isize,
+    },
+    error::{OmniResult, ShapeError},
+    index::Ix,
     shape_builder::ShapeBuilder,
     storage::{
-        traits::{RawStorage, RawStorageClone, StorageOwned},
+        traits::{RawStorage, RawStorageClone, RawStorageMut, StorageOwned},
         OwnedStorage,
         OwnedArcStorage,
     },
+    tensor_view::{TensorView, TensorViewMut},
 };
 
 pub struct TensorBase<S, D>
@@ -32,6 +46,95 @@ where
     S: RawStorage<Elem = T, Backend = B>,
     D: Dimensions,
 {
+    pub fn shape(&self) -> &D {
+        &self.dims
+    }
+
+    /// Strides in elements; negative strides walk towards lower addresses.
+    pub fn strides(&self) -> &[isize] {
+        strides_as_isize(self.strides.as_slice())
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.dims.ndim()
+    }
+
+    /// Total number of elements.
+    pub fn size(&self) -> usize {
+        self.dims.size()
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    pub fn view(&self) -> TensorView<'_, T, B, D> {
+        TensorView::new(
+            self.ptr,
+            self.dims.clone(),
+            self.strides.clone(),
+            self.storage.backend(),
+        )
+    }
+
+    pub fn view_mut(&mut self) -> TensorViewMut<'_, T, B, D>
+    where
+        S: RawStorageMut,
+    {
+        S::try_ensure_unique(self);
+        TensorViewMut::new(
+            self.ptr,
+            self.dims.clone(),
+            self.strides.clone(),
+            self.storage.backend(),
+        )
+    }
+
+    /// Views `self` with shape `dims`, using zero strides on the axes that
+    /// are broadcast.
+    pub(crate) fn broadcast_view<E>(&self, dims: &E) -> OmniResult<TensorView<'_, T, B, E>>
+    where
+        E: Dimensions,
+    {
+        let strides = upcast(dims, &self.dims, &self.strides).ok_or_else(|| {
+            ShapeError::IncompatibleBroadcast(
+                self.dims.as_slice().to_vec(),
+                dims.as_slice().to_vec(),
+            )
+        })?;
+        Ok(TensorView::new(self.ptr, dims.clone(), strides, self.storage.backend()))
+    }
+
+    /// Returns a read-only view of `self` broadcast to `shape`.
+    ///
+    /// Axes are aligned from the trailing end; axes of length 1 and missing
+    /// leading axes are expanded with a stride of zero, so no data is copied.
+    pub fn broadcast_to<Sh>(&self, shape: Sh) -> OmniResult<TensorView<'_, T, B, Sh::Dims>>
+    where
+        Sh: IntoDimension,
+    {
+        self.broadcast_view(&shape.into_dimension())
+    }
+
+    /// Broadcasts `self` and `other` against each other, returning views of
+    /// both with the common shape.
+    #[allow(clippy::type_complexity)]
+    pub fn broadcast_with<'a, 'b, U, S2, E>(
+        &'a self,
+        other: &'b TensorBase<S2, E>,
+    ) -> OmniResult<(
+        TensorView<'a, T, B, DimsMaxOf<D, E>>,
+        TensorView<'b, U, B, DimsMaxOf<D, E>>,
+    )>
+    where
+        S2: RawStorage<Elem = U, Backend = B>,
+        D: DimsMax<E>,
+        E: Dimensions,
+    {
+        let dims = co_broadcast(&self.dims, &other.dims)?;
+        Ok((self.broadcast_view(&dims)?, other.broadcast_view(&dims)?))
+    }
+
     pub fn is_contiguous(&self) -> bool {
         D::is_contiguous(&self.dims, &self.strides)
     }
@@ -49,21 +152,24 @@ where
         }
     }
 
+    /// Copies the visible elements into a new owned tensor in standard
+    /// (row-major) layout, whatever the strides of `self` are.
     pub fn to_owned(&self) -> TensorBase<OwnedStorage<T, B>, D> {
-        if let Some(slice) = self.as_slice_memory_order() {
-            let src_ptr = slice.as_ptr();
-            let size = slice.len();
-            let backend = self.storage.backend();
-            let (mut storage, ptr) = OwnedStorage::empty(size, backend);
-            unsafe { backend.copy(src_ptr, ptr.as_ptr(), size); }
-            TensorBase {
-                storage,
-                ptr,
-                dims: self.dims.clone(),
-                strides: self.strides.clone(),
+        let backend = self.storage.backend();
+        unsafe {
+            let out = TensorBase::uninit(self.dims.clone(), backend);
+            if self.strides.equal(&out.strides) {
+                backend.copy(self.ptr.as_ptr(), out.ptr.as_ptr(), out.size());
+            } else {
+                backend.copy_strided(
+                    self.dims.as_slice(),
+                    self.ptr.as_ptr(),
+                    strides_as_isize(self.strides.as_slice()),
+                    out.ptr.as_ptr(),
+                    strides_as_isize(out.strides.as_slice()),
+                );
             }
-        } else {
-            unimplemented!("to_owned for non-contiguous tensors")
+            out
         }
     }
 
@@ -80,6 +186,56 @@ where
             strides: self.strides,
         }
     }
+
+    /// Converts the tensor to dynamic dimensions.
+    pub fn into_dyn(self) -> TensorBase<S, DynDims> {
+        TensorBase {
+            storage: self.storage,
+            ptr: self.ptr,
+            dims: dims_from_slice(self.dims.as_slice()),
+            strides: dims_from_slice(self.strides.as_slice()),
+        }
+    }
+
+    /// Converts the tensor to the dimension type `D2`.
+    ///
+    /// **Errors** with `IncompatibleShape` if `D2` has a fixed number of axes
+    /// that differs from the tensor's.
+    pub fn into_dimensionality<D2>(self) -> OmniResult<TensorBase<S, D2>>
+    where
+        D2: Dimensions,
+    {
+        match (into_dimensionality(&self.dims), into_dimensionality(&self.strides)) {
+            (Some(dims), Some(strides)) => Ok(TensorBase {
+                storage: self.storage,
+                ptr: self.ptr,
+                dims,
+                strides,
+            }),
+            _ => Err(ShapeError::IncompatibleShape.into()),
+        }
+    }
+}
+
+impl<T, B, D> TensorBase<OwnedStorage<T, B>, D>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    /// Allocates a standard-layout tensor of shape `dims`.
+    ///
+    /// Safety: the elements are uninitialized and must be written before
+    /// they are read.
+    pub(crate) unsafe fn uninit(dims: D, backend: B) -> Self {
+        let strides = dims.default_strides();
+        let (storage, ptr) = OwnedStorage::empty(dims.size(), backend);
+        TensorBase {
+            storage,
+            ptr,
+            dims,
+            strides,
+        }
+    }
 }
 
 impl<T, B, S, D> TensorBase<S, D>
@@ -110,9 +266,9 @@ where
         Sh: ShapeBuilder<Dims = D>,
     {
         let shape = shape.into_shape();
-        let dims = shape.dims;
-        let strides = dims.default_strides();
         let size = shape.size();
+        let strides = shape.default_strides();
+        let dims = shape.dims;
         let (mut storage, ptr) = S::empty(size, S::Backend::default());
         storage.fill(elem);
         Self {
@@ -124,6 +280,26 @@ where
     }
 }
 
+/// Broadcasts all of `tensors` to their common shape, returning a view of each.
+pub fn broadcast_tensors<'a, T, B, D>(
+    tensors: &[TensorView<'a, T, B, D>],
+) -> OmniResult<Vec<TensorView<'a, T, B, DynDims>>>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    let shapes: Vec<&[Ix]> = tensors.iter().map(|t| t.dims.as_slice()).collect();
+    let dims = broadcast_shapes(&shapes)?;
+    tensors
+        .iter()
+        .map(|t| {
+            let view = t.broadcast_view(&dims)?;
+            // Re-borrow with the lifetime of the original views.
+            Ok(TensorView::new(view.ptr, view.dims, view.strides, t.storage.backend()))
+        })
+        .collect()
+}
+
 impl<S, D> Clone for TensorBase<S, D>
 where
     S: RawStorageClone,
@@ -152,3 +328,70 @@ where
         self.strides.clone_from(&other.strides);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dimension::{Dimensions, Dims3},
+        s,
+        slice::NewAxis,
+        test_util::*,
+    };
+
+    #[test]
+    fn to_owned_negative_strides() {
+        let t = arange([3, 5]);
+        let v = t.slice(s![..;-1, ..;-2]);
+        assert_eq!(v.strides(), &[-5, -2]);
+        let owned = v.to_owned();
+        assert_eq!(owned.shape().as_slice(), &[3, 3]);
+        assert_eq!(owned.strides(), &[3, 1]);
+        let expected = from_fn([3, 3], |i| ((2 - i[0]) * 5 + 4 - 2 * i[1]) as f64);
+        assert_eq!(to_vec(&owned), to_vec(&expected));
+
+        let v = t.slice(s![1..;-1, 1..4]);
+        let expected = from_fn([2, 3], |i| ((2 - i[0]) * 5 + 1 + i[1]) as f64);
+        assert_eq!(to_vec(&v.to_owned()), to_vec(&expected));
+    }
+
+    #[test]
+    fn to_owned_length_one_axes() {
+        let t = arange([4, 3]);
+        // A length-1 axis keeps the stride of the axis it was sliced from,
+        // which differs from the stride of a fresh tensor of that shape.
+        let v = t.slice(s![2..3, ..;-1]);
+        assert_eq!(v.strides(), &[3, -1]);
+        let owned = v.to_owned();
+        assert_eq!(owned.strides(), &[3, 1]);
+        assert_eq!(to_vec(&owned), [8.0, 7.0, 6.0]);
+
+        let v = t.slice(s![.., NewAxis, 1]);
+        assert_eq!(v.strides(), &[3, 0]);
+        assert_eq!(to_vec(&v.to_owned()), [1.0, 4.0, 7.0, 10.0]);
+
+        let owned: Tensor<f64, Dims3> = t.slice(s![1..2, NewAxis, 1..2]).to_owned();
+        assert_eq!(owned.shape().as_slice(), &[1, 1, 1]);
+        assert_eq!(to_vec(&owned), [4.0]);
+    }
+
+    #[test]
+    fn to_owned_permuted_and_broadcast() {
+        let t = arange([2, 3, 4]);
+        let owned = t.view().permuted_axes([2, 0, 1]).unwrap().to_owned();
+        let expected = from_fn([4, 2, 3], |i| (i[1] * 12 + i[2] * 4 + i[0]) as f64);
+        assert_eq!(to_vec(&owned), to_vec(&expected));
+
+        let row = arange([3]);
+        let owned = row.broadcast_to([2, 3]).unwrap().to_owned();
+        assert_eq!(owned.strides(), &[3, 1]);
+        assert_eq!(to_vec(&owned), [0.0, 1.0, 2.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn to_owned_empty_reversed() {
+        let t = arange([3, 0]);
+        let owned = t.slice(s![..;-1, ..;-1]).to_owned();
+        assert_eq!(owned.shape().as_slice(), &[3, 0]);
+        assert!(to_vec(&owned).is_empty());
+    }
+}
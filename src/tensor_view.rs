@@ -3,7 +3,7 @@ use std::ptr::NonNull;
 use crate::{
     backend::Backend,
     dimension::Dimensions,
-    storage::ViewStorage,
+    storage::{CowStorage, ViewStorage},
     tensor::TensorBase,
 };
 
@@ -11,6 +11,9 @@ pub type TensorView<'a, T, B, D> = TensorBase<ViewStorage<&'a T, B>, D>;
 
 pub type TensorViewMut<'a, T, B, D> = TensorBase<ViewStorage<&'a mut T, B>, D>;
 
+/// A tensor that either borrows its elements or owns a copy of them.
+pub type CowTensor<'a, T, B, D> = TensorBase<CowStorage<'a, T, B>, D>;
+
 impl<'a, T, B, D> TensorView<'a, T, B, D>
 where
     B: Backend,
@@ -41,3 +44,19 @@ where
         }
     }
 }
+
+impl<'a, T, B, D> CowTensor<'a, T, B, D>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    /// Whether the tensor borrows its elements.
+    pub fn is_view(&self) -> bool {
+        self.storage.is_view()
+    }
+
+    /// Whether the tensor owns its elements.
+    pub fn is_owned(&self) -> bool {
+        self.storage.is_owned()
+    }
+}
@@ -0,0 +1,73 @@
+//! Helpers shared by the unit tests.
+
+use crate::{
+    backend::CpuBackend,
+    dimension::{Dimensions, IntoDimension},
+    index::Ix,
+    storage::{traits::Storage, OwnedStorage},
+    tensor::TensorBase,
+};
+
+pub(crate) type Tensor<T, D> = TensorBase<OwnedStorage<T, CpuBackend>, D>;
+
+/// A standard-layout tensor holding `f` of each index.
+pub(crate) fn from_fn<T, Sh, F>(shape: Sh, mut f: F) -> Tensor<T, Sh::Dims>
+where
+    T: Copy,
+    Sh: IntoDimension,
+    F: FnMut(&[Ix]) -> T,
+{
+    let dims = shape.into_dimension();
+    let mut index = vec![0; dims.ndim()];
+    unsafe {
+        let out = Tensor::<T, Sh::Dims>::uninit(dims, CpuBackend);
+        for k in 0..out.size() {
+            out.ptr.as_ptr().add(k).write(f(&index));
+            for d in (0..index.len()).rev() {
+                index[d] += 1;
+                if index[d] < out.dims[d] {
+                    break;
+                }
+                index[d] = 0;
+            }
+        }
+        out
+    }
+}
+
+/// A tensor holding `0, 1, 2, ..` in row-major order.
+pub(crate) fn arange<Sh: IntoDimension>(shape: Sh) -> Tensor<f64, Sh::Dims> {
+    let mut k = -1.0;
+    from_fn(shape, |_| {
+        k += 1.0;
+        k
+    })
+}
+
+/// A tensor of irregular values in `[-5, 5)` in row-major order.
+pub(crate) fn noise<Sh: IntoDimension>(shape: Sh) -> Tensor<f64, Sh::Dims> {
+    let mut k = 0;
+    from_fn(shape, |_| {
+        k += 1;
+        ((k * 7919 + 13) % 101) as f64 / 10.1 - 5.0
+    })
+}
+
+/// Asserts that `actual` and `expected` agree up to rounding.
+#[track_caller]
+pub(crate) fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "lengths differ");
+    for (k, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() <= 1e-9 * (1.0 + e.abs()), "element {k}: {a} != {e}");
+    }
+}
+
+/// The elements of `tensor` in row-major order.
+pub(crate) fn to_vec<T, S, D>(tensor: &TensorBase<S, D>) -> Vec<T>
+where
+    T: Copy,
+    S: Storage<Elem = T>,
+    D: Dimensions,
+{
+    tensor.iter().copied().collect()
+}
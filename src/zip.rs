@@ -0,0 +1,456 @@
+//! Walking several tensors in lockstep.
+//!
+//! A [`Zip`] takes its shape from the first operand; every operand added with
+//! [`Zip::and`] is broadcast to that shape. Read-only operands yield `&T`,
+//! mutable ones yield `&mut T` and must match the shape exactly, since
+//! broadcasting would hand out aliasing references.
+//!
+//! `for_each` and `map_collect` visit the elements in row-major order, while
+//! `par_for_each` and `par_map_collect` spread the work over the rayon
+//! thread pool.
+
+use std::marker::PhantomData;
+
+use crate::{
+    backend::Backend,
+    dimension::{broadcast::upcast, strides_as_isize, Dimensions},
+    error::ShapeError,
+    storage::{
+        traits::{RawStorage, Storage, StorageMut},
+        OwnedStorage,
+    },
+    strided,
+    tensor::TensorBase,
+    tensor_view::{TensorView, TensorViewMut},
+};
+
+/// An element reference handed out by a [`Zip`].
+pub trait ZipItem: Sized {
+    type Elem;
+
+    /// Whether the operand is written through.
+    const IS_MUT: bool;
+
+    /// Turns a pointer to an element of an operand into a reference.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for the lifetime of `Self`, and unique if `Self`
+    /// is a mutable reference.
+    unsafe fn from_ptr(ptr: *mut Self::Elem) -> Self;
+}
+
+impl<T> ZipItem for &T {
+    type Elem = T;
+
+    const IS_MUT: bool = false;
+
+    unsafe fn from_ptr(ptr: *mut T) -> Self {
+        &*ptr
+    }
+}
+
+impl<T> ZipItem for &mut T {
+    type Elem = T;
+
+    const IS_MUT: bool = true;
+
+    unsafe fn from_ptr(ptr: *mut T) -> Self {
+        &mut *ptr
+    }
+}
+
+/// A tensor taking part in a [`Zip`], in its own shape.
+pub struct ZipPart<I: ZipItem, D, B> {
+    ptr: *mut I::Elem,
+    dims: D,
+    strides: D,
+    backend: B,
+    marker: PhantomData<I>,
+}
+
+/// Values that can take part in a [`Zip`]: tensors and views by reference
+/// yield `&T`, and by mutable reference or as mutable views yield `&mut T`.
+pub trait IntoZipPart {
+    type Item: ZipItem;
+    type Dims: Dimensions;
+    type Backend: Backend;
+
+    fn into_zip_part(self) -> ZipPart<Self::Item, Self::Dims, Self::Backend>;
+}
+
+impl<'a, T, B, S, D> IntoZipPart for &'a TensorBase<S, D>
+where
+    T: 'a,
+    B: Backend,
+    S: Storage<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Item = &'a T;
+    type Dims = D;
+    type Backend = B;
+
+    fn into_zip_part(self) -> ZipPart<&'a T, D, B> {
+        ZipPart {
+            ptr: self.ptr.as_ptr(),
+            dims: self.dims.clone(),
+            strides: self.strides.clone(),
+            backend: self.storage.backend(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, B, S, D> IntoZipPart for &'a mut TensorBase<S, D>
+where
+    T: 'a,
+    B: Backend,
+    S: StorageMut<Elem = T, Backend = B>,
+    D: Dimensions,
+{
+    type Item = &'a mut T;
+    type Dims = D;
+    type Backend = B;
+
+    fn into_zip_part(self) -> ZipPart<&'a mut T, D, B> {
+        S::ensure_unique(self);
+        ZipPart {
+            ptr: self.ptr.as_ptr(),
+            dims: self.dims.clone(),
+            strides: self.strides.clone(),
+            backend: self.storage.backend(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, B, D> IntoZipPart for TensorView<'a, T, B, D>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    type Item = &'a T;
+    type Dims = D;
+    type Backend = B;
+
+    fn into_zip_part(self) -> ZipPart<&'a T, D, B> {
+        ZipPart {
+            ptr: self.ptr.as_ptr(),
+            backend: self.storage.backend(),
+            dims: self.dims,
+            strides: self.strides,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, B, D> IntoZipPart for TensorViewMut<'a, T, B, D>
+where
+    B: Backend,
+    D: Dimensions,
+{
+    type Item = &'a mut T;
+    type Dims = D;
+    type Backend = B;
+
+    fn into_zip_part(self) -> ZipPart<&'a mut T, D, B> {
+        ZipPart {
+            ptr: self.ptr.as_ptr(),
+            backend: self.storage.backend(),
+            dims: self.dims,
+            strides: self.strides,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// An operand of a [`Zip`], with strides broadcast to the zip's shape.
+pub struct Operand<I: ZipItem, D> {
+    ptr: *mut I::Elem,
+    strides: D,
+    marker: PhantomData<I>,
+}
+
+/// Lockstep traversal of up to six tensors; see the [module docs](self).
+pub struct Zip<P, D, B> {
+    parts: P,
+    dims: D,
+    backend: B,
+}
+
+impl<I, D, B> Zip<(Operand<I, D>,), D, B>
+where
+    I: ZipItem,
+    D: Dimensions,
+    B: Backend,
+{
+    /// Starts a zip over `part`, whose shape becomes the shape of the zip.
+    pub fn from<P>(part: P) -> Self
+    where
+        P: IntoZipPart<Item = I, Dims = D, Backend = B>,
+    {
+        let part = part.into_zip_part();
+        Zip {
+            parts: (Operand {
+                ptr: part.ptr,
+                strides: part.strides,
+                marker: PhantomData,
+            },),
+            dims: part.dims,
+            backend: part.backend,
+        }
+    }
+}
+
+impl<P, D, B> Zip<P, D, B>
+where
+    D: Dimensions,
+    B: Backend,
+{
+    /// The shape all operands are walked in.
+    pub fn shape(&self) -> &D {
+        &self.dims
+    }
+
+    /// Broadcasts `part` to the shape of the zip.
+    ///
+    /// **Panics** if it cannot be broadcast, or if a mutable operand does not
+    /// have exactly the shape of the zip.
+    fn operand<Q>(&self, part: Q) -> Operand<Q::Item, D>
+    where
+        Q: IntoZipPart<Backend = B>,
+    {
+        let part = part.into_zip_part();
+        let incompatible = || {
+            let e = ShapeError::IncompatibleBroadcast(
+                part.dims.as_slice().to_vec(),
+                self.dims.as_slice().to_vec(),
+            );
+            panic!("{}", e)
+        };
+        if Q::Item::IS_MUT && part.dims.as_slice() != self.dims.as_slice() {
+            incompatible();
+        }
+        let strides = upcast(&self.dims, &part.dims, &part.strides).unwrap_or_else(incompatible);
+        Operand {
+            ptr: part.ptr,
+            strides,
+            marker: PhantomData,
+        }
+    }
+}
+
+macro_rules! impl_zip {
+    ($([$($I:ident $p:ident),+] $($J:ident)?;)+) => {
+        $(
+            impl<$($I,)+ D, B> Zip<($(Operand<$I, D>,)+), D, B>
+            where
+                $($I: ZipItem,)+
+                D: Dimensions,
+                B: Backend,
+            {
+                impl_zip!(@and [$($I $p),+] $($J)?);
+
+                /// Calls `f` with the elements of all operands at each index,
+                /// in row-major order.
+                pub fn for_each<F>(self, mut f: F)
+                where
+                    F: FnMut($($I),+),
+                {
+                    let ($($p,)+) = self.parts;
+                    unsafe {
+                        strided::walk(
+                            self.dims.as_slice(),
+                            [$($p.ptr as *mut u8),+],
+                            [$(strides_as_isize($p.strides.as_slice())),+],
+                            [$(std::mem::size_of::<$I::Elem>()),+],
+                            |[$($p),+]| f($($I::from_ptr($p as *mut $I::Elem)),+),
+                        );
+                    }
+                }
+
+                /// Like [`for_each`](Self::for_each), but runs on the rayon
+                /// thread pool, in no particular order.
+                pub fn par_for_each<F>(self, f: F)
+                where
+                    $($I: Send,)+
+                    F: Fn($($I),+) + Sync + Send,
+                {
+                    let ($($p,)+) = self.parts;
+                    unsafe {
+                        strided::par_walk(
+                            self.dims.as_slice(),
+                            [$($p.ptr as *mut u8),+],
+                            [$(strides_as_isize($p.strides.as_slice())),+],
+                            [$(std::mem::size_of::<$I::Elem>()),+],
+                            |[$($p),+]| f($($I::from_ptr($p as *mut $I::Elem)),+),
+                        );
+                    }
+                }
+
+                /// Collects `f` applied to the elements of all operands at
+                /// each index into a new tensor in standard layout. Storages
+                /// never drop their elements, hence `R: Copy`.
+                pub fn map_collect<R, F>(self, mut f: F) -> TensorBase<OwnedStorage<R, B>, D>
+                where
+                    R: Copy,
+                    F: FnMut($($I),+) -> R,
+                {
+                    let ($($p,)+) = self.parts;
+                    unsafe {
+                        let out = TensorBase::<OwnedStorage<R, B>, D>::uninit(self.dims, self.backend);
+                        strided::walk(
+                            out.dims.as_slice(),
+                            [out.ptr.as_ptr() as *mut u8, $($p.ptr as *mut u8),+],
+                            [strides_as_isize(out.strides.as_slice()), $(strides_as_isize($p.strides.as_slice())),+],
+                            [std::mem::size_of::<R>(), $(std::mem::size_of::<$I::Elem>()),+],
+                            |[out, $($p),+]| {
+                                (out as *mut R).write(f($($I::from_ptr($p as *mut $I::Elem)),+))
+                            },
+                        );
+                        out
+                    }
+                }
+
+                /// Like [`map_collect`](Self::map_collect), but runs on the
+                /// rayon thread pool.
+                pub fn par_map_collect<R, F>(self, f: F) -> TensorBase<OwnedStorage<R, B>, D>
+                where
+                    $($I: Send,)+
+                    R: Copy + Send,
+                    F: Fn($($I),+) -> R + Sync + Send,
+                {
+                    let ($($p,)+) = self.parts;
+                    unsafe {
+                        let out = TensorBase::<OwnedStorage<R, B>, D>::uninit(self.dims, self.backend);
+                        strided::par_walk(
+                            out.dims.as_slice(),
+                            [out.ptr.as_ptr() as *mut u8, $($p.ptr as *mut u8),+],
+                            [strides_as_isize(out.strides.as_slice()), $(strides_as_isize($p.strides.as_slice())),+],
+                            [std::mem::size_of::<R>(), $(std::mem::size_of::<$I::Elem>()),+],
+                            |[out, $($p),+]| {
+                                (out as *mut R).write(f($($I::from_ptr($p as *mut $I::Elem)),+))
+                            },
+                        );
+                        out
+                    }
+                }
+            }
+        )+
+    };
+    (@and [$($I:ident $p:ident),+] $J:ident) => {
+        /// Adds `part` to the zip, broadcast to its shape.
+        ///
+        /// **Panics** if `part` cannot be broadcast to the shape of the zip,
+        /// or is mutable and does not have exactly that shape.
+        pub fn and<Q>(self, part: Q) -> Zip<($(Operand<$I, D>,)+ Operand<Q::Item, D>,), D, B>
+        where
+            Q: IntoZipPart<Backend = B>,
+        {
+            let operand = self.operand(part);
+            let ($($p,)+) = self.parts;
+            Zip {
+                parts: ($($p,)+ operand,),
+                dims: self.dims,
+                backend: self.backend,
+            }
+        }
+    };
+    (@and [$($I:ident $p:ident),+]) => {};
+}
+
+impl_zip! {
+    [A a] B2;
+    [A a, B1 b] C;
+    [A a, B1 b, C c] E;
+    [A a, B1 b, C c, E e] F1;
+    [A a, B1 b, C c, E e, F1 f1] G;
+    [A a, B1 b, C c, E e, F1 f1, G g];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{s, test_util::*};
+
+    #[test]
+    fn for_each_matches_reference() {
+        let (a, b, c) = (noise([3, 4]), arange([4]), noise([4, 3]));
+        let mut out = from_fn([3, 4], |_| 0.0);
+        let mut seen = Vec::new();
+        Zip::from(&mut out)
+            .and(a.slice(s![..;-1, ..]))
+            .and(&b)
+            .and(c.t())
+            .for_each(|o, &a, &b, &c| {
+                seen.push(b);
+                *o = a * b + c;
+            });
+        let expected = from_fn([3, 4], |i| a[[2 - i[0], i[1]]] * b[[i[1]]] + c[[i[1], i[0]]]);
+        assert_close(&to_vec(&out), &to_vec(&expected));
+        assert_eq!(seen, to_vec(&b.broadcast_to([3, 4]).unwrap()));
+
+        let collected = Zip::from(a.slice(s![..;-1, ..])).and(&b).and(c.t()).map_collect(|&a, &b, &c| a * b + c);
+        assert!(collected.is_standard_layout());
+        assert_close(&to_vec(&collected), &to_vec(&expected));
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        // Large enough to be split across several tasks.
+        let a = noise([96, 40, 3]);
+        let v = a.view().permuted_axes([2, 0, 1]).unwrap();
+        let b = arange([40]);
+        let sequential = Zip::from(v.clone()).and(&b).map_collect(|&a, &b| a - b);
+        let parallel = Zip::from(v.clone()).and(&b).par_map_collect(|&a, &b| a - b);
+        assert_eq!(to_vec(&parallel), to_vec(&sequential));
+
+        let mut out = from_fn([40, 96, 3], |_| 0.0);
+        Zip::from(out.view_mut().permuted_axes([2, 1, 0]).unwrap())
+            .and(v)
+            .and(&b)
+            .par_for_each(|o, &a, &b| *o = a - b);
+        let out = out.view().permuted_axes([2, 1, 0]).unwrap();
+        assert_eq!(to_vec(&out), to_vec(&sequential));
+    }
+
+    #[test]
+    fn six_operands() {
+        let t: Vec<_> = (0..6).map(|k| arange([2, 3]).mapv(|x| x * 10f64.powi(k))).collect();
+        let sum = Zip::from(&t[0])
+            .and(&t[1])
+            .and(&t[2])
+            .and(&t[3])
+            .and(&t[4])
+            .and(t[5].slice(s![..;-1, ..;-1]))
+            .map_collect(|a, b, c, d, e, f| a + b + c + d + e + f);
+        let expected = from_fn([2, 3], |i| {
+            let x = (i[0] * 3 + i[1]) as f64;
+            x * 11111.0 + (5.0 - x) * 100000.0
+        });
+        assert_eq!(to_vec(&sum), to_vec(&expected));
+    }
+
+    #[test]
+    fn mutable_operands_are_unshared() {
+        let shared = arange([2, 2]).into_shared();
+        let mut t = shared.clone();
+        Zip::from(&mut t).and(&arange([2, 1])).for_each(|x, &y| *x += y);
+        assert_eq!(to_vec(&t), [0.0, 1.0, 3.0, 4.0]);
+        assert_eq!(to_vec(&shared), [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(Zip::from(&t).shape().as_slice(), &[2, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "broadcast")]
+    fn incompatible_operands_panic() {
+        Zip::from(&arange([2, 3])).and(&arange([2])).for_each(|_, _| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "broadcast")]
+    fn mutable_operands_are_not_broadcast() {
+        let mut row = arange([3]);
+        Zip::from(&arange([2, 3])).and(&mut row).for_each(|&x, y| *y += x);
+    }
+}